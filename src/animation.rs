@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use rug::{Assign, Complex, Float};
+
+use crate::math::{HighPrecisionState, PRECISION, target_iterations};
+
+/// How long an animated camera jump takes
+pub const JUMP_DURATION: Duration = Duration::from_millis(1000);
+
+// Past this depth the f32 shader runs out of range, so the autopilot starts over
+const AUTOPILOT_MAX_LOG_ZOOM: f64 = 30.0;
+// Candidate points checked when picking the next autopilot target
+const AUTOPILOT_SAMPLES: u32 = 64;
+
+/// Famous spots: name, center and log10 of the zoom. The demo visits them in order and
+/// the settings panel lists them as bookmarks.
+pub const LANDMARKS: [(&str, &str, &str, f64); 5] = [
+    (
+        "Seahorse valley",
+        "-0.743643887037151",
+        "0.131825904205330",
+        5.0,
+    ),
+    ("Elephant valley", "0.2925", "0.0149", 2.5),
+    ("Antenna minibrot", "-1.7548776662463", "0.0", 2.0), // Period 3
+    (
+        "Spiral near the top",
+        "-0.10109636384562",
+        "0.95628651080914",
+        4.0,
+    ),
+    ("Double spiral", "-0.77568377", "0.13646737", 6.0),
+];
+// Flights between demo spots are slower than jumps, so they read as a tour
+const DEMO_FLIGHT_DURATION: Duration = Duration::from_secs(4);
+// How long the demo zooms into each spot before moving on
+const DEMO_DIVE_DURATION: Duration = Duration::from_secs(8);
+
+/// How a camera flight speeds up and slows down.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Gentle start and stop
+    #[default]
+    EaseInOut,
+    /// Sharp acceleration and braking, most of the move happens mid-flight
+    Exponential,
+}
+
+impl Easing {
+    pub fn next(self) -> Self {
+        match self {
+            Easing::Linear => Easing::EaseInOut,
+            Easing::EaseInOut => Easing::Exponential,
+            Easing::Exponential => Easing::Linear,
+        }
+    }
+
+    /// Maps normalized time [0, 1] to eased progress [0, 1].
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => smoothstep(t),
+            Easing::Exponential => {
+                if t <= 0.0 {
+                    0.0
+                } else if t >= 1.0 {
+                    1.0
+                } else if t < 0.5 {
+                    0.5 * 2f64.powf(20.0 * t - 10.0)
+                } else {
+                    1.0 - 0.5 * 2f64.powf(10.0 - 20.0 * t)
+                }
+            }
+        }
+    }
+}
+
+/// An eased camera move between two views.
+/// Zoom is interpolated in log space. If the target is off-screen, the path
+/// zooms out until both ends are visible, then zooms back in.
+pub struct CameraFlight {
+    from: Complex,
+    delta: Complex, // Target minus start
+    from_log_zoom: f64,
+    to_log_zoom: f64,
+    peak_log_zoom: f64, // Most zoomed-out point of the path
+    started: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl CameraFlight {
+    pub fn new(
+        from: &Complex,
+        from_zoom: &Float,
+        to: &Complex,
+        to_zoom: &Float,
+        easing: Easing,
+    ) -> Self {
+        let delta = to.clone() - from;
+        let distance = Float::with_val(53, delta.abs_ref()).to_f64();
+
+        let from_log_zoom = from_zoom.clone().log10().to_f64();
+        let to_log_zoom = to_zoom.clone().log10().to_f64();
+
+        // The view spans 1/zoom vertically from the center, so both points fit at zoom <= 1/distance
+        let mut peak_log_zoom = from_log_zoom.min(to_log_zoom);
+        if distance > 0.0 {
+            peak_log_zoom = peak_log_zoom.min(-distance.log10());
+        }
+
+        Self {
+            from: from.clone(),
+            delta,
+            from_log_zoom,
+            to_log_zoom,
+            peak_log_zoom,
+            started: Instant::now(),
+            duration: JUMP_DURATION,
+            easing,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Returns the camera and zoom for the current time, and whether the flight is finished.
+    pub fn sample(&self) -> (Complex, Float, bool) {
+        let t = (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0);
+        let s = self.easing.apply(t);
+
+        let log_zoom = if self.peak_log_zoom < self.from_log_zoom.min(self.to_log_zoom) {
+            // Zoom out on the first half, back in on the second
+            if t < 0.5 {
+                lerp(
+                    self.from_log_zoom,
+                    self.peak_log_zoom,
+                    self.easing.apply(t * 2.0),
+                )
+            } else {
+                lerp(
+                    self.peak_log_zoom,
+                    self.to_log_zoom,
+                    self.easing.apply(t * 2.0 - 1.0),
+                )
+            }
+        } else {
+            lerp(self.from_log_zoom, self.to_log_zoom, s)
+        };
+
+        let mut camera = self.delta.clone();
+        camera *= s;
+        camera += &self.from;
+
+        let zoom = Float::with_val(PRECISION, log_zoom).exp10();
+
+        (camera, zoom, t >= 1.0)
+    }
+}
+
+/// Idle "screensaver" zoom that keeps diving toward detail on the set boundary.
+pub struct Autopilot {
+    target: Complex,
+    zoom_speed: f64,        // Zoom multiplier per second
+    retarget_log_zoom: f64, // Pick a fresh target once the zoom passes this depth
+    last_step: Instant,
+}
+
+impl Autopilot {
+    pub fn new(state: &HighPrecisionState, zoom_speed: f64) -> Self {
+        let mut autopilot = Self {
+            target: state.camera.clone(),
+            zoom_speed,
+            retarget_log_zoom: 0.0,
+            last_step: Instant::now(),
+        };
+        autopilot.retarget(state);
+        autopilot
+    }
+
+    /// Advances the camera and zoom by the time since the last step.
+    pub fn step(&mut self, state: &mut HighPrecisionState) {
+        // Clamp the step so a stalled frame doesn't cause a jump
+        let dt = self.last_step.elapsed().as_secs_f64().min(0.1);
+        self.last_step = Instant::now();
+
+        state.zoom *= self.zoom_speed.powf(dt);
+
+        // Ease the camera toward the target
+        let mut shift = self.target.clone() - &state.camera;
+        shift *= (dt * 2.0).min(1.0);
+        state.camera += &shift;
+
+        let log_zoom = state.zoom.clone().log10().to_f64();
+        if log_zoom > AUTOPILOT_MAX_LOG_ZOOM {
+            // Start over from the full view. Framed for a square, so it fits any landscape window.
+            let home = HighPrecisionState::with_default_view(1.0);
+            state.camera.assign(&home.camera);
+            state.zoom.assign(&home.zoom);
+            self.target.assign(&home.camera);
+            self.retarget(state);
+        } else if log_zoom >= self.retarget_log_zoom {
+            self.retarget(state);
+        }
+    }
+
+    fn retarget(&mut self, state: &HighPrecisionState) {
+        // Search around the previous target so the path keeps following the same feature
+        self.target = state.find_zoom_target(
+            &self.target,
+            &state.zoom,
+            target_iterations(&state.zoom),
+            AUTOPILOT_SAMPLES,
+        );
+        self.retarget_log_zoom = state.zoom.clone().log10().to_f64() + 1.0;
+    }
+}
+
+/// Attract mode shown on launch: flies through `LANDMARKS`, zooming into each one
+/// with the autopilot for a while before moving on.
+pub struct Demo {
+    waypoint: usize, // Index of the spot being flown to or explored
+    zoom_speed: f64,
+    phase: DemoPhase,
+}
+
+enum DemoPhase {
+    Flying(CameraFlight),
+    Diving {
+        autopilot: Autopilot,
+        started: Instant,
+    },
+}
+
+impl Demo {
+    pub fn new(state: &HighPrecisionState, zoom_speed: f64) -> Self {
+        Self {
+            waypoint: 0,
+            zoom_speed,
+            phase: DemoPhase::Flying(Self::flight_to(state, 0)),
+        }
+    }
+
+    /// Advances the camera and zoom along the tour.
+    pub fn step(&mut self, state: &mut HighPrecisionState) {
+        match &mut self.phase {
+            DemoPhase::Flying(flight) => {
+                let (camera, zoom, finished) = flight.sample();
+                state.camera.assign(&camera);
+                state.zoom = zoom;
+                if finished {
+                    self.phase = DemoPhase::Diving {
+                        autopilot: Autopilot::new(state, self.zoom_speed),
+                        started: Instant::now(),
+                    };
+                }
+            }
+            DemoPhase::Diving { autopilot, started } => {
+                autopilot.step(state);
+                if started.elapsed() >= DEMO_DIVE_DURATION {
+                    self.waypoint = (self.waypoint + 1) % LANDMARKS.len();
+                    self.phase = DemoPhase::Flying(Self::flight_to(state, self.waypoint));
+                }
+            }
+        }
+    }
+
+    fn flight_to(state: &HighPrecisionState, waypoint: usize) -> CameraFlight {
+        let (target, zoom) = landmark_view(waypoint);
+        CameraFlight::new(
+            &state.camera,
+            &state.zoom,
+            &target,
+            &zoom,
+            Easing::EaseInOut,
+        )
+        .with_duration(DEMO_FLIGHT_DURATION)
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Center and zoom of `LANDMARKS[index]`.
+pub fn landmark_view(index: usize) -> (Complex, Float) {
+    let (_, re, im, log_zoom) = LANDMARKS[index];
+    let parse = |value: &str| Float::with_val(PRECISION, Float::parse(value).unwrap());
+    let center = Complex::with_val(PRECISION, (parse(re), parse(im)));
+    let zoom = Float::with_val(PRECISION, log_zoom).exp10();
+    (center, zoom)
+}
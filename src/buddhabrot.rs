@@ -0,0 +1,218 @@
+use std::io::Write;
+
+use image::{Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::cli::{Args, BuddhabrotArgs};
+
+// Orbit points sampled by each shader invocation
+const SAMPLES_PER_INVOCATION: u32 = 64;
+
+// Must match @workgroup_size in buddhabrot.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+// Workgroups per dispatch. Batches stay short so the GPU driver doesn't time out.
+const WORKGROUPS_PER_BATCH: u32 = 256;
+
+// Spreads `--seed` values apart so neighbouring seeds don't share batch seeds (2^32 / phi)
+const SEED_STRIDE: u32 = 0x9E37_79B9;
+
+// Framing of the whole set: the middle of the image and its height in complex units
+const VIEW_CENTER: [f32; 2] = [-0.4, 0.0];
+const VIEW_HEIGHT: f32 = 3.0;
+
+// Share of lit pixels allowed to clip to white, so a few hot spots don't darken the rest
+const WHITE_POINT_CLIP: f64 = 0.001;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    seed: u32,
+    center: [f32; 2],
+    pixel_size: f32,
+    samples_per_invocation: u32,
+}
+
+/// Renders a Buddhabrot, the density of the orbits of escaping points, to a PNG.
+/// The orbits are traced in a compute shader that counts hits per pixel.
+/// Sample points are drawn from `global.seed`, so the image is reproducible.
+pub async fn run(args: &BuddhabrotArgs, global: &Args) -> Result<(), String> {
+    if args.width == 0 || args.height == 0 {
+        return Err("width and height must be greater than zero".to_string());
+    }
+
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .map_err(|e| format!("Failed to find an appropriate adapter: {}", e))?;
+    println!("Found adapter: {:?}", adapter.get_info().name);
+
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            required_limits: adapter.limits(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("Failed to create device: {}", e))?;
+
+    let pixel_count = args.width as u64 * args.height as u64;
+    let hits_size = pixel_count * std::mem::size_of::<u32>() as u64;
+    let limits = device.limits();
+    if hits_size > limits.max_storage_buffer_binding_size {
+        return Err(format!(
+            "{}x{} needs a {} byte hit buffer, the GPU allows {}",
+            args.width, args.height, hits_size, limits.max_storage_buffer_binding_size
+        ));
+    }
+
+    let mut params = Params {
+        width: args.width,
+        height: args.height,
+        max_iter: args.max_iter,
+        seed: 0,
+        center: VIEW_CENTER,
+        pixel_size: VIEW_HEIGHT / args.height as f32,
+        samples_per_invocation: SAMPLES_PER_INVOCATION,
+    };
+
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Buddhabrot Params Buffer"),
+        contents: bytemuck::cast_slice(&[params]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    // Zero-initialized by wgpu
+    let hits_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Buddhabrot Hits Buffer"),
+        size: hits_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Buddhabrot Readback Buffer"),
+        size: hits_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Buddhabrot Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/buddhabrot.wgsl").into()),
+    });
+    // The layout is inferred from the shader
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Buddhabrot Pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Buddhabrot Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hits_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let samples_per_batch = (WORKGROUPS_PER_BATCH * WORKGROUP_SIZE * SAMPLES_PER_INVOCATION) as u64;
+    let batches = args.samples.div_ceil(samples_per_batch).max(1);
+    println!(
+        "Tracing {} orbits in {} batches, up to {} iterations each",
+        batches * samples_per_batch,
+        batches,
+        args.max_iter
+    );
+
+    for batch in 0..batches {
+        // Seed 0 keeps the batch seeds images were rendered with before --seed existed
+        params.seed = global
+            .seed
+            .wrapping_mul(SEED_STRIDE)
+            .wrapping_add(batch as u32);
+        queue.write_buffer(&params_buffer, 0, bytemuck::cast_slice(&[params]));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Buddhabrot Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Buddhabrot Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(WORKGROUPS_PER_BATCH, 1, 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        // Waiting per batch keeps the queue short and gives a progress readout
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| format!("Failed to wait for the GPU: {}", e))?;
+        print!("\rBatch {}/{}", batch + 1, batches);
+        std::io::stdout().flush().ok();
+    }
+    println!();
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Buddhabrot Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(&hits_buffer, 0, &readback_buffer, 0, hits_size);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let buffer_slice = readback_buffer.slice(..);
+    buffer_slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Failed to map the hit buffer");
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .map_err(|e| format!("Failed to wait for the readback: {}", e))?;
+    let hits: Vec<u32> = bytemuck::cast_slice(&buffer_slice.get_mapped_range()).to_vec();
+    readback_buffer.unmap();
+
+    let image = colorize(&hits, args.width, args.height, args.exposure);
+    image
+        .save(&args.out)
+        .map_err(|e| format!("Failed to write {}: {}", args.out.display(), e))?;
+    println!("Rendered {}", args.out.display());
+
+    Ok(())
+}
+
+/// Maps hit counts to colors. Counts are normalized to a white point just below the
+/// maximum, square-rooted to lift the faint outer orbits, then scaled by `exposure`.
+fn colorize(hits: &[u32], width: u32, height: u32, exposure: f32) -> RgbaImage {
+    let mut lit: Vec<u32> = hits.iter().copied().filter(|&count| count > 0).collect();
+    let white_point = if lit.is_empty() {
+        1
+    } else {
+        lit.sort_unstable();
+        let index = ((lit.len() - 1) as f64 * (1.0 - WHITE_POINT_CLIP)) as usize;
+        lit[index].max(1)
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let count = hits[(y * width + x) as usize];
+        let value = ((count as f32 / white_point as f32).sqrt() * exposure).min(1.0);
+        // Blue-white ramp: blue comes up first, red last
+        let rgb = [value.powf(1.6), value.powf(1.2), value.powf(0.8)];
+        Rgba([
+            (rgb[0] * 255.0) as u8,
+            (rgb[1] * 255.0) as u8,
+            (rgb[2] * 255.0) as u8,
+            255,
+        ])
+    })
+}
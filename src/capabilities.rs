@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// What the GPU can do, for the features that depend on it. Shown at startup and on
+/// the diagnostics overlay so it's clear why something isn't available.
+pub struct GpuCapabilities {
+    pub adapter_name: String,
+    pub backend: wgpu::Backend,
+    pub max_storage_buffer_binding_size: u64,
+    pub max_texture_dimension_2d: u32,
+    pub timestamp_queries: bool,
+    pub fragment_storage: FragmentStorage,
+}
+
+/// Storage buffer access from fragment shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentStorage {
+    Unsupported,
+    ReadOnly,
+    ReadWrite,
+}
+
+impl GpuCapabilities {
+    pub fn from_adapter(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+        let limits = adapter.limits();
+
+        // WebGL2 and some old GL drivers have no storage buffers at all
+        let fragment_storage = if limits.max_storage_buffers_per_shader_stage == 0 {
+            FragmentStorage::Unsupported
+        } else if adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE)
+        {
+            FragmentStorage::ReadWrite
+        } else {
+            FragmentStorage::ReadOnly
+        };
+
+        Self {
+            adapter_name: info.name,
+            backend: info.backend,
+            max_storage_buffer_binding_size: limits.max_storage_buffer_binding_size,
+            max_texture_dimension_2d: limits.max_texture_dimension_2d,
+            timestamp_queries: adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY),
+            fragment_storage,
+        }
+    }
+}
+
+impl fmt::Display for GpuCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fragment_storage = match self.fragment_storage {
+            FragmentStorage::Unsupported => "no",
+            FragmentStorage::ReadOnly => "read-only",
+            FragmentStorage::ReadWrite => "read/write",
+        };
+        write!(
+            f,
+            "Adapter: {} ({:?})\nMax storage buffer: {} MiB\nMax texture size: {}\nTimestamp queries: {}\nFragment storage buffers: {}",
+            self.adapter_name,
+            self.backend,
+            self.max_storage_buffer_binding_size / (1024 * 1024),
+            self.max_texture_dimension_2d,
+            if self.timestamp_queries { "yes" } else { "no" },
+            fragment_storage
+        )
+    }
+}
@@ -0,0 +1,675 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::Easing,
+    math::{MAX_ITER, MAX_ORBIT_PRECISION, OCEAN_THRESHOLD, PRECISION},
+    primitives::{
+        COLOR_SCALE_LINEAR, COLOR_SCALE_LOG, COLOR_SCALE_POWER, COLOR_SCALE_SQRT,
+        GLITCH_MODE_COLOR, GLITCH_MODE_FILL, GLITCH_MODE_REBASE, GLITCH_MODE_SHOW, MAX_AA_SAMPLES,
+        PALETTE_BLUE, PALETTE_FIRE, PALETTE_GRAYSCALE, PALETTE_RAINBOW, PALETTE_ULTRA_FRACTAL,
+    },
+};
+
+#[derive(Parser, Debug, Clone, Default)]
+#[command(version, about = "GPU-accelerated Mandelbrot explorer")]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Resolution of exported images as WIDTHxHEIGHT (e.g. 3840x2160).
+    /// Defaults to the current window size.
+    #[arg(long, value_parser = parse_size)]
+    pub export_size: Option<(u32, u32)>,
+
+    /// Supersample E key exports: render at N times the export size per axis and average
+    /// each NxN block down. Needs N^2 times the GPU memory of a plain export.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub export_aa: u32,
+
+    /// Bits per channel of exported PNGs (E key and `render`). 16 avoids banding in
+    /// smooth gradients at twice the file size.
+    #[arg(long, value_enum, default_value_t = BitDepth::Eight, global = true)]
+    pub bit_depth: BitDepth,
+
+    /// Custom WGSL shader to render with. It must keep the built-in shader's
+    /// entry points and bindings. Falls back to the built-in shader on errors.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub shader: Option<PathBuf>,
+
+    /// Start at the view stored in a PNG exported by this program
+    #[arg(long, value_name = "PATH")]
+    pub from_image: Option<PathBuf>,
+
+    /// Load a render recipe (TOML, see the README): view, iterations, precision, coloring,
+    /// anti-aliasing and, for `render`, the output. Its settings win over the matching flags.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub recipe: Option<PathBuf>,
+
+    /// Pin the iteration count from the first frame instead of the zoom-based budget.
+    /// Can go above the usual ceiling, as far as the GPU's buffer limits allow.
+    #[arg(long, value_name = "N")]
+    pub max_iter: Option<u32>,
+
+    /// Most iterations the zoom-based budget may reach. Larger caps let very deep views
+    /// resolve, clamped to what the GPU's buffers can hold.
+    #[arg(long, value_name = "N", default_value_t = MAX_ITER)]
+    pub iteration_cap: u32,
+
+    /// Real part of the starting center, as text to keep full precision. Wins over
+    /// `--from-image` and `--recipe`, a missing part keeps theirs or the default.
+    #[arg(long, allow_hyphen_values = true)]
+    pub re: Option<String>,
+
+    /// Imaginary part of the starting center
+    #[arg(long, allow_hyphen_values = true)]
+    pub im: Option<String>,
+
+    /// Starting zoom (1 shows a height of 2), e.g. 1e12
+    #[arg(long)]
+    pub zoom: Option<String>,
+
+    /// How the window is composited. The multiplied modes let the desktop show
+    /// through the interior of the set, where the compositor supports it.
+    #[arg(long, value_enum, default_value_t = AlphaMode::Opaque)]
+    pub alpha_mode: AlphaMode,
+
+    /// Open the window without a title bar or borders. Drag the top edge to move it.
+    #[arg(long)]
+    pub frameless: bool,
+
+    /// Teleport to jump targets instead of animating the camera
+    #[arg(long)]
+    pub instant_jumps: bool,
+
+    /// Gradient the escape counts are colored with. Shift+P cycles through them.
+    #[arg(long, value_enum, default_value_t = Palette::Rainbow, global = true)]
+    pub palette: Palette,
+
+    /// How iteration counts map to palette position
+    #[arg(long, value_enum, default_value_t = ColorScale::Linear, global = true)]
+    pub color_scale: ColorScale,
+
+    /// Exponent for `--color-scale power`. Below 1 spreads colors over low iteration counts.
+    #[arg(long, default_value_t = 1.0, global = true)]
+    pub color_exponent: f32,
+
+    /// Color for points that escape right away (far outside the set), as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_hex_color, global = true)]
+    pub exterior_color: Option<[f32; 3]>,
+
+    /// Iterations over which the exterior color fades into the palette
+    #[arg(long, value_name = "ITERATIONS", default_value_t = 0.0, global = true)]
+    pub exterior_ramp: f32,
+
+    /// What to draw where perturbation breaks down, until a better reference clears it up
+    #[arg(long, value_enum, default_value_t = Glitches::Show, global = true)]
+    pub glitches: Glitches,
+
+    /// Color for `--glitches color`, as RRGGBB hex
+    #[arg(
+        long,
+        value_name = "RRGGBB",
+        default_value = "ff00ff",
+        value_parser = parse_hex_color,
+        global = true
+    )]
+    pub glitch_color: [f32; 3],
+
+    /// Stripes per turn for stripe average coloring (the k in sin(k*arg(z)))
+    #[arg(long, value_name = "K", default_value_t = 5.0, global = true)]
+    pub stripe_frequency: f32,
+
+    /// Color escaping points with this image, at the spot where their orbit came
+    /// closest to --trap-center. Starts in the image trap coloring.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub trap_image: Option<PathBuf>,
+
+    /// Middle of the trap image in the complex plane
+    #[arg(
+        long,
+        value_name = "RE,IM",
+        default_value = "0,0",
+        value_parser = parse_point,
+        allow_hyphen_values = true,
+        global = true
+    )]
+    pub trap_center: [f32; 2],
+
+    /// Height of the trap image in complex units, the width follows its aspect
+    #[arg(long, value_name = "SIZE", default_value_t = 1.0, value_parser = parse_trap_size, global = true)]
+    pub trap_size: f32,
+
+    /// Start with auto exposure on: the coloring is renormalized to the escape counts in
+    /// view whenever the iteration count changes, so deep zooms don't wash out
+    #[arg(long)]
+    pub auto_exposure: bool,
+
+    /// Smooth the edge of the set by blending boundary pixels with the interior
+    /// based on the distance estimate, a cheap alternative to supersampling
+    #[arg(long, global = true)]
+    pub boundary_aa: bool,
+
+    /// Color by the integer escape count instead of the smooth (continuous) one, which
+    /// shows hard bands between iterations. Shift+D switches back and forth.
+    #[arg(long, global = true)]
+    pub banded: bool,
+
+    /// Skip the reference search when the camera escapes in fewer iterations than this,
+    /// out in the exterior where every pixel is cheap. 0 always searches.
+    #[arg(long, value_name = "ITERATIONS", default_value_t = OCEAN_THRESHOLD, global = true)]
+    pub ocean_threshold: u32,
+
+    /// Format the F key saves the current location in, for other fractal programs
+    #[arg(long, value_enum, default_value_t = LocationFormat::Kfr)]
+    pub location_format: LocationFormat,
+
+    /// JSON file Ctrl+S saves the view's parameters to and Ctrl+O loads them from
+    #[arg(long, value_name = "PATH", default_value = "view.json")]
+    pub view_file: PathBuf,
+
+    /// Supersample in the shader with an NxN grid per pixel. Costs N^2 times the GPU work.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        global = true,
+        value_parser = clap::value_parser!(u32).range(1..=MAX_AA_SAMPLES as i64)
+    )]
+    pub shader_aa: u32,
+
+    /// Most bits the reference orbit may be computed with when raising its precision to
+    /// clear glitches. Past the zoom this supports the view turns blocky instead of slow.
+    #[arg(
+        long,
+        value_name = "BITS",
+        default_value_t = MAX_ORBIT_PRECISION,
+        global = true,
+        value_parser = clap::value_parser!(u32).range(PRECISION as i64..)
+    )]
+    pub max_precision: u32,
+
+    /// Iterate the shader's perturbation deltas in double-single (two f32s, about 48 bits)
+    /// against a hi/lo copy of the reference orbit. Slower on the GPU and the CPU, it cuts
+    /// rounding noise but not the f32 exponent limit on depth. W toggles it.
+    #[arg(long, global = true)]
+    pub double_single: bool,
+
+    /// Rotate the view counterclockwise by this many degrees
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 0.0,
+        allow_hyphen_values = true,
+        global = true
+    )]
+    pub rotation: f64,
+
+    /// Seed for randomized sampling (the Buddhabrot's sample points). The same seed
+    /// gives the same image, a different one a new draw of samples.
+    #[arg(long, default_value_t = 0, global = true)]
+    pub seed: u32,
+
+    /// Draw the window with 4x MSAA. Smooths the overlay lines and pane edges, the fractal
+    /// itself is still shaded once per pixel (see --shader-aa). A toggles it.
+    #[arg(long)]
+    pub msaa: bool,
+
+    /// Draw the first frame after the view moves at a fraction of the iterations, then
+    /// refine it over the next frames while the view holds still. Keeps navigation smooth
+    /// at high iteration counts, exports are always drawn in full.
+    #[arg(long)]
+    pub progressive: bool,
+
+    /// Render the fractal at this fraction of the window resolution and stretch it to fit.
+    /// Below 1 trades sharpness for speed, above 1 supersamples.
+    #[arg(long, value_name = "SCALE", default_value_t = 1.0, value_parser = parse_render_scale)]
+    pub render_scale: f32,
+
+    /// Key that cycles the render scale through 0.25, 0.5, 1 and 2.
+    /// Takes precedence over the built-in binding of the same key.
+    #[arg(long, value_name = "KEY", default_value_t = 'Q', value_parser = parse_key)]
+    pub render_scale_key: char,
+
+    /// Period of the minibrots the U key looks for, instead of the lowest period nearby
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub nucleus_period: Option<u32>,
+
+    /// How fast held arrow keys pan, in view widths per second
+    #[arg(long, value_name = "WIDTHS", default_value_t = 0.5, value_parser = parse_pan_speed)]
+    pub pan_speed: f64,
+
+    /// Point that stays fixed while scrolling
+    #[arg(long, value_enum, default_value_t = ZoomAnchor::Cursor)]
+    pub zoom_anchor: ZoomAnchor,
+
+    /// Acceleration curve of animated jumps and tour transitions
+    #[arg(long, value_enum, default_value_t = Easing::EaseInOut, global = true)]
+    pub easing: Easing,
+
+    /// Cap the frame rate to save power, sleeping out the rest of each frame
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_fps: Option<u32>,
+
+    /// Start an automatic zoom after this many seconds without input
+    #[arg(long, value_name = "SECONDS")]
+    pub idle_timeout: Option<u64>,
+
+    /// Hide the mouse cursor after this many seconds without mouse movement, for
+    /// presentations. Moving the mouse brings it back.
+    #[arg(long, value_name = "SECONDS")]
+    pub hide_cursor: Option<u64>,
+
+    /// Zoom multiplier per second while the idle autopilot is running
+    #[arg(long, default_value_t = 1.5)]
+    pub autopilot_speed: f64,
+
+    /// Don't start the demo tour on launch. The demo only runs without --from-image.
+    #[arg(long)]
+    pub no_demo: bool,
+
+    /// While a timelapse records a view that hasn't moved for this long, look for a better
+    /// reference this often, with more samples every time. 0 turns it off.
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub reference_refresh: u64,
+
+    /// Record a timelapse, saving a frame every this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub timelapse_interval: Option<u64>,
+
+    /// Record a timelapse, saving a frame every time the zoom changes by this many decades
+    #[arg(long, value_name = "DECADES")]
+    pub timelapse_decades: Option<f64>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScale {
+    #[default]
+    Linear,
+    Log,
+    Sqrt,
+    /// Raise the iteration count to `--color-exponent`
+    Power,
+}
+
+impl ColorScale {
+    /// The matching `Uniforms::color_scale_mode` value
+    pub fn uniform_value(self) -> u32 {
+        match self {
+            ColorScale::Linear => COLOR_SCALE_LINEAR,
+            ColorScale::Log => COLOR_SCALE_LOG,
+            ColorScale::Sqrt => COLOR_SCALE_SQRT,
+            ColorScale::Power => COLOR_SCALE_POWER,
+        }
+    }
+
+    pub fn from_uniform_value(value: u32) -> Self {
+        match value {
+            COLOR_SCALE_LOG => ColorScale::Log,
+            COLOR_SCALE_SQRT => ColorScale::Sqrt,
+            COLOR_SCALE_POWER => ColorScale::Power,
+            _ => ColorScale::Linear,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    /// Phase-shifted sines through every hue
+    #[default]
+    Rainbow,
+    /// Deep blue, white and orange, like Ultra Fractal's default gradient
+    UltraFractal,
+    Fire,
+    Grayscale,
+    Blue,
+}
+
+impl Palette {
+    /// The matching `Uniforms::palette_id` value
+    pub fn uniform_value(self) -> u32 {
+        match self {
+            Palette::Rainbow => PALETTE_RAINBOW,
+            Palette::UltraFractal => PALETTE_ULTRA_FRACTAL,
+            Palette::Fire => PALETTE_FIRE,
+            Palette::Grayscale => PALETTE_GRAYSCALE,
+            Palette::Blue => PALETTE_BLUE,
+        }
+    }
+
+    pub fn from_uniform_value(value: u32) -> Self {
+        match value {
+            PALETTE_ULTRA_FRACTAL => Palette::UltraFractal,
+            PALETTE_FIRE => Palette::Fire,
+            PALETTE_GRAYSCALE => Palette::Grayscale,
+            PALETTE_BLUE => Palette::Blue,
+            _ => Palette::Rainbow,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Glitches {
+    /// Draw whatever the iteration produced
+    #[default]
+    Show,
+    /// Paint glitched pixels in `--glitch-color`
+    Color,
+    /// Fill glitched pixels in from their neighbors on screen. Exports show them as they are.
+    Fill,
+    /// Re-iterate glitched pixels from the start of the reference orbit, which repairs them
+    Rebase,
+}
+
+impl Glitches {
+    /// The matching `Uniforms::glitch_mode` value
+    pub fn uniform_value(self) -> u32 {
+        match self {
+            Glitches::Show => GLITCH_MODE_SHOW,
+            Glitches::Color => GLITCH_MODE_COLOR,
+            Glitches::Fill => GLITCH_MODE_FILL,
+            Glitches::Rebase => GLITCH_MODE_REBASE,
+        }
+    }
+
+    pub fn from_uniform_value(value: u32) -> Self {
+        match value {
+            GLITCH_MODE_COLOR => Glitches::Color,
+            GLITCH_MODE_FILL => Glitches::Fill,
+            GLITCH_MODE_REBASE => Glitches::Rebase,
+            _ => Glitches::Show,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Use whatever the surface prefers (usually opaque)
+    #[default]
+    Opaque,
+    PreMultiplied,
+    PostMultiplied,
+}
+
+impl AlphaMode {
+    /// The wgpu mode to request, or None for the surface default
+    pub fn composite_mode(self) -> Option<wgpu::CompositeAlphaMode> {
+        match self {
+            AlphaMode::Opaque => None,
+            AlphaMode::PreMultiplied => Some(wgpu::CompositeAlphaMode::PreMultiplied),
+            AlphaMode::PostMultiplied => Some(wgpu::CompositeAlphaMode::PostMultiplied),
+        }
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self != AlphaMode::Opaque
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LocationFormat {
+    /// Kalles Fraktaler location (.kfr)
+    #[default]
+    Kfr,
+    /// Ultra Fractal parameter block (.upr)
+    UltraFractal,
+}
+
+impl LocationFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            LocationFormat::Kfr => "kfr",
+            LocationFormat::UltraFractal => "upr",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resample {
+    /// Plain average of each block of samples. Fast, slightly soft.
+    #[default]
+    Box,
+    /// Linear weights reaching into neighbouring blocks. Smoother, softer still.
+    Triangle,
+    /// Lanczos3. Sharpest, but can ring around hard edges.
+    Lanczos,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZoomAnchor {
+    /// Zoom toward the point under the mouse
+    #[default]
+    Cursor,
+    /// Scale about the center of the window
+    Center,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Render a single image without opening a window
+    Render(RenderArgs),
+    /// Render a guided tour through a script of keyframes as numbered frames
+    Tour(TourArgs),
+    /// Render a Buddhabrot: how often the orbits of escaping points visit each pixel
+    Buddhabrot(BuddhabrotArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RenderArgs {
+    /// Real part of the view center. Given as text to keep full precision.
+    #[arg(long, default_value = "0", allow_hyphen_values = true)]
+    pub re: String,
+
+    /// Imaginary part of the view center
+    #[arg(long, default_value = "0", allow_hyphen_values = true)]
+    pub im: String,
+
+    /// Zoom factor (1 shows the full set), e.g. 1e12
+    #[arg(long, default_value = "1")]
+    pub zoom: String,
+
+    /// Output path. A `.exr` extension writes linear float OpenEXR instead of a PNG.
+    #[arg(long, value_name = "PATH")]
+    pub out: PathBuf,
+
+    #[arg(long, default_value_t = 1920)]
+    pub width: u32,
+
+    #[arg(long, default_value_t = 1080)]
+    pub height: u32,
+
+    /// Iteration limit. Defaults to the zoom-based budget used interactively.
+    #[arg(long)]
+    pub max_iter: Option<u32>,
+
+    /// Supersampling factor per axis (2 renders 4 samples per pixel)
+    #[arg(long, default_value_t = 1)]
+    pub aa: u32,
+
+    /// Filter used to downsample `--aa` renders to the output size
+    #[arg(long, value_enum, default_value_t = Resample::Box)]
+    pub resample: Resample,
+
+    /// Also write the escape count of every pixel to a NumPy .npy file, a
+    /// (height, width) u32 array with 0 for points that never escaped
+    #[arg(long, value_name = "PATH")]
+    pub export_raw: Option<PathBuf>,
+
+    /// With `--export-raw`, write a (height, width, 2) f32 array instead, holding the
+    /// escape count and the continuous (smooth) count
+    #[arg(long, requires = "export_raw")]
+    pub raw_smooth: bool,
+
+    /// Also save the render's recipe (TOML), which `--recipe` turns back into the same image
+    #[arg(long, value_name = "PATH")]
+    pub save_recipe: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TourArgs {
+    /// Keyframe script, one `key=value` line per stop (see the README)
+    pub script: PathBuf,
+
+    /// Folder for the numbered frames
+    #[arg(long, value_name = "DIR", default_value = "tour")]
+    pub out_dir: PathBuf,
+
+    /// Encode straight to a video with ffmpeg instead of saving frames, e.g. tour.mp4
+    /// or tour.gif. Falls back to frames in `--out-dir` if ffmpeg isn't available.
+    #[arg(long, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 1920)]
+    pub width: u32,
+
+    #[arg(long, default_value_t = 1080)]
+    pub height: u32,
+
+    #[arg(long, default_value_t = 30)]
+    pub fps: u32,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BuddhabrotArgs {
+    /// Output PNG path
+    #[arg(long, value_name = "PATH")]
+    pub out: PathBuf,
+
+    #[arg(long, default_value_t = 1024)]
+    pub width: u32,
+
+    #[arg(long, default_value_t = 1024)]
+    pub height: u32,
+
+    /// Number of random points whose orbits are traced. More samples means less noise.
+    #[arg(long, default_value_t = 100_000_000)]
+    pub samples: u64,
+
+    /// Longest orbit traced. Low limits give the classic nebula look, high ones show
+    /// the thin orbits of points near the boundary.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_iter: u32,
+
+    /// Brightness multiplier applied after normalizing the hit counts
+    #[arg(long, default_value_t = 1.0)]
+    pub exposure: f32,
+}
+
+/// Parses a "WIDTHxHEIGHT" string into a pair of non-zero dimensions.
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+
+    let width: u32 = w
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid width '{}'", w))?;
+    let height: u32 = h
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid height '{}'", h))?;
+    if width == 0 || height == 0 {
+        return Err("dimensions must be greater than zero".to_string());
+    }
+
+    Ok((width, height))
+}
+
+fn parse_render_scale(s: &str) -> Result<f32, String> {
+    let scale: f32 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid scale '{}'", s))?;
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err(format!("scale must be positive, got {}", scale));
+    }
+    Ok(scale)
+}
+
+fn parse_pan_speed(s: &str) -> Result<f64, String> {
+    let speed: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid speed '{}'", s))?;
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(format!("speed must be positive, got {}", speed));
+    }
+    Ok(speed)
+}
+
+fn parse_trap_size(s: &str) -> Result<f32, String> {
+    let size: f32 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    if !(size > 0.0 && size.is_finite()) {
+        return Err(format!("size must be positive, got {}", size));
+    }
+    Ok(size)
+}
+
+/// Parses an "RE,IM" pair of coordinates.
+fn parse_point(s: &str) -> Result<[f32; 2], String> {
+    let (re, im) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected RE,IM, got '{}'", s))?;
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<f32>()
+            .map_err(|_| format!("invalid coordinate '{}'", part))
+    };
+    Ok([parse(re)?, parse(im)?])
+}
+
+fn parse_key(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Ok(c.to_ascii_uppercase()),
+        _ => Err(format!("expected a single letter or digit, got '{}'", s)),
+    }
+}
+
+/// Parses an "RRGGBB" hex color (with optional leading '#') into linear RGB,
+/// since the shader output is converted to sRGB by the surface.
+pub fn parse_hex_color(s: &str) -> Result<[f32; 3], String> {
+    let hex = s.trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(format!("expected RRGGBB, got '{}'", s));
+    }
+
+    let mut color = [0.0; 3];
+    for (i, channel) in color.iter_mut().enumerate() {
+        let value = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("invalid hex color '{}'", s))?;
+        *channel = (value as f32 / 255.0).powf(2.2);
+    }
+
+    Ok(color)
+}
+
+/// Formats a linear RGB color as "RRGGBB", the inverse of `parse_hex_color`.
+pub fn format_hex_color(color: [f32; 3]) -> String {
+    color
+        .iter()
+        .map(|channel| {
+            let value = (channel.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8;
+            format!("{:02x}", value)
+        })
+        .collect()
+}
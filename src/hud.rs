@@ -0,0 +1,156 @@
+use glyphon::{
+    Attrs, Buffer, Cache, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache,
+    TextArea, TextAtlas, TextBounds, TextRenderer, Viewport,
+};
+
+// Font size in logical pixels, before DPI and window scaling
+const HUD_FONT_SIZE: f32 = 14.0;
+// Distance from the top-left corner in logical pixels
+const HUD_MARGIN: f32 = 8.0;
+// Windows taller than this (logical pixels) get proportionally bigger text, up to HUD_MAX_GROWTH
+const HUD_REFERENCE_HEIGHT: f32 = 720.0;
+const HUD_MAX_GROWTH: f32 = 1.5;
+
+/// Antialiased text overlay drawn in its own pass on top of the fractal.
+/// Text is white with a dark outline so it stays readable on any palette.
+pub struct Hud {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    viewport: Viewport,
+    atlas: TextAtlas,
+    renderer: TextRenderer,
+    buffer: Buffer,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let mut font_system = FontSystem::new();
+        let swash_cache = SwashCache::new();
+        let cache = Cache::new(device);
+        let viewport = Viewport::new(device, &cache);
+        let mut atlas = TextAtlas::new(device, queue, &cache, format);
+        let renderer =
+            TextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+        let buffer = Buffer::new(
+            &mut font_system,
+            Metrics::new(HUD_FONT_SIZE, HUD_FONT_SIZE * 1.25),
+        );
+
+        Self {
+            font_system,
+            swash_cache,
+            viewport,
+            atlas,
+            renderer,
+            buffer,
+        }
+    }
+
+    /// Lays out `text` for the current window and uploads any new glyphs to the atlas.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text: &str,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+    ) {
+        // Scale with DPI, then grow a little on big windows so it's still legible at 4K
+        let logical_height = height as f32 / scale_factor;
+        let growth = (logical_height / HUD_REFERENCE_HEIGHT).clamp(1.0, HUD_MAX_GROWTH);
+        let font_size = HUD_FONT_SIZE * scale_factor * growth;
+
+        self.buffer.set_metrics(
+            &mut self.font_system,
+            Metrics::new(font_size, font_size * 1.25),
+        );
+        self.buffer.set_size(
+            &mut self.font_system,
+            Some(width as f32),
+            Some(height as f32),
+        );
+        self.buffer.set_text(
+            &mut self.font_system,
+            text,
+            &Attrs::new().family(Family::Monospace),
+            Shaping::Basic,
+            None,
+        );
+        self.buffer.shape_until_scroll(&mut self.font_system, false);
+
+        self.viewport.update(queue, Resolution { width, height });
+
+        let margin = HUD_MARGIN * scale_factor;
+        let outline = (font_size / HUD_FONT_SIZE).max(1.0);
+        let bounds = TextBounds {
+            left: 0,
+            top: 0,
+            right: width as i32,
+            bottom: height as i32,
+        };
+
+        // Dark copies shifted in each direction form the outline, the white text goes last
+        let offsets = [
+            (-outline, 0.0, Color::rgba(0, 0, 0, 200)),
+            (outline, 0.0, Color::rgba(0, 0, 0, 200)),
+            (0.0, -outline, Color::rgba(0, 0, 0, 200)),
+            (0.0, outline, Color::rgba(0, 0, 0, 200)),
+            (0.0, 0.0, Color::rgb(255, 255, 255)),
+        ];
+        let text_areas = offsets.map(|(dx, dy, color)| TextArea {
+            buffer: &self.buffer,
+            left: margin + dx,
+            top: margin + dy,
+            scale: 1.0,
+            bounds,
+            default_color: color,
+            custom_glyphs: &[],
+        });
+
+        if let Err(e) = self.renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            &self.viewport,
+            text_areas,
+            &mut self.swash_cache,
+        ) {
+            eprintln!("Failed to prepare HUD text: {}", e);
+        }
+    }
+
+    /// Draws the prepared text over `view`, keeping what's already there.
+    pub fn render(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("HUD Pass"),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                multiview_mask: None,
+
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            if let Err(e) = self
+                .renderer
+                .render(&self.atlas, &self.viewport, &mut render_pass)
+            {
+                eprintln!("Failed to render HUD text: {}", e);
+            }
+        }
+
+        // Drop glyphs that weren't used this frame
+        self.atlas.trim();
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+// The preview frames the whole Julia set, which always fits within |z| <= 2
+pub const JULIA_ZOOM: f64 = 0.55;
+pub const JULIA_ASPECT: f64 = 1.0;
+// Same budget as the minimap, the preview is small and has to keep up with the mouse
+pub const JULIA_MAX_ITER: u32 = 256;
+// Shortest time between preview updates while the cursor moves
+pub const JULIA_UPDATE_INTERVAL: Duration = Duration::from_millis(33);
+
+// Preview height as a fraction of the window height
+const JULIA_HEIGHT_FRACTION: f32 = 0.35;
+// Gap between the preview and the window edges, in pixels
+const JULIA_MARGIN: f32 = 10.0;
+
+/// Pixel rectangle [x, y, width, height] of the Julia preview in the bottom-left corner.
+pub fn julia_rect(width: u32, height: u32) -> [f32; 4] {
+    let h = height as f32 * JULIA_HEIGHT_FRACTION;
+    let w = (h * JULIA_ASPECT as f32).min(width as f32 - 2.0 * JULIA_MARGIN);
+
+    [JULIA_MARGIN, height as f32 - h - JULIA_MARGIN, w, h]
+}
+
+/// Whether a window pixel lies inside a pixel rectangle, like the Julia view.
+pub fn in_rect(rect: [f32; 4], x: f64, y: f64) -> bool {
+    let [rx, ry, rw, rh] = rect.map(|v| v as f64);
+    x >= rx && x <= rx + rw && y >= ry && y <= ry + rh
+}
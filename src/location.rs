@@ -0,0 +1,42 @@
+use rug::Float;
+
+use crate::{cli::LocationFormat, wgpu::WgpuState};
+
+/// The current view as a location file for another fractal program.
+/// `width` and `height` are only used where the format stores an image size.
+pub fn format_location(
+    state: &WgpuState<'_>,
+    format: LocationFormat,
+    width: u32,
+    height: u32,
+) -> String {
+    let fractal_state = &state.uniform_data.fractal_state;
+    let re = fractal_state.camera.real().to_string_radix(10, None);
+    let im = fractal_state.camera.imag().to_string_radix(10, None);
+    let iterations = state.uniform_data.uniforms.max_iter;
+
+    // Our zoom 1 spans 2 units vertically, both formats show 4 units (radius 2) at 1
+    let magnification = Float::with_val(fractal_state.zoom.prec(), &fractal_state.zoom * 2u32);
+    let magnification = magnification.to_string_radix(10, Some(20));
+
+    match format {
+        LocationFormat::Kfr => format!(
+            "Re: {}\r\nIm: {}\r\nZoom: {}\r\nIterations: {}\r\n",
+            re, im, magnification, iterations
+        ),
+        LocationFormat::UltraFractal => format!(
+            "mandelbrot_adaptive {{\n\
+             fractal:\n  \
+             title=\"mandelbrot_adaptive\" width={} height={} layers=1\n\
+             layer:\n  \
+             method=multipass caption=\"Background\" opacity=100\n\
+             mapping:\n  \
+             center={}/{} magn={}\n\
+             formula:\n  \
+             maxiter={} filename=\"Standard.ufm\" entry=\"Mandelbrot\"\n  \
+             p_start=0/0 p_power=2/0 p_bailout=4\n\
+             }}\n",
+            width, height, re, im, magnification, iterations
+        ),
+    }
+}
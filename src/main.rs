@@ -1,25 +1,86 @@
+use std::process::ExitCode;
+
+use clap::Parser;
 use winit::event_loop::{ControlFlow, EventLoop};
 
-use crate::window::App;
+use crate::{
+    cli::{Args, Command},
+    window::App,
+};
 
+mod animation;
+mod buddhabrot;
+mod capabilities;
+mod cli;
+mod hud;
+mod julia;
+mod location;
+mod metadata;
+mod minimap;
+mod npy;
+mod orbit_worker;
+mod panel;
+mod precision;
 mod primitives;
+mod recipe;
+mod render;
+mod scale;
+mod timelapse;
+mod tour;
+mod trap;
+mod video;
+mod view_params;
 mod wgpu;
 mod window;
 mod math;
 
-fn main() {
+fn main() -> ExitCode {
     env_logger::init();
 
-    pollster::block_on(run());
+    let args = Args::parse();
+
+    match &args.command {
+        Some(Command::Render(render_args)) => {
+            match pollster::block_on(render::run(render_args, &args)) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Render failed: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::Tour(tour_args)) => {
+            match pollster::block_on(tour::run(tour_args, &args)) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Tour failed: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::Buddhabrot(buddhabrot_args)) => {
+            match pollster::block_on(buddhabrot::run(buddhabrot_args, &args)) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Buddhabrot failed: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        None => {
+            pollster::block_on(run(args));
+            ExitCode::SUCCESS
+        }
+    }
 }
 
-async fn run() {
+async fn run(args: Args) {
     // Setup the event loop
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
     // Create our app state
-    let mut app = App::default();
+    let mut app = App::new(args);
 
     event_loop.run_app(&mut app).unwrap();
 }
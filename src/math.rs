@@ -1,14 +1,110 @@
-use std::{f32::consts::PI, ops::AddAssign};
+use std::{
+    f32::consts::PI,
+    ops::{AddAssign, Range},
+};
 
-use rug::{Complex, Float};
+use rayon::prelude::*;
+use rug::{Assign, Complex, Float};
 
-const PRECISION: u32 = 128;
+use crate::{
+    precision::{F64_MAX_LOG_ZOOM, F64Math, OrbitMath, PrecisionMode, RugMath},
+    primitives::{BlaStep, FRACTAL_MODE_JULIA, FRACTAL_MODE_MANDELBROT},
+    render::parse_float,
+};
+
+pub const PRECISION: u32 = 128;
+// Bits it takes to tell apart two points one decade of zoom closer together, log2(10)
+pub const BITS_PER_DECADE: f64 = 3.33;
+// Bits kept on top of what the zoom depth needs, for the pixels between decades
+const PRECISION_MARGIN: u32 = 64;
+// The view precision grows in steps this big, so it isn't reallocated every decade
+const PRECISION_STEP: u32 = 64;
 pub const MAX_ITER: u32 = 50000;
 
+// A perturbed point is glitched when |Z + dz| collapses below this fraction of |Z| (squared)
+const GLITCH_TOLERANCE: f32 = 1e-6;
+// Side length of the sample grid used to estimate glitch coverage
+const GLITCH_SAMPLE_GRID: u32 = 8;
+// Side length of the sample grid auto exposure measures escape counts on
+const EXPOSURE_SAMPLE_GRID: u32 = 16;
+// Relative error the series approximation may leave in a pixel's delta
+const SERIES_TOLERANCE: f64 = 1e-6;
+// Points on the rim of the view where the series is checked against direct iteration
+const SERIES_PROBES: u32 = 16;
+// Relative error a BLA step may leave in dz, 2^-24, about what f32 rounding leaves anyway
+const BLA_TOLERANCE: f64 = 5.96e-8;
+// Side length of the sample grid the BLA skip ratio is measured on
+const BLA_SAMPLE_GRID: u32 = 8;
+// Above this glitched fraction the orbit is recomputed at a higher precision
+pub const MAX_GLITCH_FRACTION: f32 = 0.1;
+// Upper bound for precision escalation of the reference orbit
+pub const MAX_ORBIT_PRECISION: u32 = 1024;
+// Squared distance under which an orbit counts as having returned to an earlier value
+const PERIODICITY_TOLERANCE: f64 = 1e-30;
+// Escape checks tighten that to this share of the view height (before squaring), so points
+// just outside a component, whose orbits shadow its cycle for a while, still escape
+const PERIODICITY_VIEW_FRACTION: f64 = 1e-3;
+// Longest orbit traced for the overlay, more is just an unreadable scribble
+pub const ORBIT_TRACE_MAX_ITER: u32 = 2000;
+// A camera that escapes in fewer iterations than this is out in the exterior "ocean",
+// where there's no deep point nearby worth searching for
+pub const OCEAN_THRESHOLD: u32 = 10;
+// Newton steps allowed when homing in on a nucleus, it converges quadratically when it works
+const NUCLEUS_NEWTON_STEPS: u32 = 64;
+// Share of the view height searched around the cursor for a minibrot
+pub const NUCLEUS_SEARCH_RADIUS: f64 = 0.25;
+
+/// Middle of the set's bounding box, the center of the starting view
+pub const DEFAULT_CENTER: (f64, f64) = (-0.765, 0.0);
+// Half the width and height of the set's bounding box, from -2 to about 0.47 on the
+// real axis and +-1.12 on the imaginary one
+const SET_HALF_EXTENT: (f64, f64) = (1.236, 1.12);
+// Space left around the set in the starting view, as a share of its size
+const DEFAULT_VIEW_MARGIN: f64 = 0.05;
+
+/// Which set the main view shows. Both iterate z^2 + c, they differ in what a pixel picks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FractalMode {
+    /// Pixels are c, starting from z = 0
+    #[default]
+    Mandelbrot,
+    /// Pixels are the starting z, c is `HighPrecisionState::julia_c`
+    Julia,
+}
+
+impl FractalMode {
+    pub fn uniform_value(self) -> u32 {
+        match self {
+            FractalMode::Mandelbrot => FRACTAL_MODE_MANDELBROT,
+            FractalMode::Julia => FRACTAL_MODE_JULIA,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct HighPrecisionState {
     pub reference: Complex, // The anchor
     pub camera: Complex,    // Where user is looking
     pub zoom: Float,
+    pub precision: u32, // Bits of the camera, reference and zoom, grows with the depth
+    pub orbit_precision: u32, // Bits used while iterating the reference orbit
+    pub precision_mode: PrecisionMode,
+    pub fractal_mode: FractalMode,
+    pub julia_c: Complex, // The constant of the Julia set, unused in Mandelbrot mode
+    computed: Option<OrbitInputs>, // What the current orbit came from, None = dirty
+}
+
+/// Everything the reference search and orbit depend on, see `HighPrecisionState::is_dirty`.
+#[derive(Clone, PartialEq)]
+struct OrbitInputs {
+    reference: Complex,
+    camera: Complex,
+    zoom: Float,
+    orbit_precision: u32,
+    precision_mode: PrecisionMode,
+    fractal_mode: FractalMode,
+    julia_c: Complex,
+    max_iter: u32,
 }
 
 impl HighPrecisionState {
@@ -17,27 +113,269 @@ impl HighPrecisionState {
             reference: Complex::new(PRECISION),
             camera: Complex::new(PRECISION),
             zoom: Float::with_val(PRECISION, 1.0),
+            precision: PRECISION,
+            orbit_precision: PRECISION,
+            precision_mode: PrecisionMode::Auto,
+            fractal_mode: FractalMode::Mandelbrot,
+            julia_c: Complex::new(PRECISION),
+            computed: None,
         }
     }
 
-    /// Calculates how many iterations it takes to survive or escape
+    /// Whether the view or orbit settings changed since `mark_clean`, or the iteration
+    /// target isn't `max_iter` any more. The fields are public and set all over, so this
+    /// compares them against a snapshot rather than trusting setters.
+    pub fn is_dirty(&self, max_iter: u32) -> bool {
+        self.computed.as_ref() != Some(&self.orbit_inputs(max_iter))
+    }
+
+    /// Records the current state as the one the orbit was computed from.
+    pub fn mark_clean(&mut self, max_iter: u32) {
+        self.computed = Some(self.orbit_inputs(max_iter));
+    }
+
+    /// Forces the next `is_dirty`, for changes outside this state such as a new buffer.
+    pub fn mark_dirty(&mut self) {
+        self.computed = None;
+    }
+
+    /// Takes over the reference and orbit precision an `OrbitJob` picked on a copy of this
+    /// state. The snapshot follows, so the reference move alone doesn't make it dirty.
+    pub fn adopt_reference(&mut self, computed: &HighPrecisionState) {
+        self.reference = computed.reference.clone();
+        self.orbit_precision = computed.orbit_precision;
+        if let Some(inputs) = &mut self.computed {
+            inputs.reference = computed.reference.clone();
+            inputs.orbit_precision = computed.orbit_precision;
+        }
+    }
+
+    /// Offset (Reference -> Camera) in screen space, for the shader.
+    pub fn reference_offset(&self) -> [f32; 2] {
+        self.offset_from(&self.reference)
+    }
+
+    /// Offset from `point` to the camera in screen space.
+    pub fn offset_from(&self, point: &Complex) -> [f32; 2] {
+        let diff_re = Float::with_val(128, self.camera.real() - point.real());
+        let diff_im = Float::with_val(128, self.camera.imag() - point.imag());
+
+        // Convert high-precision diff to screen-space offset (f32)
+        [
+            Float::with_val(24, &diff_re * &self.zoom).to_f32(),
+            Float::with_val(24, &diff_im * &self.zoom).to_f32(),
+        ]
+    }
+
+    /// The point at NDC (`x`, `y`) of a view with the given width/height ratio, turned
+    /// `rotation` radians counterclockwise around the camera. The same mapping as the shader.
+    pub fn point_at(&self, x: f64, y: f64, aspect: f64, rotation: f64) -> Complex {
+        let (sin, cos) = rotation.sin_cos();
+        let (x, y) = (x * aspect, y);
+        let dx = Float::with_val(PRECISION, cos * x - sin * y) / &self.zoom;
+        let dy = Float::with_val(PRECISION, sin * x + cos * y) / &self.zoom;
+
+        let mut point = self.camera.clone();
+        point.mut_real().add_assign(&dx);
+        point.mut_imag().add_assign(&dy);
+        point
+    }
+
+    fn orbit_inputs(&self, max_iter: u32) -> OrbitInputs {
+        OrbitInputs {
+            reference: self.reference.clone(),
+            camera: self.camera.clone(),
+            zoom: self.zoom.clone(),
+            orbit_precision: self.orbit_precision,
+            precision_mode: self.precision_mode,
+            fractal_mode: self.fractal_mode,
+            julia_c: self.julia_c.clone(),
+            max_iter,
+        }
+    }
+
+    /// The whole set centered in a view of the given width/height ratio, with
+    /// `DEFAULT_VIEW_MARGIN` to spare on the tighter side.
+    pub fn with_default_view(aspect: f64) -> Self {
+        let mut state = Self::new();
+        state.camera.assign(DEFAULT_CENTER);
+        state.reference.assign(&state.camera);
+
+        // The view is 2/zoom tall and 2*aspect/zoom wide
+        let padding = 1.0 + DEFAULT_VIEW_MARGIN;
+        let zoom =
+            (1.0 / (SET_HALF_EXTENT.1 * padding)).min(aspect / (SET_HALF_EXTENT.0 * padding));
+        state.zoom.assign(zoom);
+        state
+    }
+
+    /// Raises `precision` to what the zoom needs, see `required_precision`, carrying the
+    /// camera, reference and zoom over at the new width. It never shrinks, so zooming back
+    /// out and in again doesn't reallocate. Returns whether it grew.
+    pub fn grow_precision(&mut self) -> bool {
+        let required = required_precision(&self.zoom);
+        if required <= self.precision {
+            return false;
+        }
+
+        self.precision = required;
+        self.camera.set_prec(required);
+        self.reference.set_prec(required);
+        self.zoom.set_prec(required);
+        // An orbit iterated with fewer bits than its starting point would throw them away
+        self.orbit_precision = self.orbit_precision.max(required);
+        true
+    }
+
+    /// The view as one line for the clipboard or a URL query, like
+    /// `re=-7.4e-1&im=1.3e-1&zoom=1.0e10&iterations=1500`. The center and zoom keep every
+    /// digit of the view's precision, see `from_location_string`.
+    pub fn to_location_string(&self, iterations: u32) -> String {
+        format!(
+            "re={}&im={}&zoom={}&iterations={}",
+            self.camera.real().to_string_radix(10, None),
+            self.camera.imag().to_string_radix(10, None),
+            self.zoom.to_string_radix(10, None),
+            iterations
+        )
+    }
+
+    /// Parses a `to_location_string` line back into a state at that view (precision grown
+    /// to fit) and its iteration count. Anything up to a `?` is skipped, so a whole URL
+    /// works too. Malformed text is an error, never a panic.
+    pub fn from_location_string(text: &str) -> Result<(Self, u32), String> {
+        let text = text.trim();
+        let query = text.split_once('?').map_or(text, |(_, query)| query);
+        let (mut re, mut im, mut zoom, mut iterations) = (None, None, None, None);
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+
+            match key {
+                "re" => re = Some(parse_float(key, value)?),
+                "im" => im = Some(parse_float(key, value)?),
+                "zoom" => zoom = Some(parse_float(key, value)?),
+                "iterations" => {
+                    iterations = Some(
+                        value
+                            .parse::<u32>()
+                            .map_err(|e| format!("invalid {} '{}': {}", key, value, e))?,
+                    )
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+
+        let (Some(re), Some(im), Some(zoom), Some(iterations)) = (re, im, zoom, iterations) else {
+            return Err("re, im, zoom and iterations are required".to_string());
+        };
+        if !(re.is_finite() && im.is_finite() && zoom.is_finite()) {
+            return Err("re, im and zoom must be finite".to_string());
+        }
+        if zoom <= 0 {
+            return Err("zoom must be positive".to_string());
+        }
+        if iterations == 0 {
+            return Err("iterations must be at least 1".to_string());
+        }
+
+        // Grow for the zoom first, so the camera keeps its digits
+        let mut state = Self::new();
+        state.zoom.assign(&zoom);
+        state.grow_precision();
+        state.camera.assign((&re, &im));
+        state.reference.assign(&state.camera);
+        Ok((state, iterations))
+    }
+
+    /// Picks the arithmetic for CPU iteration based on the mode and current zoom.
+    /// Escalated orbit precision always needs rug.
+    pub fn orbit_math(&self) -> &'static dyn OrbitMath {
+        let use_f64 = match self.precision_mode {
+            PrecisionMode::F64 => true,
+            PrecisionMode::Rug => false,
+            PrecisionMode::Auto => {
+                self.orbit_precision <= PRECISION
+                    && self.zoom.clone().log10().to_f64() < F64_MAX_LOG_ZOOM
+            }
+        };
+
+        if use_f64 { &F64Math } else { &RugMath }
+    }
+
+    /// Calculates how many iterations it takes to survive or escape. Interior points
+    /// return `max_checks` early once their orbit is seen to cycle.
     pub fn get_escape_time(&self, point: &Complex, max_checks: u32) -> u32 {
-        let mut z = Complex::with_val(PRECISION, (0.0, 0.0));
-        let c = point; // c is the candidate point
+        // The main body never escapes, no need to iterate all the way to the limit
+        if self.fractal_mode == FractalMode::Mandelbrot && in_main_body(point) {
+            return max_checks;
+        }
+
+        let (z0, c) = self.start_and_constant(point);
+        let tolerance = self.periodicity_tolerance();
+        self.orbit_math().escape_time(&z0, c, max_checks, tolerance)
+    }
+
+    /// Squared distance under which an orbit counts as cycling at this zoom.
+    fn periodicity_tolerance(&self) -> f64 {
+        // Past ~10^150 the square underflows to 0 and the check is off
+        let view = PERIODICITY_VIEW_FRACTION / self.zoom.to_f64();
+        PERIODICITY_TOLERANCE.min(view * view)
+    }
+
+    /// Where the orbit of `point` starts and the c it iterates with, depending on the mode.
+    fn start_and_constant<'a>(&'a self, point: &'a Complex) -> (Complex, &'a Complex) {
+        match self.fractal_mode {
+            FractalMode::Mandelbrot => (Complex::new(working_precision(point)), point),
+            FractalMode::Julia => (point.clone(), &self.julia_c),
+        }
+    }
 
-        for i in 0..max_checks {
+    /// Whether `point` survives `max_iter` iterations, i.e. counts as inside the set.
+    pub fn contains(&self, point: &Complex, max_iter: u32) -> bool {
+        self.get_escape_time(point, max_iter) == max_iter
+    }
+
+    /// Same answer as `contains`, but stops early once the orbit repeats itself.
+    /// Interior points settle into a cycle, so this is much faster for them.
+    /// Uses Brent-style checks: compare against a saved value, saving it again at
+    /// doubling intervals so any period is eventually caught.
+    pub fn contains_periodic(&self, point: &Complex, max_iter: u32) -> bool {
+        if self.fractal_mode == FractalMode::Mandelbrot && in_main_body(point) {
+            return true;
+        }
+
+        let (mut z, c) = self.start_and_constant(point);
+        let mut saved = z.clone();
+        let mut diff = Complex::new(z.prec());
+        let tolerance = self.periodicity_tolerance();
+        let mut check_interval = 8;
+        let mut steps_since_save = 0;
+
+        for _ in 0..max_iter {
             z.square_mut();
             z += c;
 
-            // Optimization: Check norm only periodically or use a rough check first
-            if z.real().to_f32().abs() > 2.0 || z.imag().to_f32().abs() > 2.0 {
-                let norm = Float::with_val(24, z.norm_ref());
-                if norm.to_f32() > 4.0 {
-                    return i;
-                }
+            if escaped(&z) {
+                return false;
+            }
+
+            diff.assign(&z - &saved);
+            let (d_re, d_im) = (diff.real().to_f64(), diff.imag().to_f64());
+            if d_re * d_re + d_im * d_im < tolerance {
+                return true; // Back where it was, so it cycles forever
+            }
+
+            steps_since_save += 1;
+            if steps_since_save == check_interval {
+                saved.assign(&z);
+                steps_since_save = 0;
+                check_interval *= 2;
             }
         }
-        max_checks // Survived!
+        true // Survived!
     }
 
     /// Searches for a better reference point near the target center.
@@ -57,72 +395,1070 @@ impl HighPrecisionState {
 
         // If center fails, sample a pattern around it.
         // We look for a point that is "deeper" in the set (lasts longer).
-        let mut best_point = center.clone();
-        let mut best_score = center_score;
-
         let one = Float::with_val(PRECISION, 1.0);
         let radius = one / zoom;
 
-        for i in 0..num_samples {
-            // Generate offsets using a simple golden ratio spiral for even distribution
-            let t = i as f32 / num_samples as f32;
-            let angle = 2.0 * PI * 21.0 * t; // Spiral factor
-            let dist = t * 2.0;
+        // Same pick as a sequential scan: the first candidate with the longest survival
+        let mut best_point = center.clone();
+        let mut best_score = center_score;
+
+        // Candidates are independent, so score them in parallel, a chunk per round with a
+        // candidate for every thread. A survivor can't be beaten, so once a chunk has one
+        // the rest are never iterated, which is most of the work at deep zooms.
+        let chunk = rayon::current_num_threads().max(1) as u32;
+        for start in (0..num_samples).step_by(chunk as usize) {
+            let samples = start..(start + chunk).min(num_samples);
+            let scored =
+                self.score_candidates(center, &radius, max_iter, samples, num_samples, 2.0);
+            for (candidate, score) in scored {
+                if score > best_score {
+                    best_score = score;
+                    best_point = candidate;
+                    if best_score == max_iter {
+                        return (best_point, best_score);
+                    }
+                }
+            }
+        }
 
-            let ox = angle.cos() * dist;
-            let oy = angle.sin() * dist;
+        (best_point, best_score)
+    }
 
-            let mut candidate = center.clone();
-            let dx = Float::with_val(PRECISION, ox) * &radius;
-            let dy = Float::with_val(PRECISION, oy) * &radius;
+    /// Picks a visually interesting point near `center` to zoom toward.
+    /// Points that escape late sit right on the boundary, where the detail is.
+    pub fn find_zoom_target(
+        &self,
+        center: &Complex,
+        zoom: &Float,
+        max_iter: u32,
+        num_samples: u32,
+    ) -> Complex {
+        let one = Float::with_val(PRECISION, 1.0);
+        let radius = one / zoom;
 
-            candidate.mut_real().add_assign(&dx);
-            candidate.mut_imag().add_assign(&dy);
+        // Stay inside the visible area so the autopilot doesn't lurch sideways
+        let scored =
+            self.score_candidates(center, &radius, max_iter, 0..num_samples, num_samples, 0.8);
 
-            let score = self.get_escape_time(&candidate, max_iter);
+        let mut best_point = center.clone();
+        let mut best_score = 0;
 
-            if score > best_score {
+        for (candidate, score) in scored {
+            // Interior points are flat black, only take the ones that eventually escape
+            if score < max_iter && score > best_score {
                 best_score = score;
                 best_point = candidate;
-                // If we found a survivor, stop immediately!
-                if best_score == max_iter {
-                    break;
-                }
             }
         }
 
-        (best_point, best_score)
+        best_point
+    }
+
+    /// Escape times for the `samples` of a spiral of `num_samples` candidates around
+    /// `center`, in spiral order. Each candidate is built from its own clone of `center`,
+    /// so threads share nothing mutable.
+    fn score_candidates(
+        &self,
+        center: &Complex,
+        radius: &Float,
+        max_iter: u32,
+        samples: Range<u32>,
+        num_samples: u32,
+        spread: f32,
+    ) -> Vec<(Complex, u32)> {
+        samples
+            .into_par_iter()
+            .map(|i| {
+                let candidate = spiral_candidate(center, radius, i, num_samples, spread);
+                let score = self.get_escape_time(&candidate, max_iter);
+                (candidate, score)
+            })
+            .collect()
     }
 
     /// Calculates the "Reference Orbit" for the center point.
-    /// Returns a list of points (Z values) that the GPU will use.
-    pub fn calculate_orbit(&self, max_iter: u32) -> (Vec<[f32; 2]>, u32) {
-        let mut orbit = Vec::with_capacity(max_iter as usize);
-        let mut z = Complex::with_val(PRECISION, (0.0, 0.0));
-        let c = &self.reference;
+    /// Returns a list of [Z.re, Z.im, dZ.re, dZ.im] entries that the GPU will use,
+    /// where dZ is the derivative with respect to c (for distance estimation).
+    /// Each step depends on the previous one, so this stays on one thread.
+    /// In Julia mode the reference is the orbit's start and dZ is taken with respect to it.
+    pub fn calculate_orbit(&self, max_iter: u32) -> (Vec<[f32; 4]>, u32) {
+        let math = self.orbit_math();
+        match self.fractal_mode {
+            FractalMode::Mandelbrot => math.orbit(&self.reference, max_iter, self.orbit_precision),
+            FractalMode::Julia => math.julia_orbit(
+                &self.reference,
+                &self.julia_c,
+                max_iter,
+                self.orbit_precision,
+            ),
+        }
+    }
 
-        let mut valid_count = 0; // Track valid iterations
+    /// The reference orbit in double-single form: [Z.re hi, Z.re lo, Z.im hi, Z.im lo]
+    /// per step, see `split_f64`. Iterated in rug at `orbit_precision` whatever the
+    /// precision mode, since f64 math couldn't fill the low halves. Only valid entries,
+    /// which line up with `calculate_orbit`'s.
+    pub fn orbit_df(&self, max_iter: u32) -> Vec<[f32; 4]> {
+        let mut orbit = Vec::with_capacity(max_iter as usize);
+        let (start, c) = match self.fractal_mode {
+            FractalMode::Mandelbrot => (Complex::new(self.orbit_precision), &self.reference),
+            FractalMode::Julia => (
+                Complex::with_val(self.orbit_precision, &self.reference),
+                &self.julia_c,
+            ),
+        };
+        let mut z = start;
 
         for _ in 0..max_iter {
-            let re = z.real().to_f32();
-            let im = z.imag().to_f32();
-            orbit.push([re, im]);
-            valid_count += 1; // We added a valid point
+            let [re_hi, re_lo] = split_f64(z.real());
+            let [im_hi, im_lo] = split_f64(z.imag());
+            orbit.push([re_hi, re_lo, im_hi, im_lo]);
 
             z.square_mut();
             z += c;
 
-            let norm = Float::with_val(24, z.norm_ref());
-            if norm.to_f32() > 4.0 {
+            if escaped(&z) {
                 break;
             }
         }
+        orbit
+    }
+}
+
+/// Splits `x` into the f32 pair of double-single arithmetic: `hi` is the nearest f32
+/// and `lo` the f32 nearest to what's left, so `hi + lo` keeps about 48 bits of `x`.
+pub fn split_f64(x: &Float) -> [f32; 2] {
+    let hi = x.to_f32();
+    let lo = Float::with_val(x.prec(), x - hi).to_f32();
+    [hi, lo]
+}
+
+/// Iteration budget for a given zoom, up to the default cap `MAX_ITER`.
+pub fn target_iterations(zoom: &Float) -> u32 {
+    zoom_iterations(zoom).min(MAX_ITER)
+}
+
+/// Iterations a zoom needs to resolve its detail, with no cap. Deeper views need more.
+pub fn zoom_iterations(zoom: &Float) -> u32 {
+    let log_zoom = zoom.clone().log10().to_f32();
+    500u32.saturating_add((100.0 * log_zoom) as u32)
+}
+
+/// Bits the camera and reference need at `zoom`: `BITS_PER_DECADE` for every decade
+/// plus `PRECISION_MARGIN`, rounded up to `PRECISION_STEP` and never below `PRECISION`.
+pub fn required_precision(zoom: &Float) -> u32 {
+    let decades = zoom.clone().log10().to_f64().max(0.0);
+    let bits = (decades * BITS_PER_DECADE).ceil() as u32 + PRECISION_MARGIN;
+    bits.next_multiple_of(PRECISION_STEP).max(PRECISION)
+}
+
+/// Full-precision orbit of `c`, starting at z1 = c. Stops after the first point
+/// outside the escape radius, so an escaping orbit shows where it leaves.
+pub fn trace_orbit(c: &Complex, max_iter: u32) -> Vec<Complex> {
+    let mut points = Vec::new();
+    let mut z = Complex::new(working_precision(c));
+
+    for _ in 0..max_iter {
+        z.square_mut();
+        z += c;
+        points.push(z.clone());
+
+        if escaped(&z) {
+            break;
+        }
+    }
+    points
+}
+
+/// Bits to iterate with for `c`: its own precision, so a deep point isn't rounded to the
+/// base precision on the first `z += c`, and never less than `PRECISION`.
+pub fn working_precision(c: &Complex) -> u32 {
+    let (re, im) = c.prec();
+    re.max(im).max(PRECISION)
+}
+
+/// Whether `z` is past the escape radius of 2. The check only needs a rough magnitude,
+/// so it squares f64 copies of the parts instead of allocating an MPFR norm.
+pub fn escaped(z: &Complex) -> bool {
+    let (re, im) = (z.real().to_f64(), z.imag().to_f64());
+    re * re + im * im > 4.0
+}
+
+/// Whether `c` lies inside the main cardioid or the period-2 bulb, which together
+/// cover most of the set's area. Checked exactly in rug, strict so boundary points
+/// still get iterated.
+pub fn in_main_body(c: &Complex) -> bool {
+    let (x, y) = (c.real(), c.imag());
+    let precision = working_precision(c);
+    let y2 = Float::with_val(precision, y.square_ref());
+
+    // Period-2 bulb: (x + 1)^2 + y^2 < 1/16
+    let x1 = Float::with_val(precision, x + 1.0);
+    if Float::with_val(precision, x1.square_ref()) + &y2 < 0.0625 {
+        return true;
+    }
+
+    // Main cardioid: q * (q + (x - 1/4)) < y^2 / 4, with q = (x - 1/4)^2 + y^2
+    let xq = Float::with_val(precision, x - 0.25);
+    let q = Float::with_val(precision, xq.square_ref()) + &y2;
+    let lhs = Float::with_val(precision, &q + &xq) * &q;
+    lhs < y2 / 4.0
+}
+
+/// Period of the lowest-period minibrot whose nucleus lies within about `radius` of `center`,
+/// using the ball method: iterate the center with a first-order bound on how far the disk's
+/// orbits spread, and stop at the first step where the disk contains 0.
+pub fn ball_period(center: &Complex, radius: &Float, max_period: u32) -> Option<u32> {
+    let precision = working_precision(center);
+    let mut z = Complex::new(precision);
+    let mut dz = Complex::new(precision);
+    let radius = radius.to_f64();
+
+    for period in 1..=max_period {
+        // dz' = 2*z*dz + 1, using z before it is advanced
+        dz *= &z;
+        dz *= 2;
+        dz += 1;
+        z.square_mut();
+        z += center;
+
+        if escaped(&z) {
+            return None;
+        }
+
+        let spread = Float::with_val(53, dz.abs_ref()).to_f64() * radius;
+        if Float::with_val(53, z.abs_ref()).to_f64() < spread {
+            return Some(period);
+        }
+    }
+    None
+}
+
+/// Solves for the nucleus of a period-`period` component near `guess` with Newton's method
+/// on z_period(c) = 0. Returns `None` if it doesn't settle.
+pub fn find_nucleus(guess: &Complex, period: u32) -> Option<Complex> {
+    let precision = working_precision(guess);
+    let mut c = Complex::with_val(precision, guess);
+    // Converged once a step is down at the last few bits of the working precision
+    let tolerance = Float::with_val(precision, Float::i_exp(1, 8 - precision as i32));
+
+    for _ in 0..NUCLEUS_NEWTON_STEPS {
+        let mut z = Complex::new(precision);
+        let mut dz = Complex::new(precision);
+        for _ in 0..period {
+            dz *= &z;
+            dz *= 2;
+            dz += 1;
+            z.square_mut();
+            z += &c;
+        }
+
+        if dz.is_zero() {
+            return None;
+        }
+        let step = Complex::with_val(precision, &z / &dz);
+        c -= &step;
+
+        let step_size = Float::with_val(precision, step.abs_ref());
+        if !step_size.is_finite() {
+            return None;
+        }
+        if step_size <= tolerance {
+            return Some(c);
+        }
+    }
+    None
+}
+
+/// Rough scale of the minibrot whose nucleus is `nucleus`, relative to 1 for the whole set.
+/// From the derivative of its period-`period` orbit, as in "Mandelbrot set component size".
+pub fn minibrot_size(nucleus: &Complex, period: u32) -> Float {
+    let precision = working_precision(nucleus);
+    let mut z = Complex::new(precision);
+    let mut l = Complex::with_val(precision, (1.0, 0.0));
+    let mut b = Complex::with_val(precision, (1.0, 0.0));
+
+    for _ in 1..period {
+        z.square_mut();
+        z += nucleus;
+        l *= &z;
+        l *= 2;
+        b += Complex::with_val(precision, l.recip_ref());
+    }
+
+    // size = 1 / (b * l^2), the components are rotated copies so only its magnitude matters
+    let l2 = Complex::with_val(precision, l.square_ref());
+    let denominator = Float::with_val(precision, (b * l2).abs_ref());
+    Float::with_val(precision, denominator.recip_ref())
+}
+
+/// Generates the i-th of `num_samples` points on a spiral around `center`,
+/// reaching out to `spread * radius` for the last sample.
+fn spiral_candidate(
+    center: &Complex,
+    radius: &Float,
+    i: u32,
+    num_samples: u32,
+    spread: f32,
+) -> Complex {
+    // Generate offsets using a simple golden ratio spiral for even distribution
+    let t = i as f32 / num_samples as f32;
+    let angle = 2.0 * PI * 21.0 * t; // Spiral factor
+    let dist = t * spread;
+
+    let ox = angle.cos() * dist;
+    let oy = angle.sin() * dist;
+
+    let mut candidate = center.clone();
+    let dx = Float::with_val(PRECISION, ox) * radius;
+    let dy = Float::with_val(PRECISION, oy) * radius;
+
+    candidate.mut_real().add_assign(&dx);
+    candidate.mut_imag().add_assign(&dy);
+
+    candidate
+}
+
+/// How the shader's delta iteration ended for one sampled pixel.
+#[derive(Debug, Clone, Copy)]
+struct PixelRun {
+    iterations: u32, // Iterations done before stopping
+    steps: u32,      // Loop steps taken, fewer than iterations when BLA skipped ahead
+    escaped: bool,
+    glitched: bool, // Stopped on Pauldelbrot's criterion
+}
+
+/// The view the CPU samplers iterate like the shader does.
+struct DeltaSampler<'a> {
+    orbit: &'a [[f32; 4]], // Valid part of the reference orbit
+    offset: [f32; 2],
+    zoom: f32,
+    aspect: f32,
+    max_iter: u32,
+    rebase: bool,
+    bla: Option<&'a BlaTable>, // Skip ahead with the table like the shader, None = off
+}
+
+impl DeltaSampler<'_> {
+    /// Runs the shader's f32 delta iteration for the pixel centers of a `grid` x `grid`
+    /// grid over the view. With `stop_on_glitch` a pixel stops where |Z + dz| becomes tiny
+    /// compared to |Z| (Pauldelbrot's criterion); rebasing, when on, is not a glitch.
+    fn run(&self, grid: u32, stop_on_glitch: bool) -> Vec<PixelRun> {
+        let orbit = self.orbit;
+        let mut runs = Vec::with_capacity((grid * grid) as usize);
+
+        for gy in 0..grid {
+            for gx in 0..grid {
+                // Pixel centers in NDC, mapped exactly like fs_main
+                let u = ((gx as f32 + 0.5) / grid as f32 * 2.0 - 1.0) * self.aspect;
+                let v = (gy as f32 + 0.5) / grid as f32 * 2.0 - 1.0;
+                let dc = [
+                    (u + self.offset[0]) / self.zoom,
+                    (v + self.offset[1]) / self.zoom,
+                ];
+
+                let mut run = PixelRun {
+                    iterations: 0,
+                    steps: 0,
+                    escaped: false,
+                    glitched: false,
+                };
+                let mut dz = [0.0f32, 0.0];
+                let mut ref_i = 0;
+                while run.iterations < self.max_iter && ref_i + 1 < orbit.len() {
+                    run.steps += 1;
+
+                    // dz = a*dz + b*dc for a whole run
+                    let dz_norm = dz[0] * dz[0] + dz[1] * dz[1];
+                    if let Some(table) = self.bla
+                        && let Some((index, length)) =
+                            table.find(ref_i, dz_norm, self.max_iter - run.iterations)
+                    {
+                        let BlaStep { a, b, .. } = table.steps[index];
+                        dz = [
+                            a[0] * dz[0] - a[1] * dz[1] + b[0] * dc[0] - b[1] * dc[1],
+                            a[0] * dz[1] + a[1] * dz[0] + b[0] * dc[1] + b[1] * dc[0],
+                        ];
+                        ref_i += length as usize;
+                        run.iterations += length;
+                        continue;
+                    }
+
+                    // dz = 2*Z*dz + dz^2 + dc
+                    let z_ref = orbit[ref_i];
+                    let re = 2.0 * (z_ref[0] * dz[0] - z_ref[1] * dz[1]) + dz[0] * dz[0]
+                        - dz[1] * dz[1]
+                        + dc[0];
+                    let im =
+                        2.0 * (z_ref[0] * dz[1] + z_ref[1] * dz[0]) + 2.0 * dz[0] * dz[1] + dc[1];
+                    dz = [re, im];
+                    run.iterations += 1;
+
+                    // Compare against the reference at the same step
+                    ref_i += 1;
+                    let next_ref = orbit[ref_i];
+                    let z_x = next_ref[0] + dz[0];
+                    let z_y = next_ref[1] + dz[1];
+                    let z_norm = z_x * z_x + z_y * z_y;
+                    if z_norm > 4.0 {
+                        run.escaped = true;
+                        break;
+                    }
+
+                    let dz_norm = dz[0] * dz[0] + dz[1] * dz[1];
+                    if self.rebase && (z_norm < dz_norm || ref_i + 1 >= orbit.len()) {
+                        // Same restart as the shader
+                        dz = [z_x, z_y];
+                        ref_i = 0;
+                        continue;
+                    }
+
+                    let ref_norm = next_ref[0] * next_ref[0] + next_ref[1] * next_ref[1];
+                    if stop_on_glitch && z_norm < GLITCH_TOLERANCE * ref_norm {
+                        run.glitched = true;
+                        break;
+                    }
+                }
+                runs.push(run);
+            }
+        }
+
+        runs
+    }
+}
+
+/// Estimates the fraction of the screen where perturbation breaks down.
+/// Runs the shader's f32 delta iteration on a coarse grid of pixels and flags
+/// points where |Z + dz| becomes tiny compared to |Z| (Pauldelbrot's criterion).
+/// With `rebase` set this mirrors the shader's rebasing, which avoids most glitches.
+pub fn estimate_glitch_fraction(
+    orbit: &[[f32; 4]],
+    valid_len: u32,
+    offset: [f32; 2],
+    zoom: f32,
+    aspect: f32,
+    max_iter: u32,
+    rebase: bool,
+) -> f32 {
+    let sampler = DeltaSampler {
+        orbit: &orbit[..(valid_len as usize).min(orbit.len())],
+        offset,
+        zoom,
+        aspect,
+        max_iter,
+        rebase,
+        bla: None,
+    };
+    let glitched = sampler
+        .run(GLITCH_SAMPLE_GRID, true)
+        .iter()
+        .filter(|run| run.glitched)
+        .count();
+
+    glitched as f32 / (GLITCH_SAMPLE_GRID * GLITCH_SAMPLE_GRID) as f32
+}
+
+/// Escape counts at the dark and bright ends of the view, measured on a coarse grid of
+/// pixels iterated like the shader does. The 5th and 95th percentiles stand in for the
+/// minimum and maximum so a few stragglers don't stretch the range.
+/// `None` when too few samples escape to tell, e.g. deep inside the set.
+pub fn escape_range(
+    orbit: &[[f32; 4]],
+    valid_len: u32,
+    offset: [f32; 2],
+    zoom: f32,
+    aspect: f32,
+    max_iter: u32,
+    rebase: bool,
+) -> Option<(u32, u32)> {
+    let sampler = DeltaSampler {
+        orbit: &orbit[..(valid_len as usize).min(orbit.len())],
+        offset,
+        zoom,
+        aspect,
+        max_iter,
+        rebase,
+        bla: None,
+    };
+    let mut escapes: Vec<u32> = sampler
+        .run(EXPOSURE_SAMPLE_GRID, false)
+        .iter()
+        .filter(|run| run.escaped)
+        .map(|run| run.iterations)
+        .collect();
+
+    // A handful of escapes is too noisy to set the colors by
+    if escapes.len() < EXPOSURE_SAMPLE_GRID as usize {
+        return None;
+    }
+    escapes.sort_unstable();
+    let last = escapes.len() - 1;
+    Some((escapes[last * 5 / 100], escapes[last * 95 / 100]))
+}
+
+/// Cubic series for the perturbation delta after `skip` steps of the reference orbit:
+/// dz = a*u + b*u^2 + c*u^3, where u = dc / radius is the pixel's offset relative to the
+/// radius the series was fitted for. Scaling by the radius keeps the coefficients about
+/// the size of the deltas, so they fit in f32 however deep the view is.
+#[derive(Debug, Clone, Copy)]
+pub struct SeriesApproximation {
+    pub skip: u32,
+    pub coefficients: [[f64; 2]; 3], // a, b and c as [re, im]
+}
+
+/// Series approximation for the Mandelbrot deltas of every pixel within `radius` (in
+/// complex units) of the reference. The unscaled coefficients follow
+/// A' = 2ZA + 1, B' = 2ZB + A^2, C' = 2ZC + 2AB, and are carried while the cubic term
+/// stays negligible next to the linear one and the delta small next to Z, so no pixel
+/// would have rebased along the way. The skip that leaves is then checked against direct
+/// perturbation at probes on the rim |u| = 1. The error is analytic in u, so it's largest
+/// on the rim, and the skip backs off to the last step where every probe agreed.
+/// `None` when not a single step can be skipped, which is the case at shallow zooms.
+pub fn series_approximation(
+    orbit: &[[f32; 4]],
+    valid_len: u32,
+    radius: f64,
+) -> Option<SeriesApproximation> {
+    let valid_len = (valid_len as usize).min(orbit.len());
+    if !(radius.is_finite() && radius > 0.0) || valid_len < 2 {
+        return None;
+    }
+
+    // Coefficients after each step, dz_0 = 0. The shader reads one entry past the skip.
+    let mut steps = vec![[[0.0; 2]; 3]];
+    for n in 0..valid_len - 2 {
+        let [a, b, c] = steps[n];
+        let two_z = [2.0 * orbit[n][0] as f64, 2.0 * orbit[n][1] as f64];
+        let ab = complex_mul(a, b);
+        let next = [
+            complex_add(complex_mul(two_z, a), [radius, 0.0]),
+            complex_add(complex_mul(two_z, b), complex_mul(a, a)),
+            complex_add(complex_mul(two_z, c), [2.0 * ab[0], 2.0 * ab[1]]),
+        ];
+
+        let [a, b, c] = next.map(|[re, im]| re.hypot(im));
+        let z = (orbit[n + 1][0] as f64).hypot(orbit[n + 1][1] as f64);
+        if !(c <= SERIES_TOLERANCE * a && 2.0 * (a + b + c) < z) {
+            break;
+        }
+        steps.push(next);
+    }
+
+    // Iterate the probes directly alongside the series until they part ways
+    let probes: Vec<[f64; 2]> = (0..SERIES_PROBES)
+        .map(|k| {
+            let angle = std::f64::consts::TAU * k as f64 / SERIES_PROBES as f64;
+            [angle.cos(), angle.sin()]
+        })
+        .collect();
+    let mut deltas = vec![[0.0; 2]; probes.len()];
+    let mut skip = 0;
+    'steps: for (n, &[a, b, c]) in steps.iter().enumerate().skip(1) {
+        let two_z = [2.0 * orbit[n - 1][0] as f64, 2.0 * orbit[n - 1][1] as f64];
+        for (u, dz) in probes.iter().zip(&mut deltas) {
+            // dz = 2*Z*dz + dz^2 + dc
+            let dc = [u[0] * radius, u[1] * radius];
+            *dz = complex_add(
+                complex_add(complex_mul(two_z, *dz), complex_mul(*dz, *dz)),
+                dc,
+            );
+
+            let u2 = complex_mul(*u, *u);
+            let series = complex_add(
+                complex_add(complex_mul(a, *u), complex_mul(b, u2)),
+                complex_mul(c, complex_mul(u2, *u)),
+            );
+            let error = (series[0] - dz[0]).hypot(series[1] - dz[1]);
+            // Written so a NaN counts as a disagreement
+            let agrees = error <= SERIES_TOLERANCE * dz[0].hypot(dz[1]);
+            if !agrees {
+                break 'steps;
+            }
+        }
+        skip = n;
+    }
+
+    (skip > 0).then(|| SeriesApproximation {
+        skip: skip as u32,
+        coefficients: steps[skip],
+    })
+}
+
+/// Bilinear approximation table for a reference orbit, see `bla_table`.
+pub struct BlaTable {
+    pub steps: Vec<BlaStep>, // Level 0 first, then each level of merged pairs
+    pub len: u32,            // Entries in level 0, level k has len >> k
+    pub levels: u32,
+}
+
+/// A BLA step in f64, while the table is built.
+#[derive(Clone, Copy)]
+struct Bla {
+    a: [f64; 2],
+    b: [f64; 2],
+    radius: f64,
+}
+
+impl Bla {
+    /// `self` followed by `next` (Heiland-Allen's merge): valid while dz is within
+    /// `self`'s radius and lands within `next`'s, for any |dc| up to `dc_max`.
+    fn then(&self, next: &Bla, dc_max: f64) -> Bla {
+        let a_norm = self.a[0].hypot(self.a[1]);
+        let b_norm = self.b[0].hypot(self.b[1]);
+        // max() also turns the 0/0 of a zero `a` into 0, never valid
+        let landing = ((next.radius - b_norm * dc_max) / a_norm).max(0.0);
+        Bla {
+            a: complex_mul(next.a, self.a),
+            b: complex_add(complex_mul(next.a, self.b), next.b),
+            radius: self.radius.min(landing),
+        }
+    }
+
+    /// The step as the shader gets it. Coefficients that overflow f32 make it unusable there.
+    fn to_gpu(self) -> BlaStep {
+        let a = self.a.map(|x| x as f32);
+        let b = self.b.map(|x| x as f32);
+        let fits = a.iter().chain(&b).all(|x| x.is_finite());
+        BlaStep {
+            a,
+            b,
+            radius: if fits { self.radius as f32 } else { 0.0 },
+            _padding: 0,
+        }
+    }
+}
+
+/// Bilinear approximation (BLA) table for the first `valid_len` entries of `orbit`, for
+/// pixels within `dc_max` of the reference (0 for Julia views, where dc isn't added).
+/// Level 0 holds the single step at each orbit index m >= 1, dz -> 2*Z_m*dz + dc, valid
+/// while dz^2 stays below `BLA_TOLERANCE` of 2*Z_m*dz. Each further level merges pairs
+/// of the one below, so entry j of level k jumps from index 1 + j*2^k by 2^k steps.
+/// Steps end two short of the orbit's end, so a pixel always lands where the shader takes
+/// an ordinary step next, with its escape and rebase checks.
+pub fn bla_table(orbit: &[[f32; 4]], valid_len: u32, dc_max: f64) -> BlaTable {
+    let valid_len = (valid_len as usize).min(orbit.len());
+    let mut level: Vec<Bla> = orbit
+        .get(1..valid_len.saturating_sub(2))
+        .unwrap_or_default()
+        .iter()
+        .map(|point| {
+            let a = [2.0 * point[0] as f64, 2.0 * point[1] as f64];
+            Bla {
+                a,
+                b: [1.0, 0.0],
+                radius: BLA_TOLERANCE * a[0].hypot(a[1]),
+            }
+        })
+        .collect();
+
+    let len = level.len() as u32;
+    let mut steps = Vec::with_capacity(2 * level.len());
+    let mut levels = 0;
+    while !level.is_empty() {
+        steps.extend(level.iter().map(|bla| bla.to_gpu()));
+        levels += 1;
+        level = level
+            .chunks_exact(2)
+            .map(|pair| pair[0].then(&pair[1], dc_max))
+            .collect();
+    }
+
+    BlaTable { steps, len, levels }
+}
 
-        // Fill the rest with zeros (Padding)
-        while orbit.len() < max_iter as usize {
-            orbit.push([0.0, 0.0]);
+impl BlaTable {
+    /// The longest step that applies at orbit index `ref_i` to a delta with squared size
+    /// `dz_norm` and takes at most `budget` iterations, as its index in `steps` and length.
+    /// Looks up the same entries in the same order as `find_bla` in the shader.
+    fn find(&self, ref_i: usize, dz_norm: f32, budget: u32) -> Option<(usize, u32)> {
+        if self.levels == 0 || ref_i == 0 {
+            return None;
         }
+        // Index m can start a run of 2^k steps when k divides it, start from the longest
+        let m = ref_i - 1;
+        let top = m.trailing_zeros().min(self.levels - 1);
+        let mut offset: usize = (0..top).map(|k| (self.len >> k) as usize).sum();
+        for level in (0..=top).rev() {
+            let length = 1u32 << level;
+            let j = m >> level;
+            if j < (self.len >> level) as usize && length <= budget {
+                let radius = self.steps[offset + j].radius;
+                if dz_norm < radius * radius {
+                    return Some((offset + j, length));
+                }
+            }
+            if level > 0 {
+                offset -= (self.len >> (level - 1)) as usize;
+            }
+        }
+        None
+    }
+
+    /// Iterations per loop step the shader gets out of the table, measured on a coarse grid
+    /// of Mandelbrot pixels iterated like it does. 1 means no gain. `orbit` is the valid part.
+    pub fn skip_ratio(
+        &self,
+        orbit: &[[f32; 4]],
+        offset: [f32; 2],
+        zoom: f32,
+        aspect: f32,
+        max_iter: u32,
+        rebase: bool,
+    ) -> f32 {
+        let sampler = DeltaSampler {
+            orbit,
+            offset,
+            zoom,
+            aspect,
+            max_iter,
+            rebase,
+            bla: Some(self),
+        };
+        let runs = sampler.run(BLA_SAMPLE_GRID, false);
+        let iterations: u64 = runs.iter().map(|run| run.iterations as u64).sum();
+        let steps: u64 = runs.iter().map(|run| run.steps as u64).sum();
+
+        iterations as f32 / steps.max(1) as f32
+    }
+}
+
+fn complex_add(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn complex_mul(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] * b[0] - a[1] * b[1], a[0] * b[1] + a[1] * b[0]]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::SubAssign;
+
+    use super::*;
+
+    fn view(re: f64, im: f64, zoom: f64) -> HighPrecisionState {
+        let mut state = HighPrecisionState::new();
+        state.camera.assign((re, im));
+        state.reference.assign(&state.camera);
+        state.zoom.assign(zoom);
+        state
+    }
+
+    #[test]
+    fn center_maps_to_camera_at_any_aspect() {
+        let state = view(-0.743643887037151, 0.131825904205330, 1e6);
+        for aspect in [1.0, 16.0 / 9.0, 9.0 / 16.0] {
+            assert_eq!(state.point_at(0.0, 0.0, aspect, 0.0), state.camera);
+            assert_eq!(state.point_at(0.0, 0.0, aspect, 0.7), state.camera);
+        }
+    }
+
+    #[test]
+    fn horizontal_extent_follows_aspect() {
+        let state = view(-0.5, 0.25, 4.0);
+        let half_width = |aspect: f64| {
+            let edge = state.point_at(1.0, 0.0, aspect, 0.0);
+            Float::with_val(53, edge.real() - state.camera.real()).to_f64()
+        };
+        let half_height = {
+            let edge = state.point_at(0.0, 1.0, 2.0, 0.0);
+            Float::with_val(53, edge.imag() - state.camera.imag()).to_f64()
+        };
+
+        // The view is 2/zoom tall whatever the aspect, and 2*aspect/zoom wide
+        assert_eq!(half_width(1.0), 0.25);
+        assert_eq!(half_width(2.0), 0.5);
+        assert_eq!(half_height, 0.25);
+    }
+
+    #[test]
+    fn orbit_derivative_matches_finite_difference() {
+        let c = Complex::with_val(PRECISION, (-0.4, 0.6));
+        let h = 1e-12;
+        let step = 20;
+
+        // trace_orbit starts at z1, the orbit's entry 0 is z0 = 0
+        let z_at = |re: f64, im: f64| {
+            let c = Complex::with_val(PRECISION, &c + Complex::with_val(PRECISION, (re, im)));
+            trace_orbit(&c, step)[step as usize - 1].clone()
+        };
+        let ahead = z_at(h, 0.0);
+        let behind = z_at(-h, 0.0);
+        let slope = Complex::with_val(PRECISION, &ahead - &behind) / (2.0 * h);
+        let (slope_re, slope_im) = (slope.real().to_f64(), slope.imag().to_f64());
+
+        for mode in [PrecisionMode::F64, PrecisionMode::Rug] {
+            let mut state = view(-0.4, 0.6, 1.0);
+            state.precision_mode = mode;
+            let (orbit, valid_len) = state.calculate_orbit(step + 1);
+            assert!(valid_len > step);
+
+            let [_, _, dz_re, dz_im] = orbit[step as usize];
+            let scale = slope_re.hypot(slope_im);
+            let error = (dz_re as f64 - slope_re).hypot(dz_im as f64 - slope_im);
+            assert!(
+                error < scale * 1e-5,
+                "{:?}: dZ/dc = ({}, {}), finite difference ({}, {})",
+                mode,
+                dz_re,
+                dz_im,
+                slope_re,
+                slope_im
+            );
+        }
+    }
+
+    #[test]
+    fn main_body_covers_cardioid_and_bulb() {
+        let point = |re: f64, im: f64| Complex::with_val(PRECISION, (re, im));
+        assert!(in_main_body(&point(-0.5, 0.0))); // Main cardioid
+        assert!(in_main_body(&point(-1.0, 0.0))); // Center of the period-2 bulb
+        assert!(in_main_body(&point(0.0, 0.0)));
+        // The cusp is on the cardioid's edge, the strict test leaves it to iteration
+        assert!(!in_main_body(&point(0.25, 0.0)));
+        assert!(!in_main_body(&point(-1.25, 0.0))); // The bulb's far edge
+        assert!(!in_main_body(&point(-0.1226, 0.7449))); // Period-3 bulb
+    }
+
+    #[test]
+    fn split_keeps_about_48_bits() {
+        let values = [
+            Float::with_val(PRECISION, rug::float::Constant::Pi),
+            parse_float("re", "-0.74364388703715870475219150611477").unwrap(),
+            Float::with_val(PRECISION, -1) / 3,
+            Float::with_val(PRECISION, 1e-5) * 3.7,
+        ];
+        for x in values {
+            let [hi, lo] = split_f64(&x);
+            let sum = Float::with_val(PRECISION, hi as f64 + lo as f64);
+            let error = (Float::with_val(PRECISION, &x - &sum) / &x).abs().to_f64();
+            assert!(error < 2f64.powi(-47), "{}: error {:e}", x, error);
+            // The halves don't overlap
+            assert!((lo as f64).abs() <= (hi as f64).abs() * 2f64.powi(-23));
+        }
+    }
+
+    #[test]
+    fn precision_grows_with_zoom() {
+        let mut state = view(-0.75, 0.1, 1.0);
+        let camera = state.camera.clone();
+        state.zoom.assign(1e50);
+        assert!(state.grow_precision());
+
+        let needed = (50.0 * BITS_PER_DECADE).ceil() as u32 + PRECISION_MARGIN;
+        assert!(state.precision >= needed);
+        assert!(state.camera.prec().0 >= needed && state.camera.prec().1 >= needed);
+        assert!(state.reference.prec().0 >= needed && state.reference.prec().1 >= needed);
+        assert!(state.zoom.prec() >= needed);
+        assert!(state.orbit_precision >= needed);
+        // Widening keeps the value, the new bits start out as zeros
+        assert_eq!(state.camera, camera);
+
+        // Already wide enough, zooming back out doesn't shrink it
+        let precision = state.precision;
+        state.zoom.assign(1e10);
+        assert!(!state.grow_precision());
+        assert_eq!(state.precision, precision);
+    }
+
+    #[test]
+    fn deep_candidates_stay_apart() {
+        let mut state = HighPrecisionState::new();
+        state.zoom.assign(1e50);
+        state.grow_precision();
+
+        // -2 sits on a repelling fixed point, anything just past it escapes within
+        // about log4(1 / distance) steps. At 128 bits the two would be the same point.
+        let tip = Complex::with_val(state.precision, (-2.0, 0.0));
+        let mut past_tip = tip.clone();
+        past_tip
+            .mut_real()
+            .sub_assign(Float::with_val(state.precision, 1e-45));
+        assert_ne!(tip, past_tip);
+
+        assert_eq!(state.get_escape_time(&tip, 1000), 1000);
+        assert!(state.get_escape_time(&past_tip, 1000) < 1000);
+        assert!(state.contains_periodic(&tip, 1000));
+        assert!(!state.contains_periodic(&past_tip, 1000));
+
+        let orbit = trace_orbit(&tip, 10);
+        let past_orbit = trace_orbit(&past_tip, 10);
+        assert_ne!(orbit[0], past_orbit[0]);
+        assert_ne!(orbit[9], past_orbit[9]);
+    }
+
+    // Billions of steps if the early exit were missing, the test would hang instead
+    const ENDLESS: u32 = u32::MAX;
+
+    #[test]
+    fn main_body_returns_early() {
+        let state = view(0.0, 0.0, 1.0);
+        for (re, im) in [(-1.0, 0.0), (0.0, 0.0)] {
+            let point = Complex::with_val(PRECISION, (re, im));
+            // Both are caught by `in_main_body` before any iteration
+            assert_eq!(state.get_escape_time(&point, ENDLESS), ENDLESS);
+        }
+    }
+
+    #[test]
+    fn periodic_orbits_return_early() {
+        // In the period-3 bulb, outside the main body, so only the cycle check stops it
+        let point = Complex::with_val(PRECISION, (-0.1226, 0.7449));
+        assert!(!in_main_body(&point));
+        let z0 = Complex::new(PRECISION);
+
+        for math in [&F64Math as &dyn OrbitMath, &RugMath] {
+            let iterations = math.escape_time(&z0, &point, ENDLESS, PERIODICITY_TOLERANCE);
+            assert_eq!(iterations, ENDLESS, "{}", math.name());
+        }
+        let state = view(0.0, 0.0, 1.0);
+        assert_eq!(state.get_escape_time(&point, ENDLESS), ENDLESS);
+        assert!(state.contains_periodic(&point, ENDLESS));
+
+        // Escaping points still report when they left
+        let outside = Complex::with_val(PRECISION, (0.5, 0.5));
+        let escape = F64Math.escape_time(&z0, &outside, 1000, PERIODICITY_TOLERANCE);
+        assert!(escape < 1000);
+        assert_eq!(
+            RugMath.escape_time(&z0, &outside, 1000, PERIODICITY_TOLERANCE),
+            escape
+        );
+    }
+
+    #[test]
+    fn double_single_orbit_matches_rug() {
+        let mut state = HighPrecisionState::new();
+        state.zoom.assign(1e30);
+        state.grow_precision();
+        let re = parse_float("re", "-0.743643887037158704752191506114774").unwrap();
+        let im = parse_float("im", "0.131825904205311970493132056385139").unwrap();
+        state.camera.assign((&re, &im));
+        state.reference.assign(&state.camera);
+
+        let orbit = state.orbit_df(2000);
+        assert_eq!(orbit.len(), 2000);
+        let (f32_orbit, _) = state.calculate_orbit(2000);
+
+        // An independent pass at twice the bits, from z0 = 0 like the orbit
+        let c = Complex::with_val(state.precision * 2, &state.reference);
+        let mut z = Complex::new(state.precision * 2);
+        for (i, [re_hi, re_lo, im_hi, im_lo]) in orbit.into_iter().enumerate() {
+            for (hi, lo, exact) in [(re_hi, re_lo, z.real()), (im_hi, im_lo, z.imag())] {
+                let error = Float::with_val(53, exact - (hi as f64 + lo as f64)).to_f64();
+                assert!(
+                    error.abs() <= 2f64.powi(-46),
+                    "step {}: error {:e}",
+                    i,
+                    error
+                );
+            }
+            // The high halves are the plain f32 orbit
+            assert_eq!([re_hi, im_hi], [f32_orbit[i][0], f32_orbit[i][1]]);
+
+            z.square_mut();
+            z += &c;
+        }
+    }
+
+    #[test]
+    fn dirty_tracks_orbit_inputs() {
+        let mut state = view(-0.75, 0.1, 10.0);
+        assert!(state.is_dirty(1000)); // Never computed
+        state.mark_clean(1000);
+        assert!(!state.is_dirty(1000));
+        assert!(state.is_dirty(2000)); // New iteration target
+
+        let mut moved = state.clone();
+        moved.camera.mut_real().add_assign(1e-20);
+        assert!(moved.is_dirty(1000));
+
+        let mut zoomed = state.clone();
+        zoomed.zoom *= 2;
+        assert!(zoomed.is_dirty(1000));
+
+        // A reference picked by an orbit job on a copy isn't a change of its own
+        let mut picked = state.clone();
+        picked.reference.assign((-0.7, 0.1));
+        state.adopt_reference(&picked);
+        assert!(!state.is_dirty(1000));
+
+        state.mark_dirty();
+        assert!(state.is_dirty(1000));
+    }
+
+    /// Times the reference search against scoring every candidate, the way it worked
+    /// before the chunks, on one thread and on rayon's default pool.
+    #[test]
+    #[ignore = "benchmark, run with --release -- --ignored --nocapture"]
+    fn bench_reference_search() {
+        use std::time::Instant;
+
+        let max_iter = 50000;
+        let serial = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let bench = |label: &str, state: &HighPrecisionState, center: &Complex| {
+            let radius = Float::with_val(PRECISION, 1.0) / &state.zoom;
+            let score_all = || {
+                let scored = state.score_candidates(center, &radius, max_iter, 0..64, 64, 2.0);
+                scored.into_iter().map(|(_, score)| score).max().unwrap()
+            };
+            let chunked = || {
+                let (_, score) = state.find_best_reference(center, &state.zoom, max_iter, 64);
+                score
+            };
+            println!(
+                "{}: zoom {:.1e}, {} math, center escapes at {}",
+                label,
+                state.zoom.to_f64(),
+                state.orbit_math().name(),
+                state.get_escape_time(center, max_iter)
+            );
+            let runs: [(&str, &(dyn Fn() -> u32 + Sync)); 2] =
+                [("all 64", &score_all), ("chunked", &chunked)];
+            for (name, run) in runs {
+                let start = Instant::now();
+                let score = serial.install(run);
+                let one_thread = start.elapsed();
+                let start = Instant::now();
+                run();
+                println!(
+                    "  {:<8} one thread {:>9.1?}, {} threads {:>9.1?}, best {}",
+                    name,
+                    one_thread,
+                    rayon::current_num_threads(),
+                    start.elapsed(),
+                    score
+                );
+            }
+        };
+
+        // Beside a minibrot found from seahorse valley, a candidate lands inside it
+        let re = parse_float("re", "-0.743643887037158704752191506114774").unwrap();
+        let im = parse_float("im", "0.131825904205311970493132056385139").unwrap();
+        let seahorse = Complex::with_val(PRECISION, (&re, &im));
+        let period = ball_period(&seahorse, &Float::with_val(PRECISION, 1e-12), 100000).unwrap();
+        let nucleus = find_nucleus(&seahorse, period).unwrap();
+        let mut state = HighPrecisionState::new();
+        state.zoom.assign(minibrot_size(&nucleus, period) * 2);
+        state.zoom.recip_mut();
+        let mut center = nucleus.clone();
+        center
+            .mut_real()
+            .add_assign(Float::with_val(PRECISION, 0.9 / &state.zoom));
+        bench("next to a minibrot", &state, &center);
 
-        (orbit, valid_count)
+        // Deep on a filament, nothing in reach survives so every candidate is scored
+        state.zoom.assign(1e20);
+        state.grow_precision();
+        let mut center = Complex::with_val(state.precision, &seahorse);
+        center
+            .mut_real()
+            .add_assign(Float::with_val(PRECISION, 0.74 / &state.zoom));
+        center
+            .mut_imag()
+            .add_assign(Float::with_val(PRECISION, 0.42 / &state.zoom));
+        bench("no survivor", &state, &center);
     }
 }
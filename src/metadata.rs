@@ -0,0 +1,245 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    str::FromStr,
+};
+
+use rug::{Assign, Float};
+
+use crate::{
+    math::{PRECISION, target_iterations},
+    primitives::Uniforms,
+    wgpu::WgpuState,
+};
+
+// tEXt chunk keywords
+const KEY_RE: &str = "mandelbrot_re";
+const KEY_IM: &str = "mandelbrot_im";
+const KEY_ZOOM: &str = "mandelbrot_zoom";
+const KEY_MAX_ITER: &str = "mandelbrot_max_iter";
+const KEY_COLOR_MODE: &str = "mandelbrot_color_mode";
+const KEY_COLOR_SCALE: &str = "mandelbrot_color_scale";
+const KEY_COLOR_EXPONENT: &str = "mandelbrot_color_exponent";
+const KEY_REBASE: &str = "mandelbrot_rebase";
+const KEY_STRIPE_FREQUENCY: &str = "mandelbrot_stripe_frequency";
+const KEY_ITERATION_OVERRIDE: &str = "mandelbrot_iteration_override";
+
+/// An RGBA image with 16 bits per channel, for exports that mustn't band.
+pub type Rgba16Image = image::ImageBuffer<image::Rgba<u16>, Vec<u16>>;
+
+/// Everything needed to reproduce a view, stored as text so no precision is lost.
+pub struct ViewMetadata {
+    pub re: String,
+    pub im: String,
+    pub zoom: String,
+    pub max_iter: u32,
+    pub iteration_override: Option<u32>, // Pinned count, None = zoom-based budget
+    pub color_mode: u32,
+    pub color_scale_mode: u32,
+    pub color_exponent: f32,
+    pub rebase: u32,
+    pub stripe_frequency: f32,
+}
+
+impl ViewMetadata {
+    pub fn from_state(state: &WgpuState<'_>) -> Self {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let uniforms = &state.uniform_data.uniforms;
+
+        Self {
+            re: fractal_state.camera.real().to_string_radix(10, None),
+            im: fractal_state.camera.imag().to_string_radix(10, None),
+            zoom: fractal_state.zoom.to_string_radix(10, None),
+            max_iter: uniforms.max_iter,
+            iteration_override: state.iteration_override,
+            color_mode: uniforms.color_mode,
+            color_scale_mode: uniforms.color_scale_mode,
+            color_exponent: uniforms.color_exponent,
+            rebase: uniforms.rebase,
+            stripe_frequency: uniforms.stripe_frequency,
+        }
+    }
+
+    /// Moves `state` to the stored view.
+    pub fn apply(&self, state: &mut WgpuState<'_>) -> Result<(), String> {
+        let re = parse_float(KEY_RE, &self.re)?;
+        let im = parse_float(KEY_IM, &self.im)?;
+        let zoom = parse_float(KEY_ZOOM, &self.zoom)?;
+
+        // Grow for the new zoom first, so the camera keeps its digits
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        fractal_state.zoom.assign(&zoom);
+        fractal_state.grow_precision();
+        fractal_state.camera.assign((&re, &im));
+        fractal_state.reference.assign(&fractal_state.camera);
+
+        // A pinned count wins over the zoom-based budget. Renders may have used more
+        // than the interactive ceiling, so the first frame grows to match.
+        state.iteration_override = None;
+        if let Some(iterations) = self.iteration_override {
+            state.pin_iterations(iterations)?;
+        }
+
+        let uniforms = &mut state.uniform_data.uniforms;
+        uniforms.color_mode = self.color_mode;
+        uniforms.color_scale_mode = self.color_scale_mode;
+        uniforms.color_exponent = self.color_exponent;
+        uniforms.rebase = self.rebase;
+        uniforms.stripe_frequency = self.stripe_frequency;
+
+        Ok(())
+    }
+
+    fn text_chunks(&self) -> Vec<(&'static str, String)> {
+        let mut chunks = vec![
+            (KEY_RE, self.re.clone()),
+            (KEY_IM, self.im.clone()),
+            (KEY_ZOOM, self.zoom.clone()),
+            (KEY_MAX_ITER, self.max_iter.to_string()),
+            (KEY_COLOR_MODE, self.color_mode.to_string()),
+            (KEY_COLOR_SCALE, self.color_scale_mode.to_string()),
+            (KEY_COLOR_EXPONENT, self.color_exponent.to_string()),
+            (KEY_REBASE, self.rebase.to_string()),
+            (KEY_STRIPE_FREQUENCY, self.stripe_frequency.to_string()),
+        ];
+        if let Some(iterations) = self.iteration_override {
+            chunks.push((KEY_ITERATION_OVERRIDE, iterations.to_string()));
+        }
+        chunks
+    }
+}
+
+/// Saves `image` as a PNG with the view stored in tEXt chunks.
+pub fn save_png(
+    image: &image::RgbaImage,
+    path: &Path,
+    metadata: &ViewMetadata,
+) -> Result<(), String> {
+    write_png(
+        path,
+        image.width(),
+        image.height(),
+        png::BitDepth::Eight,
+        image.as_raw(),
+        metadata,
+    )
+}
+
+/// `save_png` for 16-bit images.
+pub fn save_png_16(
+    image: &Rgba16Image,
+    path: &Path,
+    metadata: &ViewMetadata,
+) -> Result<(), String> {
+    // PNG stores 16-bit samples big-endian
+    let data: Vec<u8> = image
+        .as_raw()
+        .iter()
+        .flat_map(|channel| channel.to_be_bytes())
+        .collect();
+    write_png(
+        path,
+        image.width(),
+        image.height(),
+        png::BitDepth::Sixteen,
+        &data,
+        metadata,
+    )
+}
+
+/// Saves `image` as an OpenEXR file with 32-bit float channels. EXR has no room for
+/// the tEXt chunks, so the view isn't stored.
+pub fn save_exr(image: &image::Rgba32FImage, path: &Path) -> Result<(), String> {
+    let width = image.width() as usize;
+    let raw = image.as_raw();
+    exr::prelude::write_rgba_file(path, width, image.height() as usize, |x, y| {
+        let i = (y * width + x) * 4;
+        (raw[i], raw[i + 1], raw[i + 2], raw[i + 3])
+    })
+    .map_err(|e| e.to_string())
+}
+
+fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    data: &[u8],
+    metadata: &ViewMetadata,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(depth);
+    for (keyword, text) in metadata.text_chunks() {
+        encoder
+            .add_text_chunk(keyword.to_string(), text)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(data).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
+
+/// Reads the view stored by `save_png` back from an image file.
+pub fn read_png(path: &Path) -> Result<ViewMetadata, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let decoder = png::Decoder::new(BufReader::new(file));
+    let reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    let chunks = &reader.info().uncompressed_latin1_text;
+    let find = |keyword: &str| {
+        chunks
+            .iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.clone())
+            .ok_or_else(|| format!("missing '{}', not exported by this program?", keyword))
+    };
+
+    let zoom = find(KEY_ZOOM)?;
+    let max_iter = parse_value(KEY_MAX_ITER, &find(KEY_MAX_ITER)?)?;
+    let iteration_override = match find(KEY_ITERATION_OVERRIDE) {
+        Ok(value) => Some(parse_value(KEY_ITERATION_OVERRIDE, &value)?),
+        // Older exports didn't say, a count off the zoom-based budget must have been pinned
+        Err(_) => {
+            let auto = target_iterations(&parse_float(KEY_ZOOM, &zoom)?);
+            (max_iter != auto).then_some(max_iter)
+        }
+    };
+
+    Ok(ViewMetadata {
+        re: find(KEY_RE)?,
+        im: find(KEY_IM)?,
+        zoom,
+        max_iter,
+        iteration_override,
+        color_mode: parse_value(KEY_COLOR_MODE, &find(KEY_COLOR_MODE)?)?,
+        color_scale_mode: parse_value(KEY_COLOR_SCALE, &find(KEY_COLOR_SCALE)?)?,
+        color_exponent: parse_value(KEY_COLOR_EXPONENT, &find(KEY_COLOR_EXPONENT)?)?,
+        rebase: parse_value(KEY_REBASE, &find(KEY_REBASE)?)?,
+        // Older exports predate stripe coloring
+        stripe_frequency: match find(KEY_STRIPE_FREQUENCY) {
+            Ok(value) => parse_value(KEY_STRIPE_FREQUENCY, &value)?,
+            Err(_) => Uniforms::new().stripe_frequency,
+        },
+    })
+}
+
+fn parse_value<T: FromStr>(keyword: &str, value: &str) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid '{}' value '{}': {}", keyword, value, e))
+}
+
+fn parse_float(keyword: &str, value: &str) -> Result<Float, String> {
+    let parsed = Float::parse(value)
+        .map_err(|e| format!("invalid '{}' value '{}': {}", keyword, value, e))?;
+    Ok(Float::with_val(PRECISION, parsed))
+}
@@ -0,0 +1,63 @@
+use rug::{Complex, Float};
+
+use crate::math::PRECISION;
+
+// The inset always shows the whole set around this point
+pub const MINIMAP_CENTER: [f64; 2] = [-0.75, 0.0];
+pub const MINIMAP_ZOOM: f64 = 0.8;
+pub const MINIMAP_ASPECT: f64 = 1.5;
+// The full set needs few iterations, keeps the inset cheap
+pub const MINIMAP_MAX_ITER: u32 = 256;
+// Zoom applied when jumping to a clicked point
+pub const MINIMAP_JUMP_ZOOM: f64 = 10.0;
+
+// Inset height as a fraction of the window height
+const MINIMAP_HEIGHT_FRACTION: f32 = 0.25;
+// Gap between the inset and the window edges, in pixels
+const MINIMAP_MARGIN: f32 = 10.0;
+// Smallest marker half-size in inset NDC, so deep views still show where they are
+const MINIMAP_MIN_MARKER: f32 = 0.04;
+
+/// Pixel rectangle [x, y, width, height] of the inset in the bottom-right corner.
+pub fn minimap_rect(width: u32, height: u32) -> [f32; 4] {
+    let h = height as f32 * MINIMAP_HEIGHT_FRACTION;
+    let w = (h * MINIMAP_ASPECT as f32).min(width as f32 - 2.0 * MINIMAP_MARGIN);
+
+    [
+        width as f32 - w - MINIMAP_MARGIN,
+        height as f32 - h - MINIMAP_MARGIN,
+        w,
+        h,
+    ]
+}
+
+/// Converts a window pixel to the point it shows in the inset, or None if it's outside.
+/// Same mapping as the shader, but relative to the inset's own rectangle and view.
+pub fn minimap_point(rect: [f32; 4], x: f64, y: f64) -> Option<Complex> {
+    let [rx, ry, rw, rh] = rect.map(|v| v as f64);
+    if x < rx || x > rx + rw || y < ry || y > ry + rh {
+        return None;
+    }
+
+    let ndc_x = ((x - rx) / rw) * 2.0 - 1.0;
+    let ndc_y = 1.0 - ((y - ry) / rh) * 2.0;
+
+    let re = MINIMAP_CENTER[0] + ndc_x * MINIMAP_ASPECT / MINIMAP_ZOOM;
+    let im = MINIMAP_CENTER[1] + ndc_y / MINIMAP_ZOOM;
+
+    Some(Complex::with_val(PRECISION, (re, im)))
+}
+
+/// Rectangle in inset NDC [x0, y0, x1, y1] outlining the area the main view covers.
+pub fn view_marker(camera: &Complex, zoom: &Float, aspect: f32) -> [f32; 4] {
+    let zoom = zoom.to_f64();
+    let x = ((camera.real().to_f64() - MINIMAP_CENTER[0]) * MINIMAP_ZOOM / MINIMAP_ASPECT) as f32;
+    let y = ((camera.imag().to_f64() - MINIMAP_CENTER[1]) * MINIMAP_ZOOM) as f32;
+
+    let half_w = ((aspect as f64 / zoom) * MINIMAP_ZOOM / MINIMAP_ASPECT) as f32;
+    let half_h = (MINIMAP_ZOOM / zoom) as f32;
+    let half_w = half_w.max(MINIMAP_MIN_MARKER);
+    let half_h = half_h.max(MINIMAP_MIN_MARKER);
+
+    [x - half_w, y - half_h, x + half_w, y + half_h]
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+// Magic string and format version 1.0
+const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+// Magic, version, header length and header together are a multiple of this
+const HEADER_ALIGNMENT: usize = 64;
+
+/// Writes `values` as a NumPy `.npy` array of little-endian u32, for `numpy.load`.
+pub fn save_u32(path: &Path, shape: &[usize], values: &[u32]) -> io::Result<()> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    save(path, "<u4", shape, &bytes)
+}
+
+/// Writes `values` as a NumPy `.npy` array of little-endian f32.
+pub fn save_f32(path: &Path, shape: &[usize], values: &[f32]) -> io::Result<()> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    save(path, "<f4", shape, &bytes)
+}
+
+fn save(path: &Path, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+    // Python tuple syntax, a single dimension needs the trailing comma
+    let dims: Vec<String> = shape.iter().map(|dim| dim.to_string()).collect();
+    let shape = if dims.len() == 1 {
+        format!("({},)", dims[0])
+    } else {
+        format!("({})", dims.join(", "))
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+
+    // Pad with spaces and end in a newline, the 2 bytes hold the header length
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    let padding = unpadded.next_multiple_of(HEADER_ALIGNMENT) - unpadded;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(data)?;
+    writer.flush()
+}
@@ -0,0 +1,282 @@
+use std::{
+    sync::{
+        Arc, Condvar, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use rug::Assign;
+
+use crate::{
+    math::{FractalMode, HighPrecisionState, MAX_GLITCH_FRACTION, estimate_glitch_fraction},
+    precision::PrecisionMode,
+    primitives::GLITCH_MODE_REBASE,
+};
+
+// How far the reference may be from the camera before it's replaced even though it still
+// survives, in half view heights. Past this the f32 offsets lose too much precision.
+pub const MAX_REFERENCE_DISTANCE: f32 = 4.0;
+
+/// Settings besides the view that the reference search and orbit depend on.
+#[derive(Clone, Copy, PartialEq)]
+pub struct OrbitSettings {
+    pub aspect: f32,
+    pub rebase: u32,
+    pub glitch_mode: u32,
+    pub double_single: u32,
+    pub reference_locked: bool,
+    pub ocean_threshold: u32,
+    pub max_orbit_precision: u32,
+}
+
+/// The heavy part of an update: picking the reference and computing and checking its
+/// orbit. Owns everything it reads, so it can run away from the GPU state.
+pub struct OrbitJob {
+    pub id: u64, // Grows with every job, a higher id is a newer view
+    pub fractal_state: HighPrecisionState, // A copy of the view's, the search moves its reference
+    pub target_iters: u32,
+    pub zoom: f32,
+    pub settings: OrbitSettings,
+    pub force_camera_reference: bool, // Use the camera whatever the heuristic would pick
+}
+
+/// A finished `OrbitJob`, ready to upload.
+pub struct OrbitResult {
+    pub id: u64,
+    pub fractal_state: HighPrecisionState, // With the reference and orbit precision picked
+    pub orbit: Vec<[f32; 4]>,
+    pub valid_len: u32,
+    pub orbit_df: Option<Vec<[f32; 4]>>, // Only computed with double-single deltas on
+    pub manual_reference: Option<bool>,  // New `WgpuState::manual_reference`, None = keep it
+    pub glitch_fraction: f32,
+    pub precision_limited: bool, // Glitches outlasted `max_orbit_precision`
+}
+
+impl OrbitJob {
+    pub fn run(mut self) -> OrbitResult {
+        let target_iters = self.target_iters;
+        let settings = self.settings;
+        let fractal_state = &mut self.fractal_state;
+        let previous_reference = fractal_state.reference.clone();
+        let mut manual_reference = None;
+
+        if self.force_camera_reference {
+            // Manual override: use the camera whatever the heuristic would pick
+            fractal_state.reference.assign(&fractal_state.camera);
+            manual_reference = Some(true);
+        } else if !settings.reference_locked {
+            // Check Validity of current Reference and Camera
+            // We need to know if the points survive the NEW target iteration count
+            let current_ref_score =
+                fractal_state.get_escape_time(&fractal_state.reference, target_iters);
+            // A pan can leave the reference far behind even though its orbit is still fine
+            let [offset_x, offset_y] = fractal_state.reference_offset();
+            let in_reach = offset_x.hypot(offset_y) <= MAX_REFERENCE_DISTANCE;
+            let is_ref_valid = current_ref_score == target_iters && in_reach;
+
+            // Only the yes/no answer matters here, so interior cameras can bail out early
+            let is_camera_valid =
+                fractal_state.contains_periodic(&fractal_state.camera, target_iters);
+
+            // Update Reference Strategy
+            if is_camera_valid {
+                // Case A: Camera is safe.
+                // Best possible reference is the camera itself (minimizes float error).
+                fractal_state.reference.assign(&fractal_state.camera);
+            } else if !is_ref_valid && in_open_ocean(fractal_state, &settings) {
+                // Case B: Camera is in the ocean.
+                // Far out in the exterior there's no deep point to find, and pixels
+                // escape within a few iterations anyway. Rebasing carries them past
+                // the short camera orbit, so they're effectively iterated directly.
+                fractal_state.reference.assign(&fractal_state.camera);
+            } else if !is_ref_valid {
+                let (best_point, best_score) = fractal_state.find_best_reference(
+                    &fractal_state.camera,
+                    &fractal_state.zoom,
+                    target_iters,
+                    64,
+                );
+
+                if best_score > current_ref_score || !in_reach {
+                    fractal_state.reference.assign(&best_point);
+                }
+            }
+
+            // The heuristic took over again
+            if fractal_state.reference != previous_reference {
+                manual_reference = Some(false);
+            }
+        }
+
+        // A new reference starts over at the view's precision
+        if fractal_state.reference != previous_reference {
+            fractal_state.orbit_precision = fractal_state.precision;
+        }
+
+        // Calculate Orbit (Using the Reference)
+        let (mut orbit, mut valid_len) = fractal_state.calculate_orbit(target_iters);
+        let offset = fractal_state.reference_offset();
+
+        // If glitches persist with the best reference, the orbit itself may be
+        // under-resolved. Recompute at higher precision until it clears or hits the cap.
+        let (glitch_fraction, precision_limited) = loop {
+            // Rebased glitches are repaired in the shader, extra bits would only cost time.
+            // The estimate also only knows the Mandelbrot recurrence.
+            let glitch_fraction = if settings.glitch_mode == GLITCH_MODE_REBASE
+                || fractal_state.fractal_mode == FractalMode::Julia
+            {
+                0.0
+            } else {
+                estimate_glitch_fraction(
+                    &orbit,
+                    valid_len,
+                    offset,
+                    self.zoom,
+                    settings.aspect,
+                    target_iters,
+                    settings.rebase != 0,
+                )
+            };
+            let precision = fractal_state.orbit_precision;
+            // Forced f64 has a fixed width, extra bits wouldn't change anything
+            let forced_f64 = fractal_state.precision_mode == PrecisionMode::F64;
+            if glitch_fraction <= MAX_GLITCH_FRACTION
+                || precision >= settings.max_orbit_precision
+                || forced_f64
+            {
+                // Glitches that outlast the cap stay, the view is blocky rather than slow
+                break (
+                    glitch_fraction,
+                    glitch_fraction > MAX_GLITCH_FRACTION && !forced_f64,
+                );
+            }
+
+            let new_precision = (precision * 2).min(settings.max_orbit_precision);
+            println!(
+                "Glitches on {:.0}% of samples, raising orbit precision: {} -> {} bits",
+                glitch_fraction * 100.0,
+                precision,
+                new_precision
+            );
+            fractal_state.orbit_precision = new_precision;
+
+            (orbit, valid_len) = fractal_state.calculate_orbit(target_iters);
+        };
+
+        // The double-single orbit is a second pass in rug, only done while it's used
+        let orbit_df = (settings.double_single != 0).then(|| fractal_state.orbit_df(target_iters));
+        if let Some(orbit_df) = &orbit_df {
+            // rug may see the orbit escape a step before f64 did, don't read past its end
+            valid_len = valid_len.min(orbit_df.len() as u32);
+        }
+
+        OrbitResult {
+            id: self.id,
+            fractal_state: self.fractal_state,
+            orbit,
+            valid_len,
+            orbit_df,
+            manual_reference,
+            glitch_fraction,
+            precision_limited,
+        }
+    }
+}
+
+/// Whether the camera escapes within `ocean_threshold` iterations. Only trusted with
+/// rebasing on, since without it pixels can't outlive the camera's short orbit.
+fn in_open_ocean(fractal_state: &HighPrecisionState, settings: &OrbitSettings) -> bool {
+    settings.ocean_threshold > 0
+        && settings.rebase != 0
+        && fractal_state.get_escape_time(&fractal_state.camera, settings.ocean_threshold)
+            < settings.ocean_threshold
+}
+
+/// Runs `OrbitJob`s on a thread of its own, so the window keeps drawing the last orbit
+/// while the next one computes. Only the latest job matters: submitting while one is
+/// still waiting replaces it instead of queueing behind it.
+pub struct OrbitWorker {
+    pending: Arc<(Mutex<PendingJob>, Condvar)>,
+    results: Receiver<OrbitResult>,
+    ready: Option<OrbitResult>, // Newest result received, until taken
+}
+
+#[derive(Default)]
+struct PendingJob {
+    job: Option<OrbitJob>,
+    closed: bool, // The worker was dropped, the thread should exit
+}
+
+impl OrbitWorker {
+    pub fn spawn() -> Self {
+        let pending = Arc::new((Mutex::new(PendingJob::default()), Condvar::new()));
+        let (sender, results) = mpsc::channel();
+        let shared = pending.clone();
+        thread::Builder::new()
+            .name("orbit".to_string())
+            .spawn(move || run_jobs(&shared, &sender))
+            .expect("Failed to start the orbit thread");
+
+        Self {
+            pending,
+            results,
+            ready: None,
+        }
+    }
+
+    /// Queues `job`, dropping any job that hasn't started yet.
+    pub fn submit(&self, job: OrbitJob) {
+        let (lock, wake) = &*self.pending;
+        lock.lock().unwrap().job = Some(job);
+        wake.notify_one();
+    }
+
+    /// Collects finished jobs and returns whether one is waiting to be taken.
+    /// Results arrive in submission order, so only the last is kept.
+    pub fn poll(&mut self) -> bool {
+        if let Some(result) = self.results.try_iter().last() {
+            self.ready = Some(result);
+        }
+        self.ready.is_some()
+    }
+
+    /// The newest finished job, if one came in since the last call.
+    pub fn take(&mut self) -> Option<OrbitResult> {
+        self.poll();
+        self.ready.take()
+    }
+}
+
+impl Drop for OrbitWorker {
+    fn drop(&mut self) {
+        // Not joined, a deep orbit in progress would hold up closing the window
+        let (lock, wake) = &*self.pending;
+        lock.lock().unwrap().closed = true;
+        wake.notify_one();
+    }
+}
+
+fn run_jobs(pending: &(Mutex<PendingJob>, Condvar), results: &Sender<OrbitResult>) {
+    let (lock, wake) = pending;
+    loop {
+        let job = {
+            let mut pending = wake
+                .wait_while(lock.lock().unwrap(), |pending| {
+                    pending.job.is_none() && !pending.closed
+                })
+                .unwrap();
+            if pending.closed {
+                return;
+            }
+            pending.job.take()
+        };
+
+        // The receiver only goes away with the worker
+        if let Some(job) = job
+            && results.send(job.run()).is_err()
+        {
+            return;
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use winit::{event::WindowEvent, window::Window};
+
+use crate::{
+    animation::LANDMARKS,
+    cli::Palette,
+    precision::PrecisionMode,
+    primitives::{
+        COLOR_MODE_DISTANCE, COLOR_MODE_ESCAPE_TIME, COLOR_MODE_STRIPE, COLOR_MODE_TRAP,
+        COLOR_SCALE_LINEAR, COLOR_SCALE_LOG, COLOR_SCALE_POWER, COLOR_SCALE_SQRT, MAX_AA_SAMPLES,
+        PALETTE_COUNT,
+    },
+    scale::RENDER_SCALE_LEVELS,
+    wgpu::WgpuState,
+    window::iteration_multiplier,
+};
+
+// Width the panel opens at, in points
+const PANEL_WIDTH: f32 = 260.0;
+
+/// Something picked in the panel that the window carries out, since it owns the camera flights.
+pub enum PanelAction {
+    Landmark(usize), // Fly to `LANDMARKS[index]`
+    Home,
+}
+
+/// Settings window drawn with egui over the fractal, toggled with F1.
+pub struct SettingsPanel {
+    context: egui::Context,
+    input: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    pub visible: bool,
+    paint_jobs: Vec<egui::ClippedPrimitive>, // Tessellated by the last `build`
+    textures: egui::TexturesDelta,           // Font atlas changes waiting for the next render
+    pixels_per_point: f32,
+}
+
+impl SettingsPanel {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, window: &Window) -> Self {
+        let context = egui::Context::default();
+        let scale_factor = window.scale_factor() as f32;
+        let input = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(scale_factor),
+            None,
+            None,
+        );
+        let renderer =
+            egui_wgpu::Renderer::new(device, format, egui_wgpu::RendererOptions::default());
+
+        Self {
+            context,
+            input,
+            renderer,
+            visible: false,
+            paint_jobs: Vec::new(),
+            textures: egui::TexturesDelta::default(),
+            pixels_per_point: scale_factor,
+        }
+    }
+
+    /// Passes a window event to egui. Returns true when the panel used it, like a click or
+    /// scroll over a widget, so it shouldn't also pan or zoom the fractal.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let response = self.input.on_window_event(window, event);
+        if response.repaint {
+            window.request_redraw();
+        }
+        response.consumed
+    }
+
+    /// Lays out the panel for this frame, applying changed settings to `state` right away.
+    /// Returns what the window has to do on top, like jumping to a bookmark.
+    /// The stats at the top show `fps` and what the last `update` settled on.
+    pub fn build(
+        &mut self,
+        window: &Window,
+        state: &mut WgpuState<'_>,
+        fps: f32,
+    ) -> Vec<PanelAction> {
+        let mut actions = Vec::new();
+        if !self.visible {
+            self.paint_jobs.clear();
+            return actions;
+        }
+
+        let raw_input = self.input.take_egui_input(window);
+        let mut open = true;
+        let output = self.context.run_ui(raw_input, |ui| {
+            egui::Window::new("Settings")
+                .open(&mut open)
+                .resizable(false)
+                .default_width(PANEL_WIDTH)
+                .show(ui.ctx(), |ui| Self::contents(ui, state, fps, &mut actions));
+        });
+        self.visible = open;
+
+        self.input
+            .handle_platform_output(window, output.platform_output);
+        self.paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.pixels_per_point = output.pixels_per_point;
+        self.textures.append(output.textures_delta);
+
+        // Animations like an opening combo box need a few more frames
+        if self.context.has_requested_repaint() {
+            window.request_redraw();
+        }
+        actions
+    }
+
+    fn contents(
+        ui: &mut egui::Ui,
+        state: &mut WgpuState<'_>,
+        fps: f32,
+        actions: &mut Vec<PanelAction>,
+    ) {
+        egui::CollapsingHeader::new("Performance")
+            .default_open(true)
+            .show(ui, |ui| {
+                let fractal_state = &state.uniform_data.fractal_state;
+                ui.label(format!("FPS: {:.0}", fps));
+                ui.label(format!(
+                    "Zoom: 10^{:.2}",
+                    fractal_state.zoom.clone().log10().to_f64()
+                ));
+                ui.label(format!(
+                    "Iterations: {}",
+                    state.uniform_data.uniforms.max_iter
+                ));
+                ui.label(format!(
+                    "Series skip: {}",
+                    state.uniform_data.uniforms.series_skip
+                ));
+                ui.label(match state.bla_skip_ratio {
+                    Some(ratio) => format!("BLA: {:.1} iterations per step", ratio),
+                    None => "BLA: off".to_string(),
+                });
+                ui.checkbox(&mut state.progressive, "Progressive refinement");
+                // The view's bits follow the zoom, the orbit's also rise with glitches
+                ui.label(format!(
+                    "Precision: {} bits, orbit {}",
+                    fractal_state.precision, fractal_state.orbit_precision
+                ));
+            });
+
+        egui::CollapsingHeader::new("Coloring")
+            .default_open(true)
+            .show(ui, |ui| {
+                let has_trap_image = state.has_trap_image();
+                let uniforms = &mut state.uniform_data.uniforms;
+                egui::ComboBox::from_label("Mode")
+                    .selected_text(color_mode_name(uniforms.color_mode))
+                    .show_ui(ui, |ui| {
+                        let mut modes = vec![
+                            COLOR_MODE_ESCAPE_TIME,
+                            COLOR_MODE_DISTANCE,
+                            COLOR_MODE_STRIPE,
+                        ];
+                        if has_trap_image {
+                            modes.push(COLOR_MODE_TRAP);
+                        }
+                        for mode in modes {
+                            ui.selectable_value(
+                                &mut uniforms.color_mode,
+                                mode,
+                                color_mode_name(mode),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Palette")
+                    .selected_text(format!(
+                        "{:?}",
+                        Palette::from_uniform_value(uniforms.palette_id)
+                    ))
+                    .show_ui(ui, |ui| {
+                        for palette in 0..PALETTE_COUNT {
+                            ui.selectable_value(
+                                &mut uniforms.palette_id,
+                                palette,
+                                format!("{:?}", Palette::from_uniform_value(palette)),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Scale")
+                    .selected_text(color_scale_name(uniforms.color_scale_mode))
+                    .show_ui(ui, |ui| {
+                        for scale in [
+                            COLOR_SCALE_LINEAR,
+                            COLOR_SCALE_LOG,
+                            COLOR_SCALE_SQRT,
+                            COLOR_SCALE_POWER,
+                        ] {
+                            ui.selectable_value(
+                                &mut uniforms.color_scale_mode,
+                                scale,
+                                color_scale_name(scale),
+                            );
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut uniforms.color_exponent, 0.1..=4.0).text("Exponent"));
+                ui.add(
+                    egui::Slider::new(&mut uniforms.stripe_frequency, 1.0..=20.0)
+                        .text("Stripe frequency"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut uniforms.exterior_ramp, 0.0..=1000.0)
+                        .logarithmic(true)
+                        .text("Exterior ramp"),
+                );
+                let mut smooth = uniforms.smooth_coloring != 0;
+                if ui.checkbox(&mut smooth, "Smooth").changed() {
+                    uniforms.smooth_coloring = smooth as u32;
+                }
+
+                let mut auto_exposure = state.auto_exposure();
+                if ui.checkbox(&mut auto_exposure, "Auto exposure").changed() {
+                    state.set_auto_exposure(auto_exposure);
+                }
+            });
+
+        egui::CollapsingHeader::new("Iterations")
+            .default_open(true)
+            .show(ui, |ui| {
+                // Pinned counts ignore the multiplier, so don't offer it
+                ui.add_enabled_ui(state.iteration_override.is_none(), |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Halve").clicked() {
+                            state.shift_iterations(-1);
+                        }
+                        if ui.button("Double").clicked() {
+                            state.shift_iterations(1);
+                        }
+                    });
+                });
+                ui.label(format!(
+                    "{} ({})",
+                    state.uniform_data.uniforms.max_iter,
+                    iteration_multiplier(state.iteration_shift())
+                ));
+            });
+
+        egui::CollapsingHeader::new("Anti-aliasing")
+            .default_open(true)
+            .show(ui, |ui| {
+                let uniforms = &mut state.uniform_data.uniforms;
+                ui.add(
+                    egui::Slider::new(&mut uniforms.aa_samples, 1..=MAX_AA_SAMPLES)
+                        .text("Samples per axis"),
+                );
+                let mut boundary_aa = uniforms.boundary_aa != 0;
+                if ui.checkbox(&mut boundary_aa, "Boundary").changed() {
+                    uniforms.boundary_aa = boundary_aa as u32;
+                }
+                let mut msaa = state.msaa_enabled();
+                ui.add_enabled_ui(state.msaa_supported(), |ui| {
+                    if ui.checkbox(&mut msaa, "MSAA (overlays)").changed() {
+                        state.set_msaa(msaa);
+                    }
+                });
+
+                let current = state.render_scale();
+                egui::ComboBox::from_label("Render scale")
+                    .selected_text(format!("{}x", current))
+                    .show_ui(ui, |ui| {
+                        for level in RENDER_SCALE_LEVELS {
+                            if ui
+                                .selectable_label(level == current, format!("{}x", level))
+                                .clicked()
+                            {
+                                state.set_render_scale(level);
+                            }
+                        }
+                    });
+            });
+
+        egui::CollapsingHeader::new("Precision")
+            .default_open(true)
+            .show(ui, |ui| {
+                let fractal_state = &mut state.uniform_data.fractal_state;
+                egui::ComboBox::from_label("CPU precision")
+                    .selected_text(format!("{:?}", fractal_state.precision_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in [PrecisionMode::Auto, PrecisionMode::F64, PrecisionMode::Rug] {
+                            ui.selectable_value(
+                                &mut fractal_state.precision_mode,
+                                mode,
+                                format!("{:?}", mode),
+                            );
+                        }
+                    });
+                ui.label(format!("Using {}", fractal_state.orbit_math().name()));
+
+                let mut double_single = state.double_single();
+                if ui
+                    .checkbox(&mut double_single, "Double-single deltas")
+                    .changed()
+                {
+                    state.set_double_single(double_single);
+                }
+            });
+
+        egui::CollapsingHeader::new("Bookmarks")
+            .default_open(true)
+            .show(ui, |ui| {
+                if ui.button("Home").clicked() {
+                    actions.push(PanelAction::Home);
+                }
+                for (index, (name, ..)) in LANDMARKS.iter().enumerate() {
+                    if ui.button(*name).clicked() {
+                        actions.push(PanelAction::Landmark(index));
+                    }
+                }
+            });
+    }
+
+    /// Draws the panel laid out by the last `build` over `view`, keeping what's already there.
+    /// The returned command buffers hold egui's uploads and must be submitted before `encoder`.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+    ) -> Vec<wgpu::CommandBuffer> {
+        let textures = std::mem::take(&mut self.textures);
+        for (id, delta) in &textures.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: self.pixels_per_point,
+        };
+        let command_buffers =
+            self.renderer
+                .update_buffers(device, queue, encoder, &self.paint_jobs, &screen);
+
+        {
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Settings Panel Pass"),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                multiview_mask: None,
+
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &self.paint_jobs,
+                &screen,
+            );
+        }
+
+        for id in &textures.free {
+            self.renderer.free_texture(id);
+        }
+        command_buffers
+    }
+}
+
+fn color_mode_name(mode: u32) -> &'static str {
+    match mode {
+        COLOR_MODE_DISTANCE => "Distance estimate",
+        COLOR_MODE_STRIPE => "Stripe average",
+        COLOR_MODE_TRAP => "Image trap",
+        _ => "Escape time",
+    }
+}
+
+fn color_scale_name(scale: u32) -> &'static str {
+    match scale {
+        COLOR_SCALE_LOG => "Log",
+        COLOR_SCALE_SQRT => "Sqrt",
+        COLOR_SCALE_POWER => "Power",
+        _ => "Linear",
+    }
+}
@@ -0,0 +1,276 @@
+use rug::{Assign, Complex};
+use serde::{Deserialize, Serialize};
+
+use crate::math::escaped;
+
+// Below this zoom (log10) plain f64 resolves the reference orbit well enough
+pub const F64_MAX_LOG_ZOOM: f64 = 8.0;
+
+// Iterations before the first periodicity check, the interval doubles after each save
+const PERIOD_CHECK_START: u32 = 8;
+
+/// Which arithmetic the reference orbit and escape checks run in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrecisionMode {
+    /// f64 for shallow zooms, rug past `F64_MAX_LOG_ZOOM`
+    #[default]
+    Auto,
+    F64,
+    Rug,
+}
+
+impl PrecisionMode {
+    pub fn next(self) -> Self {
+        match self {
+            PrecisionMode::Auto => PrecisionMode::F64,
+            PrecisionMode::F64 => PrecisionMode::Rug,
+            PrecisionMode::Rug => PrecisionMode::Auto,
+        }
+    }
+}
+
+/// CPU-side iteration used for reference orbits and escape checks.
+/// The view itself (camera, zoom) always stays in rug so switching loses nothing.
+pub trait OrbitMath {
+    fn name(&self) -> &'static str;
+
+    /// Iterations until the orbit of `z0` under z^2 + `c` escapes, or `max_iter` if it
+    /// survives. Mandelbrot points start at `z0` = 0, Julia points at themselves.
+    /// Also `max_iter` as soon as the orbit comes back within `period_tolerance` (squared
+    /// distance) of a saved value, since it then cycles forever. Brent-style: the saved
+    /// value is replaced at doubling intervals, so any period is eventually caught.
+    /// 0 turns the check off.
+    fn escape_time(&self, z0: &Complex, c: &Complex, max_iter: u32, period_tolerance: f64) -> u32;
+
+    /// Reference orbit as [Z.re, Z.im, dZ.re, dZ.im] entries padded to `max_iter`,
+    /// plus the number of valid entries. `precision` is ignored by fixed-width backends.
+    fn orbit(&self, c: &Complex, max_iter: u32, precision: u32) -> (Vec<[f32; 4]>, u32);
+
+    /// Julia set orbit of `z0` under z^2 + `c`, laid out like `orbit` but with dZ/dz0
+    /// (starting at 1) as the derivative.
+    fn julia_orbit(
+        &self,
+        z0: &Complex,
+        c: &Complex,
+        max_iter: u32,
+        precision: u32,
+    ) -> (Vec<[f32; 4]>, u32);
+}
+
+/// Hardware doubles. Much faster, but only accurate at shallow zooms.
+pub struct F64Math;
+
+/// Arbitrary precision through rug (MPFR).
+pub struct RugMath;
+
+impl OrbitMath for F64Math {
+    fn name(&self) -> &'static str {
+        "f64"
+    }
+
+    fn escape_time(&self, z0: &Complex, c: &Complex, max_iter: u32, period_tolerance: f64) -> u32 {
+        let (c_re, c_im) = (c.real().to_f64(), c.imag().to_f64());
+        let (mut z_re, mut z_im) = (z0.real().to_f64(), z0.imag().to_f64());
+        let (mut saved_re, mut saved_im) = (z_re, z_im);
+        let mut check_interval = PERIOD_CHECK_START;
+        let mut steps_since_save = 0;
+
+        for i in 0..max_iter {
+            (z_re, z_im) = (z_re * z_re - z_im * z_im + c_re, 2.0 * z_re * z_im + c_im);
+            if z_re * z_re + z_im * z_im > 4.0 {
+                return i;
+            }
+
+            let (d_re, d_im) = (z_re - saved_re, z_im - saved_im);
+            if d_re * d_re + d_im * d_im < period_tolerance {
+                return max_iter;
+            }
+
+            steps_since_save += 1;
+            if steps_since_save == check_interval {
+                (saved_re, saved_im) = (z_re, z_im);
+                steps_since_save = 0;
+                check_interval *= 2;
+            }
+        }
+        max_iter
+    }
+
+    fn orbit(&self, c: &Complex, max_iter: u32, _precision: u32) -> (Vec<[f32; 4]>, u32) {
+        let mut orbit = Vec::with_capacity(max_iter as usize);
+        let (c_re, c_im) = (c.real().to_f64(), c.imag().to_f64());
+        let (mut z_re, mut z_im) = (0.0f64, 0.0f64);
+        let (mut dz_re, mut dz_im) = (0.0f64, 0.0f64);
+
+        let mut valid_count = 0;
+
+        for _ in 0..max_iter {
+            orbit.push([z_re as f32, z_im as f32, dz_re as f32, dz_im as f32]);
+            valid_count += 1;
+
+            // dz' = 2*z*dz + 1 (uses z before it is advanced)
+            (dz_re, dz_im) = (
+                2.0 * (z_re * dz_re - z_im * dz_im) + 1.0,
+                2.0 * (z_re * dz_im + z_im * dz_re),
+            );
+            (z_re, z_im) = (z_re * z_re - z_im * z_im + c_re, 2.0 * z_re * z_im + c_im);
+
+            if z_re * z_re + z_im * z_im > 4.0 {
+                break;
+            }
+        }
+
+        orbit.resize(max_iter as usize, [0.0; 4]);
+
+        (orbit, valid_count)
+    }
+
+    fn julia_orbit(
+        &self,
+        z0: &Complex,
+        c: &Complex,
+        max_iter: u32,
+        _precision: u32,
+    ) -> (Vec<[f32; 4]>, u32) {
+        let mut orbit = Vec::with_capacity(max_iter as usize);
+        let (c_re, c_im) = (c.real().to_f64(), c.imag().to_f64());
+        let (mut z_re, mut z_im) = (z0.real().to_f64(), z0.imag().to_f64());
+        let (mut dz_re, mut dz_im) = (1.0f64, 0.0f64);
+
+        let mut valid_count = 0;
+
+        for _ in 0..max_iter {
+            orbit.push([z_re as f32, z_im as f32, dz_re as f32, dz_im as f32]);
+            valid_count += 1;
+
+            // dz' = 2*z*dz, no +1 since c doesn't vary
+            (dz_re, dz_im) = (
+                2.0 * (z_re * dz_re - z_im * dz_im),
+                2.0 * (z_re * dz_im + z_im * dz_re),
+            );
+            (z_re, z_im) = (z_re * z_re - z_im * z_im + c_re, 2.0 * z_re * z_im + c_im);
+
+            if z_re * z_re + z_im * z_im > 4.0 {
+                break;
+            }
+        }
+
+        orbit.resize(max_iter as usize, [0.0; 4]);
+
+        (orbit, valid_count)
+    }
+}
+
+impl OrbitMath for RugMath {
+    fn name(&self) -> &'static str {
+        "rug"
+    }
+
+    fn escape_time(&self, z0: &Complex, c: &Complex, max_iter: u32, period_tolerance: f64) -> u32 {
+        let mut z = z0.clone();
+        let mut saved = z.clone();
+        let mut diff = Complex::new(z.prec()); // Reused, the loop shouldn't allocate
+        let mut check_interval = PERIOD_CHECK_START;
+        let mut steps_since_save = 0;
+
+        for i in 0..max_iter {
+            z.square_mut();
+            z += c;
+
+            if escaped(&z) {
+                return i;
+            }
+
+            // Subtracted in rug, deep views compare differences far below f64's epsilon
+            diff.assign(&z - &saved);
+            let (d_re, d_im) = (diff.real().to_f64(), diff.imag().to_f64());
+            if d_re * d_re + d_im * d_im < period_tolerance {
+                return max_iter; // Back where it was, so it cycles forever
+            }
+
+            steps_since_save += 1;
+            if steps_since_save == check_interval {
+                saved.assign(&z);
+                steps_since_save = 0;
+                check_interval *= 2;
+            }
+        }
+        max_iter // Survived!
+    }
+
+    fn orbit(&self, c: &Complex, max_iter: u32, precision: u32) -> (Vec<[f32; 4]>, u32) {
+        let mut orbit = Vec::with_capacity(max_iter as usize);
+        let mut z = Complex::with_val(precision, (0.0, 0.0));
+        let mut dz = Complex::with_val(precision, (0.0, 0.0));
+        let one = Complex::with_val(precision, (1.0, 0.0));
+
+        let mut valid_count = 0; // Track valid iterations
+
+        for _ in 0..max_iter {
+            orbit.push([
+                z.real().to_f32(),
+                z.imag().to_f32(),
+                dz.real().to_f32(),
+                dz.imag().to_f32(),
+            ]);
+            valid_count += 1; // We added a valid point
+
+            // dz' = 2*z*dz + 1 (uses z before it is advanced)
+            dz *= &z;
+            dz *= 2;
+            dz += &one;
+
+            z.square_mut();
+            z += c;
+
+            if escaped(&z) {
+                break;
+            }
+        }
+
+        // Fill the rest with zeros (Padding)
+        orbit.resize(max_iter as usize, [0.0; 4]);
+
+        (orbit, valid_count)
+    }
+
+    fn julia_orbit(
+        &self,
+        z0: &Complex,
+        c: &Complex,
+        max_iter: u32,
+        precision: u32,
+    ) -> (Vec<[f32; 4]>, u32) {
+        let mut orbit = Vec::with_capacity(max_iter as usize);
+        let mut z = Complex::with_val(precision, z0);
+        let mut dz = Complex::with_val(precision, (1.0, 0.0));
+
+        let mut valid_count = 0;
+
+        for _ in 0..max_iter {
+            orbit.push([
+                z.real().to_f32(),
+                z.imag().to_f32(),
+                dz.real().to_f32(),
+                dz.imag().to_f32(),
+            ]);
+            valid_count += 1;
+
+            // dz' = 2*z*dz, no +1 since c doesn't vary
+            dz *= &z;
+            dz *= 2;
+
+            z.square_mut();
+            z += c;
+
+            if escaped(&z) {
+                break;
+            }
+        }
+
+        orbit.resize(max_iter as usize, [0.0; 4]);
+
+        (orbit, valid_count)
+    }
+}
@@ -28,7 +28,31 @@ pub const QUAD_VERTICES: &[Vertex] = &[
     }, // Top Right
 ];
 
+// Four edges of a rectangle as a line list
+pub const RECT_OUTLINE_VERTEX_COUNT: u32 = 8;
+
 impl Vertex {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            position: [x, y, 0.0],
+        }
+    }
+
+    /// Line list tracing the outline of the rectangle between two NDC corners.
+    pub fn rect_outline(rect: [f32; 4]) -> [Vertex; RECT_OUTLINE_VERTEX_COUNT as usize] {
+        let [x0, y0, x1, y1] = rect;
+        [
+            Vertex::new(x0, y0),
+            Vertex::new(x1, y0),
+            Vertex::new(x1, y0),
+            Vertex::new(x1, y1),
+            Vertex::new(x1, y1),
+            Vertex::new(x0, y1),
+            Vertex::new(x0, y1),
+            Vertex::new(x0, y0),
+        ]
+    }
+
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             // (Should be 12 bytes: 3 floats)
@@ -46,15 +70,92 @@ impl Vertex {
     }
 }
 
+/// One entry of the bilinear approximation table: while |dz| < `radius`, a run of steps
+/// along the reference orbit is dz -> a*dz + b*dc. Mirrors `BlaStep` in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BlaStep {
+    pub a: [f32; 2], // Offset 0 (8 bytes)
+    pub b: [f32; 2], // Offset 8 (8 bytes)
+    pub radius: f32, // Offset 16 (4 bytes), in complex units, 0 = never valid
+    pub _padding: u32, // Offset 20 (4 bytes), array stride rounds up to 8
+                     // Total Size: 24 bytes
+}
+
+// Values for `Uniforms::color_mode`, must match the shader
+pub const COLOR_MODE_ESCAPE_TIME: u32 = 0;
+pub const COLOR_MODE_DISTANCE: u32 = 1;
+pub const COLOR_MODE_STRIPE: u32 = 2; // Stripe average, uses `stripe_frequency`
+pub const COLOR_MODE_TRAP: u32 = 3; // Image orbit trap, uses `trap_center` and `trap_size`
+
+// Values for `Uniforms::fractal_mode`, must match the shader
+pub const FRACTAL_MODE_MANDELBROT: u32 = 0;
+pub const FRACTAL_MODE_JULIA: u32 = 1; // The orbit buffer holds a Julia orbit
+
+// Values for `Uniforms::glitch_mode`, what happens to pixels where perturbation breaks down
+pub const GLITCH_MODE_SHOW: u32 = 0; // Draw whatever the iteration produced
+pub const GLITCH_MODE_COLOR: u32 = 1; // Paint them in `glitch_color`
+pub const GLITCH_MODE_FILL: u32 = 2; // Mark them for the blit pass to fill from their neighbors
+pub const GLITCH_MODE_REBASE: u32 = 3; // Restart them from the start of the reference orbit
+
+// Largest `Uniforms::aa_samples`, 4x4 = 16 full iterations per pixel is already very slow
+pub const MAX_AA_SAMPLES: u32 = 4;
+
+// Values for `Uniforms::color_scale_mode`, how iterations map to palette position
+pub const COLOR_SCALE_LINEAR: u32 = 0;
+pub const COLOR_SCALE_LOG: u32 = 1;
+pub const COLOR_SCALE_SQRT: u32 = 2;
+pub const COLOR_SCALE_POWER: u32 = 3; // Uses `color_exponent`
+
+// Values for `Uniforms::palette_id`, the gradients live in the shader
+pub const PALETTE_RAINBOW: u32 = 0; // Phase-shifted sines, the original look
+pub const PALETTE_ULTRA_FRACTAL: u32 = 1;
+pub const PALETTE_FIRE: u32 = 2;
+pub const PALETTE_GRAYSCALE: u32 = 3;
+pub const PALETTE_BLUE: u32 = 4;
+pub const PALETTE_COUNT: u32 = 5;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
-    pub zoom: f32,        // Offset 0  (4 bytes)
-    pub aspect: f32,      // Offset 4  (4 bytes)
-    pub offset: [f32; 2], // Offset 8  (8 bytes)
-    pub iter_count: u32,  // Offset 16 (4 bytes)
-    pub _padding: [u32; 3], // Offset 20 (12 bytes)
-                          // Total Size: 32 bytes
+    pub zoom: f32,                 // Offset 0  (4 bytes)
+    pub aspect: f32,               // Offset 4  (4 bytes)
+    pub offset: [f32; 2],          // Offset 8  (8 bytes)
+    pub iter_count: u32,           // Offset 16 (4 bytes)
+    pub color_mode: u32,           // Offset 20 (4 bytes)
+    pub max_iter: u32,             // Offset 24 (4 bytes)
+    pub rebase: u32,               // Offset 28 (4 bytes)
+    pub color_scale_mode: u32,     // Offset 32 (4 bytes)
+    pub color_exponent: f32,       // Offset 36 (4 bytes)
+    pub exterior_ramp: f32,        // Offset 40 (4 bytes)
+    pub transparent_interior: u32, // Offset 44 (4 bytes), 1 = interior alpha is 0
+    pub exterior_color: [f32; 4],  // Offset 48 (16 bytes), alpha = blend strength
+    pub stripe_frequency: f32,     // Offset 64 (4 bytes)
+    pub pixel_size: f32,           // Offset 68 (4 bytes), in complex units
+    pub boundary_aa: u32,          // Offset 72 (4 bytes), 1 = blend edge pixels by DE coverage
+    pub fractal_mode: u32,         // Offset 76 (4 bytes)
+    pub aa_samples: u32,           // Offset 80 (4 bytes), supersampling grid per axis
+    pub precision_view: u32,       // Offset 84 (4 bytes), 1 = show f32 precision loss
+    pub rotation: [f32; 2],        // Offset 88 (8 bytes), [cos, sin] of the view angle
+    pub trap_center: [f32; 2],     // Offset 96 (8 bytes), middle of the trap image
+    pub trap_size: [f32; 2],       // Offset 104 (8 bytes), in complex units, 0 = no image
+    pub color_offset: f32, // Offset 112 (4 bytes), subtracted from iterations before coloring
+    pub color_gain: f32,   // Offset 116 (4 bytes), scales iterations after the offset
+    pub raw_output: u32,   // Offset 120 (4 bytes), 1 = write escape counts instead of colors
+    pub glitch_mode: u32,  // Offset 124 (4 bytes)
+    pub glitch_color: [f32; 4], // Offset 128 (16 bytes), alpha unused
+    pub smooth_coloring: u32, // Offset 144 (4 bytes), 1 = continuous count, 0 = integer bands
+    pub palette_id: u32,   // Offset 148 (4 bytes), one of the PALETTE_* gradients
+    pub double_single: u32, // Offset 152 (4 bytes), 1 = iterate deltas in double-single
+    pub series_skip: u32,  // Offset 156 (4 bytes), steps the series approximation skips
+    pub series_a: [f32; 2], // Offset 160 (8 bytes), series coefficients, see `SeriesApproximation`
+    pub series_b: [f32; 2], // Offset 168 (8 bytes)
+    pub series_c: [f32; 2], // Offset 176 (8 bytes)
+    pub series_radius: f32, // Offset 184 (4 bytes), in complex units, pixels divide dc by it
+    pub bla_len: u32,      // Offset 188 (4 bytes), single steps in the BLA table
+    pub bla_levels: u32,   // Offset 192 (4 bytes), 0 = no BLA table
+    pub _padding: [u32; 3], // Offset 196 (12 bytes), WGSL rounds the struct up to 16
+                           // Total Size: 208 bytes
 }
 
 impl Uniforms {
@@ -64,7 +165,70 @@ impl Uniforms {
             aspect: 1.0, // Default square aspect
             offset: [0.0; 2],
             iter_count: 0,
+            color_mode: COLOR_MODE_ESCAPE_TIME,
+            max_iter: 0,
+            rebase: 1, // Rebasing on by default
+            color_scale_mode: COLOR_SCALE_LINEAR,
+            color_exponent: 1.0,
+            exterior_ramp: 0.0,
+            transparent_interior: 0,
+            exterior_color: [0.0; 4], // Transparent = off
+            stripe_frequency: 5.0,
+            pixel_size: 0.0,
+            boundary_aa: 0,
+            fractal_mode: FRACTAL_MODE_MANDELBROT,
+            aa_samples: 1,
+            precision_view: 0,
+            rotation: [1.0, 0.0], // Unrotated
+            trap_center: [0.0; 2],
+            trap_size: [0.0; 2],
+            color_offset: 0.0,
+            color_gain: 1.0,
+            raw_output: 0,
+            glitch_mode: GLITCH_MODE_SHOW,
+            glitch_color: [1.0, 0.0, 1.0, 1.0], // Magenta, nothing in the palettes looks like it
+            smooth_coloring: 1,
+            palette_id: PALETTE_RAINBOW,
+            double_single: 0,
+            series_skip: 0, // Nothing fitted yet
+            series_a: [0.0; 2],
+            series_b: [0.0; 2],
+            series_c: [0.0; 2],
+            series_radius: 0.0,
+            bla_len: 0,
+            bla_levels: 0, // Off until a table is uploaded
             _padding: [0; 3],
         }
     }
+
+    /// Takes over the coloring settings of `main`, for insets that only differ in framing.
+    pub fn copy_coloring(&mut self, main: &Uniforms) {
+        self.color_mode = main.color_mode;
+        self.rebase = main.rebase;
+        self.color_scale_mode = main.color_scale_mode;
+        self.color_exponent = main.color_exponent;
+        self.exterior_color = main.exterior_color;
+        self.exterior_ramp = main.exterior_ramp;
+        self.stripe_frequency = main.stripe_frequency;
+        self.smooth_coloring = main.smooth_coloring;
+        self.palette_id = main.palette_id;
+        self.boundary_aa = main.boundary_aa;
+        self.trap_center = main.trap_center;
+        self.trap_size = main.trap_size;
+    }
+
+    /// Sets how glitched pixels are drawn, `color` is used by `GLITCH_MODE_COLOR`.
+    pub fn set_glitches(&mut self, mode: u32, color: [f32; 3]) {
+        self.glitch_mode = mode;
+        self.glitch_color = [color[0], color[1], color[2], 1.0];
+    }
+
+    /// Turns on the exterior color, or off with `None`.
+    pub fn set_exterior(&mut self, color: Option<[f32; 3]>, ramp: f32) {
+        self.exterior_color = match color {
+            Some([r, g, b]) => [r, g, b, 1.0],
+            None => [0.0; 4],
+        };
+        self.exterior_ramp = ramp.max(0.0);
+    }
 }
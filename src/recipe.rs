@@ -0,0 +1,368 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rug::Assign;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::{
+        BitDepth, ColorScale, Glitches, Palette, RenderArgs, Resample, format_hex_color,
+        parse_hex_color,
+    },
+    math::PRECISION,
+    precision::PrecisionMode,
+    primitives::{
+        COLOR_MODE_DISTANCE, COLOR_MODE_ESCAPE_TIME, COLOR_MODE_STRIPE, COLOR_MODE_TRAP,
+        MAX_AA_SAMPLES,
+    },
+    render::parse_float,
+    wgpu::WgpuState,
+};
+
+/// Format version written into new recipes. Bump it when a field changes meaning.
+pub const RECIPE_VERSION: u32 = 1;
+
+/// Every setting that affects a render, saved as TOML so an image can be reproduced
+/// exactly or shared. Unlike the PNG metadata it also covers anti-aliasing and the output.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    pub version: u32,
+    pub view: ViewSection,
+    pub iterations: IterationSection,
+    pub precision: PrecisionSection,
+    pub coloring: ColoringSection,
+    pub anti_aliasing: AntiAliasingSection,
+    pub output: OutputSection,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ViewSection {
+    pub re: String, // Text, so no precision is lost
+    pub im: String,
+    pub zoom: String,
+    pub rotation: f64, // Degrees counterclockwise
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IterationSection {
+    pub max_iter: u32, // Always pinned, the zoom-based budget may change between versions
+    pub rebase: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PrecisionSection {
+    pub mode: PrecisionMode,
+    pub max_precision: u32, // Bits
+    #[serde(default)]
+    pub double_single: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ColoringSection {
+    pub mode: ColorMode,
+    pub scale: ColorScale,
+    pub exponent: f32,
+    pub stripe_frequency: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exterior_color: Option<String>, // RRGGBB
+    pub exterior_ramp: f32,
+    pub glitches: Glitches,
+    pub glitch_color: String, // RRGGBB
+    #[serde(default)] // Recipes from before the toggle were smooth
+    pub banded: bool,
+    #[serde(default)] // Rainbow, the only palette before there was a choice
+    pub palette: Palette,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trap: Option<TrapSection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrapSection {
+    pub image: PathBuf,
+    pub center: [f32; 2],
+    pub size: f32, // Height in complex units
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AntiAliasingSection {
+    pub shader_aa: u32, // Grid per axis in the shader
+    pub boundary_aa: bool,
+    pub supersample: u32, // Render size over output size, per axis
+    pub resample: Resample,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSection {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u32, // 8 or 16
+}
+
+/// `Uniforms::color_mode` by name.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMode {
+    EscapeTime,
+    Distance,
+    Stripe,
+    Trap,
+}
+
+impl ColorMode {
+    fn from_uniform_value(value: u32) -> Self {
+        match value {
+            COLOR_MODE_DISTANCE => ColorMode::Distance,
+            COLOR_MODE_STRIPE => ColorMode::Stripe,
+            COLOR_MODE_TRAP => ColorMode::Trap,
+            _ => ColorMode::EscapeTime,
+        }
+    }
+
+    fn uniform_value(self) -> u32 {
+        match self {
+            ColorMode::EscapeTime => COLOR_MODE_ESCAPE_TIME,
+            ColorMode::Distance => COLOR_MODE_DISTANCE,
+            ColorMode::Stripe => COLOR_MODE_STRIPE,
+            ColorMode::Trap => COLOR_MODE_TRAP,
+        }
+    }
+}
+
+// Just the version, read first so newer recipes fail with a clear message
+// instead of complaining about fields this version doesn't know
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u32,
+}
+
+impl Recipe {
+    /// Captures the current view and settings for an output of `width`x`height`.
+    /// The state doesn't keep the trap image's path, so that has to be passed in.
+    pub fn from_state(
+        state: &WgpuState<'_>,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        trap_image: Option<&Path>,
+    ) -> Self {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let uniforms = &state.uniform_data.uniforms;
+
+        let exterior_color = (uniforms.exterior_color[3] > 0.0).then(|| {
+            let [r, g, b, _] = uniforms.exterior_color;
+            format_hex_color([r, g, b])
+        });
+        let [r, g, b, _] = uniforms.glitch_color;
+        let trap = trap_image
+            .filter(|_| state.has_trap_image())
+            .map(|image| TrapSection {
+                image: image.to_path_buf(),
+                center: uniforms.trap_center,
+                size: uniforms.trap_size[1],
+            });
+
+        Self {
+            version: RECIPE_VERSION,
+            view: ViewSection {
+                re: fractal_state.camera.real().to_string_radix(10, None),
+                im: fractal_state.camera.imag().to_string_radix(10, None),
+                zoom: fractal_state.zoom.to_string_radix(10, None),
+                rotation: state.rotation.to_degrees(),
+            },
+            iterations: IterationSection {
+                max_iter: uniforms.max_iter,
+                rebase: uniforms.rebase != 0,
+            },
+            precision: PrecisionSection {
+                mode: fractal_state.precision_mode,
+                max_precision: state.max_orbit_precision,
+                double_single: state.double_single(),
+            },
+            coloring: ColoringSection {
+                mode: ColorMode::from_uniform_value(uniforms.color_mode),
+                scale: ColorScale::from_uniform_value(uniforms.color_scale_mode),
+                exponent: uniforms.color_exponent,
+                stripe_frequency: uniforms.stripe_frequency,
+                exterior_color,
+                exterior_ramp: uniforms.exterior_ramp,
+                glitches: Glitches::from_uniform_value(uniforms.glitch_mode),
+                glitch_color: format_hex_color([r, g, b]),
+                banded: uniforms.smooth_coloring == 0,
+                palette: Palette::from_uniform_value(uniforms.palette_id),
+                trap,
+            },
+            anti_aliasing: AntiAliasingSection {
+                shader_aa: uniforms.aa_samples,
+                boundary_aa: uniforms.boundary_aa != 0,
+                supersample: 1,
+                resample: Resample::Box,
+            },
+            output: OutputSection {
+                width,
+                height,
+                bit_depth: match bit_depth {
+                    BitDepth::Eight => 8,
+                    BitDepth::Sixteen => 16,
+                },
+            },
+        }
+    }
+
+    /// Reads and validates a recipe file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        let VersionOnly { version } = toml::from_str(&text).map_err(|e| e.to_string())?;
+        if version > RECIPE_VERSION {
+            return Err(format!(
+                "recipe version {} is newer than this program supports ({})",
+                version, RECIPE_VERSION
+            ));
+        }
+        if version == 0 {
+            return Err("recipe version must be at least 1".to_string());
+        }
+
+        let recipe: Recipe = toml::from_str(&text).map_err(|e| e.to_string())?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+
+    /// Checks everything `apply` and the renderer would otherwise trip over halfway.
+    fn validate(&self) -> Result<(), String> {
+        parse_float("re", &self.view.re)?;
+        parse_float("im", &self.view.im)?;
+        if parse_float("zoom", &self.view.zoom)? <= 0 {
+            return Err(format!("zoom must be positive, got '{}'", self.view.zoom));
+        }
+        if !self.view.rotation.is_finite() {
+            return Err("rotation must be a finite number of degrees".to_string());
+        }
+
+        if self.iterations.max_iter == 0 {
+            return Err("max_iter must be at least 1".to_string());
+        }
+        if self.precision.max_precision < PRECISION {
+            return Err(format!("max_precision must be at least {}", PRECISION));
+        }
+
+        let coloring = &self.coloring;
+        if let Some(color) = &coloring.exterior_color {
+            parse_hex_color(color)?;
+        }
+        parse_hex_color(&coloring.glitch_color)?;
+        if coloring.mode == ColorMode::Trap && coloring.trap.is_none() {
+            return Err("the trap coloring needs a [coloring.trap] image".to_string());
+        }
+        if let Some(trap) = &coloring.trap
+            && !(trap.size > 0.0 && trap.size.is_finite())
+        {
+            return Err(format!("trap size must be positive, got {}", trap.size));
+        }
+
+        let anti_aliasing = &self.anti_aliasing;
+        if !(1..=MAX_AA_SAMPLES).contains(&anti_aliasing.shader_aa) {
+            return Err(format!(
+                "shader_aa must be between 1 and {}",
+                MAX_AA_SAMPLES
+            ));
+        }
+        if anti_aliasing.supersample == 0 {
+            return Err("supersample must be at least 1".to_string());
+        }
+
+        if self.output.width == 0 || self.output.height == 0 {
+            return Err("output width and height must be greater than zero".to_string());
+        }
+        self.bit_depth()?;
+
+        Ok(())
+    }
+
+    pub fn bit_depth(&self) -> Result<BitDepth, String> {
+        match self.output.bit_depth {
+            8 => Ok(BitDepth::Eight),
+            16 => Ok(BitDepth::Sixteen),
+            other => Err(format!("bit_depth must be 8 or 16, got {}", other)),
+        }
+    }
+
+    /// `args` with the view, iterations and output replaced by the recipe's.
+    pub fn render_args(&self, args: &RenderArgs) -> RenderArgs {
+        RenderArgs {
+            re: self.view.re.clone(),
+            im: self.view.im.clone(),
+            zoom: self.view.zoom.clone(),
+            width: self.output.width,
+            height: self.output.height,
+            max_iter: Some(self.iterations.max_iter),
+            aa: self.anti_aliasing.supersample,
+            resample: self.anti_aliasing.resample,
+            ..args.clone()
+        }
+    }
+
+    /// Moves `state` to the recipe's view and settings.
+    pub fn apply(&self, state: &mut WgpuState<'_>) -> Result<(), String> {
+        let re = parse_float("re", &self.view.re)?;
+        let im = parse_float("im", &self.view.im)?;
+        let zoom = parse_float("zoom", &self.view.zoom)?;
+
+        // Grow for the new zoom first, so the camera keeps its digits
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        fractal_state.zoom.assign(&zoom);
+        fractal_state.grow_precision();
+        fractal_state.camera.assign((&re, &im));
+        fractal_state.reference.assign(&fractal_state.camera);
+        fractal_state.precision_mode = self.precision.mode;
+        state.max_orbit_precision = self.precision.max_precision;
+        state.set_double_single(self.precision.double_single);
+        state.rotation = self.view.rotation.to_radians();
+
+        state.iteration_override = None;
+        state.pin_iterations(self.iterations.max_iter)?;
+
+        let coloring = &self.coloring;
+        if let Some(trap) = &coloring.trap {
+            state
+                .load_trap_image(&trap.image, trap.center, trap.size)
+                .map_err(|e| format!("failed to load the trap image: {}", e))?;
+        }
+        let exterior_color = coloring
+            .exterior_color
+            .as_deref()
+            .map(parse_hex_color)
+            .transpose()?;
+        let glitch_color = parse_hex_color(&coloring.glitch_color)?;
+
+        let uniforms = &mut state.uniform_data.uniforms;
+        uniforms.rebase = self.iterations.rebase as u32;
+        uniforms.color_mode = coloring.mode.uniform_value();
+        uniforms.color_scale_mode = coloring.scale.uniform_value();
+        uniforms.color_exponent = coloring.exponent;
+        uniforms.stripe_frequency = coloring.stripe_frequency;
+        uniforms.smooth_coloring = !coloring.banded as u32;
+        uniforms.palette_id = coloring.palette.uniform_value();
+        uniforms.set_exterior(exterior_color, coloring.exterior_ramp);
+        uniforms.set_glitches(coloring.glitches.uniform_value(), glitch_color);
+        uniforms.aa_samples = self.anti_aliasing.shader_aa;
+        uniforms.boundary_aa = self.anti_aliasing.boundary_aa as u32;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::path::Path;
+
+use image::{
+    ImageBuffer, Pixel, Rgba,
+    imageops::{self, FilterType},
+};
+use rug::{Complex, Float};
+
+use crate::{
+    cli::{Args, BitDepth, RenderArgs, Resample},
+    math::{BITS_PER_DECADE, PRECISION},
+    metadata::{ViewMetadata, save_exr, save_png, save_png_16},
+    npy,
+    primitives::COLOR_MODE_TRAP,
+    recipe::Recipe,
+    wgpu::{RenderParams, WgpuState},
+};
+
+// An RGBA image with any channel type, so 8-bit, 16-bit and float renders share the downsampling
+pub type RgbaBuffer<S> = ImageBuffer<Rgba<S>, Vec<S>>;
+
+/// Channel types `downsample` can average. Integers sum exactly in a u64, floats in an f64.
+pub trait Channel: Copy {
+    type Sum: Copy + Default + std::ops::AddAssign;
+
+    fn widen(self) -> Self::Sum;
+    fn average(sum: Self::Sum, count: u32) -> Self;
+}
+
+impl Channel for u8 {
+    type Sum = u64;
+
+    fn widen(self) -> u64 {
+        self.into()
+    }
+
+    fn average(sum: u64, count: u32) -> Self {
+        // An average never exceeds the largest sample, so it always fits
+        (sum / count as u64) as u8
+    }
+}
+
+impl Channel for u16 {
+    type Sum = u64;
+
+    fn widen(self) -> u64 {
+        self.into()
+    }
+
+    fn average(sum: u64, count: u32) -> Self {
+        (sum / count as u64) as u16
+    }
+}
+
+impl Channel for f32 {
+    type Sum = f64;
+
+    fn widen(self) -> f64 {
+        self.into()
+    }
+
+    fn average(sum: f64, count: u32) -> Self {
+        (sum / count as f64) as f32
+    }
+}
+
+/// Renders a single view to a PNG without opening a window.
+pub async fn run(args: &RenderArgs, global: &Args) -> Result<(), String> {
+    // A recipe replaces the view and output flags here, the rest of it goes to the state below
+    let recipe = match &global.recipe {
+        Some(path) => Some(
+            Recipe::load(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+    let args = &match &recipe {
+        Some(recipe) => recipe.render_args(args),
+        None => args.clone(),
+    };
+    let bit_depth = match &recipe {
+        Some(recipe) => recipe.bit_depth()?,
+        None => global.bit_depth,
+    };
+
+    if args.width == 0 || args.height == 0 {
+        return Err("width and height must be greater than zero".to_string());
+    }
+    if args.aa == 0 {
+        return Err("--aa must be at least 1".to_string());
+    }
+
+    let re = parse_float("--re", &args.re)?;
+    let im = parse_float("--im", &args.im)?;
+    let zoom = parse_float("--zoom", &args.zoom)?;
+    if zoom <= 0 {
+        return Err(format!("--zoom must be positive, got '{}'", args.zoom));
+    }
+
+    let render_width = args.width.saturating_mul(args.aa);
+    let render_height = args.height.saturating_mul(args.aa);
+
+    let mut state =
+        WgpuState::new_headless(render_width, render_height, global.shader.as_deref()).await?;
+
+    // Unlike interactive exports, silently clamping would break the requested downsample
+    let max_dim = state.max_texture_dimension();
+    if render_width > max_dim || render_height > max_dim {
+        return Err(format!(
+            "{}x{} at {}x AA exceeds the GPU texture limit of {}",
+            args.width, args.height, args.aa, max_dim
+        ));
+    }
+
+    let uniforms = &mut state.uniform_data.uniforms;
+    uniforms.color_scale_mode = global.color_scale.uniform_value();
+    uniforms.color_exponent = global.color_exponent;
+    uniforms.stripe_frequency = global.stripe_frequency;
+    uniforms.smooth_coloring = !global.banded as u32;
+    uniforms.palette_id = global.palette.uniform_value();
+    uniforms.boundary_aa = global.boundary_aa as u32;
+    uniforms.aa_samples = global.shader_aa;
+    uniforms.set_exterior(global.exterior_color, global.exterior_ramp);
+    uniforms.set_glitches(global.glitches.uniform_value(), global.glitch_color);
+    state.ocean_threshold = global.ocean_threshold;
+    state.max_orbit_precision = global.max_precision;
+    state.set_double_single(global.double_single);
+    state.rotation = global.rotation.to_radians();
+    if let Some(path) = &global.trap_image {
+        state.load_trap_image(path, global.trap_center, global.trap_size)?;
+        state.uniform_data.uniforms.color_mode = COLOR_MODE_TRAP;
+    }
+    if let Some(recipe) = &recipe {
+        recipe.apply(&mut state)?;
+    }
+
+    // Stills can afford more than the interactive ceiling, the orbit buffer grows to fit
+    let precision = re.prec().max(im.prec());
+    let params = RenderParams {
+        camera: Complex::with_val(precision, (&re, &im)),
+        zoom,
+        max_iter: args.max_iter,
+    };
+
+    let aspect = args.width as f32 / args.height as f32;
+    let exr = args
+        .out
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"));
+    // Float output ignores the bit depth, the extension asks for it
+    let saved = match (exr, bit_depth) {
+        (true, _) => {
+            state.set_view(&params)?;
+            let image = state.render_to_image_f32(render_width, render_height, aspect);
+            save_exr(&fit_to_output(&image, args), &args.out)
+        }
+        (false, BitDepth::Eight) => {
+            let image = state.render_params_to_image(&params, render_width, render_height)?;
+            save_png(
+                &fit_to_output(&image, args),
+                &args.out,
+                &ViewMetadata::from_state(&state),
+            )
+        }
+        (false, BitDepth::Sixteen) => {
+            state.set_view(&params)?;
+            let image = state.render_to_image_16(render_width, render_height, aspect);
+            save_png_16(
+                &fit_to_output(&image, args),
+                &args.out,
+                &ViewMetadata::from_state(&state),
+            )
+        }
+    };
+    saved.map_err(|e| format!("Failed to write {}: {}", args.out.display(), e))?;
+    println!("Rendered {}", args.out.display());
+
+    if let Some(path) = &args.export_raw {
+        export_raw(&mut state, args, aspect, path).await?;
+    }
+
+    if let Some(path) = &args.save_recipe {
+        let trap_image = match &recipe {
+            Some(recipe) => recipe
+                .coloring
+                .trap
+                .as_ref()
+                .map(|trap| trap.image.as_path()),
+            None => global.trap_image.as_deref(),
+        };
+        let mut saved = Recipe::from_state(&state, args.width, args.height, bit_depth, trap_image);
+        saved.anti_aliasing.supersample = args.aa;
+        saved.anti_aliasing.resample = args.resample;
+        saved
+            .save(path)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        println!("Saved the recipe to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Writes the per-pixel escape counts at the output size, see `--export-raw`.
+/// Counts can't be averaged meaningfully, so `--aa` doesn't apply.
+async fn export_raw(
+    state: &mut WgpuState<'_>,
+    args: &RenderArgs,
+    aspect: f32,
+    path: &Path,
+) -> Result<(), String> {
+    let (width, height, values) = state.render_to_raw(args.width, args.height, aspect).await?;
+    let (width, height) = (width as usize, height as usize);
+
+    let saved = if args.raw_smooth {
+        npy::save_f32(path, &[height, width, 2], &values)
+    } else {
+        let counts: Vec<u32> = values
+            .iter()
+            .step_by(2)
+            .map(|&count| count as u32)
+            .collect();
+        npy::save_u32(path, &[height, width], &counts)
+    };
+    saved.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    println!("Wrote escape counts to {}", path.display());
+
+    Ok(())
+}
+
+/// Parses a decimal with enough bits for every digit given, deep locations need more than
+/// the base precision.
+pub fn parse_float(name: &str, value: &str) -> Result<Float, String> {
+    let parsed = Float::parse(value).map_err(|e| format!("invalid {} '{}': {}", name, value, e))?;
+    let precision = PRECISION.max((value.len() as f64 * BITS_PER_DECADE).ceil() as u32);
+    Ok(Float::with_val(precision, parsed))
+}
+
+/// Brings a supersampled render down to the output size with the chosen filter.
+fn fit_to_output<S>(image: &RgbaBuffer<S>, args: &RenderArgs) -> RgbaBuffer<S>
+where
+    S: Channel + 'static,
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    match args.resample {
+        Resample::Box => downsample(image, args.aa),
+        Resample::Triangle => resize(image, args.width, args.height, FilterType::Triangle),
+        Resample::Lanczos => resize(image, args.width, args.height, FilterType::Lanczos3),
+    }
+}
+
+/// Averages each `factor`x`factor` block into one pixel (box filter).
+pub fn downsample<S>(image: &RgbaBuffer<S>, factor: u32) -> RgbaBuffer<S>
+where
+    S: Channel,
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    if factor == 1 {
+        return image.clone();
+    }
+
+    let width = image.width() / factor;
+    let height = image.height() / factor;
+    let samples = factor * factor;
+
+    RgbaBuffer::from_fn(width, height, |x, y| {
+        let mut sum = [S::Sum::default(); 4];
+        for sy in 0..factor {
+            for sx in 0..factor {
+                let pixel = image.get_pixel(x * factor + sx, y * factor + sy);
+                for (total, channel) in sum.iter_mut().zip(pixel.0) {
+                    *total += channel.widen();
+                }
+            }
+        }
+        Rgba(sum.map(|total| S::average(total, samples)))
+    })
+}
+
+/// Scales the supersampled image down to the output size with one of `image`'s filters.
+fn resize<S>(image: &RgbaBuffer<S>, width: u32, height: u32, filter: FilterType) -> RgbaBuffer<S>
+where
+    S: Copy + 'static,
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    if image.dimensions() == (width, height) {
+        return image.clone();
+    }
+    imageops::resize(image, width, height, filter)
+}
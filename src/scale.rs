@@ -0,0 +1,299 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    primitives::{QUAD_VERTICES, Vertex},
+    wgpu::MSAA_SAMPLES,
+};
+
+/// Scales the render scale key steps through, from fastest to sharpest.
+pub const RENDER_SCALE_LEVELS: [f32; 4] = [0.25, 0.5, 1.0, 2.0];
+
+// How long the previous frame takes to dissolve into the new one after a scale change
+const FADE_DURATION: Duration = Duration::from_millis(250);
+
+/// Intermediate texture the fractal is rendered into at the scaled resolution.
+struct ScaledTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup, // Samples `view` for the blit
+}
+
+/// The frame shown before a scale change, blended out over `FADE_DURATION`.
+struct Fade {
+    previous: ScaledTarget,
+    started: Instant,
+}
+
+/// Renders the fractal at a fraction (or multiple) of the window resolution and stretches
+/// it over the view with linear filtering. Cross-fades from the old frame when the scale
+/// changes, so quality switches don't pop. The glitch fill runs in the same blit.
+pub struct RenderScaler {
+    pipeline: wgpu::RenderPipeline,
+    fill_pipeline: wgpu::RenderPipeline, // Same blit, filling in texels marked as glitched
+    msaa_pipelines: Option<[wgpu::RenderPipeline; 2]>, // Both blits for the multisampled pass
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    target: Option<ScaledTarget>,
+    fade: Option<Fade>,
+}
+
+impl RenderScaler {
+    /// With `msaa`, the blits are also built for a window pass with `MSAA_SAMPLES` samples.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, msaa: bool) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blit.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        let pipeline = Self::create_pipeline(device, &shader, &layout, format, "fs_main", 1);
+        let fill_pipeline = Self::create_pipeline(device, &shader, &layout, format, "fs_fill", 1);
+        let msaa_pipelines = msaa.then(|| {
+            ["fs_main", "fs_fill"].map(|entry| {
+                Self::create_pipeline(device, &shader, &layout, format, entry, MSAA_SAMPLES)
+            })
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            fill_pipeline,
+            msaa_pipelines,
+            bind_group_layout,
+            sampler,
+            format,
+            target: None,
+            fade: None,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        fragment_entry: &str,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        // The blend constant sets the opacity, so one pipeline draws both ends of a fade
+        let blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(fragment_entry),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: blend,
+                        alpha: blend,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview_mask: None,
+            cache: None,
+        })
+    }
+
+    /// Makes sure the target texture is `width`x`height` and returns its view.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> &wgpu::TextureView {
+        let stale = self.target.as_ref().is_none_or(|target| {
+            target.texture.width() != width || target.texture.height() != height
+        });
+        if stale {
+            self.target = Some(self.create_target(device, width, height));
+        }
+        &self.target.as_ref().unwrap().view
+    }
+
+    /// The view to render the fractal into, if a target is in use.
+    pub fn target_view(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|target| &target.view)
+    }
+
+    /// Drops the target, the fractal goes straight to the window again.
+    pub fn release(&mut self) {
+        self.target = None;
+    }
+
+    /// Keeps the current target's frame to fade out from. The next `prepare` starts a new one.
+    pub fn start_fade(&mut self) {
+        if let Some(previous) = self.target.take() {
+            self.fade = Some(Fade {
+                previous,
+                started: Instant::now(),
+            });
+        }
+    }
+
+    /// Opacity of the new frame during a fade, `None` when not fading.
+    pub fn fade_progress(&self) -> Option<f32> {
+        self.fade.as_ref().map(|fade| {
+            (fade.started.elapsed().as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0)
+        })
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Ends a fade that has run its course.
+    pub fn finish_fade(&mut self) {
+        if self.fade_progress().is_some_and(|progress| progress >= 1.0) {
+            self.fade = None;
+        }
+    }
+
+    /// Stretches the target over the current viewport, over the previous frame while fading.
+    /// `fill_glitches` fills in the pixels the fractal shader marked as glitched, `msaa` picks
+    /// the blits for a multisampled pass.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        vertex_buffer: &wgpu::Buffer,
+        fill_glitches: bool,
+        msaa: bool,
+    ) {
+        let Some(target) = &self.target else {
+            return;
+        };
+
+        let [pipeline, fill_pipeline] = match &self.msaa_pipelines {
+            Some(pipelines) if msaa => [&pipelines[0], &pipelines[1]],
+            _ => [&self.pipeline, &self.fill_pipeline],
+        };
+        render_pass.set_pipeline(if fill_glitches {
+            fill_pipeline
+        } else {
+            pipeline
+        });
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        let vertex_count = QUAD_VERTICES.len() as u32;
+
+        let mut opacity = 1.0;
+        if let (Some(fade), Some(progress)) = (&self.fade, self.fade_progress()) {
+            render_pass.set_blend_constant(wgpu::Color::WHITE);
+            render_pass.set_bind_group(0, &fade.previous.bind_group, &[]);
+            render_pass.draw(0..vertex_count, 0..1);
+            opacity = progress as f64;
+        }
+
+        render_pass.set_blend_constant(wgpu::Color {
+            r: opacity,
+            g: opacity,
+            b: opacity,
+            a: opacity,
+        });
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..vertex_count, 0..1);
+    }
+
+    fn create_target(&self, device: &wgpu::Device, width: u32, height: u32) -> ScaledTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Scaled Render Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format, // Must match the fractal pipeline's color target
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        ScaledTarget {
+            texture,
+            view,
+            bind_group,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Stretches the render-scaled fractal texture over the view
+
+// How far the glitch fill looks for intact pixels, in texels
+const FILL_RADIUS: i32 = 4;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+@group(0) @binding(1)
+var frame_sampler: sampler;
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    // NDC y points up, texture v points down
+    out.uv = vec2<f32>(model.position.x * 0.5 + 0.5, 0.5 - model.position.y * 0.5);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(frame, frame_sampler, in.uv);
+}
+
+// Like fs_main, but texels with alpha 0 (glitches marked by the fractal shader) are
+// replaced by the inverse distance weighted average of the intact texels around them
+@fragment
+fn fs_fill(in: VertexOutput) -> @location(0) vec4<f32> {
+    // textureSample needs uniform control flow, so sample before branching
+    let sampled = textureSample(frame, frame_sampler, in.uv);
+
+    let size = vec2<i32>(textureDimensions(frame));
+    let texel = clamp(vec2<i32>(in.uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    let center = textureLoad(frame, texel, 0);
+    if (center.a >= 0.5) {
+        return sampled;
+    }
+
+    var sum = vec3<f32>(0.0, 0.0, 0.0);
+    var total = 0.0;
+    for (var dy = -FILL_RADIUS; dy <= FILL_RADIUS; dy = dy + 1) {
+        for (var dx = -FILL_RADIUS; dx <= FILL_RADIUS; dx = dx + 1) {
+            let neighbor = textureLoad(frame, clamp(texel + vec2<i32>(dx, dy), vec2<i32>(0), size - 1), 0);
+            if (neighbor.a >= 0.5) {
+                let weight = 1.0 / f32(dx * dx + dy * dy);
+                sum = sum + neighbor.rgb * weight;
+                total = total + weight;
+            }
+        }
+    }
+
+    // Holes wider than the radius keep the glitch color until the next reference clears them
+    if (total == 0.0) {
+        return vec4<f32>(center.rgb, 1.0);
+    }
+    return vec4<f32>(sum / total, 1.0);
+}
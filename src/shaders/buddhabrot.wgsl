@@ -0,0 +1,95 @@
+// Buddhabrot: counts how often the orbits of escaping points pass through each pixel
+
+struct Params {
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    seed: u32,                    // Changes every batch so batches sample different points
+    center: vec2<f32>,            // Complex point at the middle of the image
+    pixel_size: f32,              // Complex units per pixel
+    samples_per_invocation: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var<storage, read_write> hits: array<atomic<u32>>;
+
+// Points are drawn from this square, everything outside escapes right away
+const SAMPLE_RADIUS: f32 = 2.0;
+
+// PCG hash, good enough randomness for scattering samples
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn to_unit(v: u32) -> f32 {
+    return f32(v) / 4294967296.0;
+}
+
+// Main cardioid and period-2 bulb, these never escape so iterating them is wasted work
+fn in_main_body(c: vec2<f32>) -> bool {
+    let y2 = c.y * c.y;
+    let q = (c.x - 0.25) * (c.x - 0.25) + y2;
+    let bulb = (c.x + 1.0) * (c.x + 1.0) + y2;
+    return q * (q + (c.x - 0.25)) <= 0.25 * y2 || bulb <= 0.0625;
+}
+
+fn square_add(z: vec2<f32>, c: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+}
+
+fn plot(z: vec2<f32>) {
+    let size = vec2<f32>(f32(params.width), f32(params.height));
+    let pixel = (z - params.center) / params.pixel_size + size * 0.5;
+    if (pixel.x < 0.0 || pixel.y < 0.0 || pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+    // Image rows go down, the imaginary axis goes up
+    let x = u32(pixel.x);
+    let y = params.height - 1u - u32(pixel.y);
+    atomicAdd(&hits[y * params.width + x], 1u);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rng = pcg(id.x ^ pcg(params.seed));
+
+    for (var s = 0u; s < params.samples_per_invocation; s = s + 1u) {
+        rng = pcg(rng);
+        let x = to_unit(rng);
+        rng = pcg(rng);
+        let y = to_unit(rng);
+        let c = (vec2<f32>(x, y) * 2.0 - 1.0) * SAMPLE_RADIUS;
+        if (in_main_body(c)) {
+            continue;
+        }
+
+        // First pass: only orbits that escape contribute
+        var z = vec2<f32>(0.0, 0.0);
+        var escaped = false;
+        for (var i = 0u; i < params.max_iter; i = i + 1u) {
+            z = square_add(z, c);
+            if (dot(z, z) > 4.0) {
+                escaped = true;
+                break;
+            }
+        }
+        if (!escaped) {
+            continue;
+        }
+
+        // Second pass: retrace the orbit and count every pixel it visits
+        z = vec2<f32>(0.0, 0.0);
+        for (var i = 0u; i < params.max_iter; i = i + 1u) {
+            z = square_add(z, c);
+            if (dot(z, z) > 4.0) {
+                break;
+            }
+            plot(z);
+        }
+    }
+}
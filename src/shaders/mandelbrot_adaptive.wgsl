@@ -11,16 +11,308 @@ struct Uniforms {
     zoom: f32,              // Relative scale
     aspect: f32,
     offset: vec2<f32>,
-    iter_count: u32,
+    iter_count: u32,        // Valid length of the reference orbit
+    color_mode: u32,        // 0 = escape time, 1 = distance estimate, 2 = stripe average, 3 = image trap
+    max_iter: u32,          // Iteration budget per pixel
+    rebase: u32,            // 1 = restart from the orbit start instead of giving up
+    color_scale_mode: u32,  // 0 = linear, 1 = log, 2 = sqrt, 3 = power
+    color_exponent: f32,    // Exponent for the power mapping
+    exterior_ramp: f32,     // Iterations over which the exterior color fades out
+    transparent_interior: u32, // 1 = interior is see-through (for transparent windows)
+    exterior_color: vec4<f32>, // Color of immediate escapes, alpha = strength
+    stripe_frequency: f32,  // Stripes per turn around the origin (k)
+    pixel_size: f32,        // Size of one pixel in complex units
+    boundary_aa: u32,       // 1 = blend edge pixels with the interior by DE coverage
+    fractal_mode: u32,      // 0 = Mandelbrot, 1 = Julia (c is baked into the orbit)
+    aa_samples: u32,        // Supersampling grid per axis, 1 = one sample per pixel
+    precision_view: u32,    // 1 = show estimated f32 precision loss instead of the fractal
+    rotation: vec2<f32>,    // cos and sin of the view angle
+    trap_center: vec2<f32>, // Middle of the trap image in the complex plane
+    trap_size: vec2<f32>,   // Width and height of the trap image, 0 = no image loaded
+    color_offset: f32,      // Auto exposure: iterations subtracted before coloring
+    color_gain: f32,        // Auto exposure: scale applied after the offset
+    raw_output: u32,        // 1 = write (escape count, smooth count) instead of a color
+    glitch_mode: u32,       // 0 = show glitches, 1 = paint them in glitch_color, 2 = mark for fill, 3 = rebase them
+    glitch_color: vec4<f32>,
+    smooth_coloring: u32,   // 1 = color by the continuous count, 0 = integer bands
+    palette_id: u32,        // 0 = rainbow, 1 = Ultra Fractal, 2 = fire, 3 = grayscale, 4 = blue
+    double_single: u32,     // 1 = iterate dz in double-single against reference_orbit_df
+    series_skip: u32,       // Steps every pixel starts past, 0 = iterate from the start
+    series_a: vec2<f32>,    // dz after series_skip steps = a*u + b*u^2 + c*u^3,
+    series_b: vec2<f32>,    // with u = delta_c / series_radius
+    series_c: vec2<f32>,
+    series_radius: f32,
+    bla_len: u32,           // Single steps in bla_table, level k holds bla_len >> k
+    bla_levels: u32,        // 0 = no table, iterate every step
 };
 
+// A run of steps along the reference orbit, dz -> a*dz + b*dc while |dz| < radius
+struct BlaStep {
+    a: vec2<f32>,
+    b: vec2<f32>,
+    radius: f32,
+};
+
+const COLOR_MODE_DISTANCE: u32 = 1u;
+const COLOR_MODE_STRIPE: u32 = 2u;
+const COLOR_MODE_TRAP: u32 = 3u;
+
+const FRACTAL_MODE_JULIA: u32 = 1u;
+
+const GLITCH_MODE_SHOW: u32 = 0u;
+const GLITCH_MODE_FILL: u32 = 2u;
+const GLITCH_MODE_REBASE: u32 = 3u;
+
+// A pixel is glitched when |Z + dz| collapses below this fraction of |Z| (squared), like math.rs
+const GLITCH_TOLERANCE: f32 = 1e-6;
+
+// One full cycle of the sine palette, stripe averages in [0, 1] are spread over it
+const STRIPE_PALETTE_SPAN: f32 = 62.83;
+
+// Mantissa bits of an f32, what the perturbation deltas have to work with
+const F32_MANTISSA_BITS: f32 = 24.0;
+
+const COLOR_SCALE_LOG: u32 = 1u;
+const COLOR_SCALE_SQRT: u32 = 2u;
+const COLOR_SCALE_POWER: u32 = 3u;
+
+const PALETTE_ULTRA_FRACTAL: u32 = 1u;
+const PALETTE_FIRE: u32 = 2u;
+const PALETTE_GRAYSCALE: u32 = 3u;
+const PALETTE_BLUE: u32 = 4u;
+
+// Iterations per cycle of every palette, the period of the sines below
+const PALETTE_PERIOD: f32 = 62.83;
+
 // BINDING 0: The Uniforms
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
 // BINDING 1: The Reference Orbit calculated by Rug
+// xy = Z, zw = dZ/dc
 @group(0) @binding(1)
-var<storage, read> reference_orbit: array<vec2<f32>>;
+var<storage, read> reference_orbit: array<vec4<f32>>;
+
+// BINDINGS 2 and 3: Image for the orbit trap coloring
+@group(0) @binding(2)
+var trap_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var trap_sampler: sampler;
+
+// BINDING 4: The Reference Orbit as double-single pairs, filled while double_single is on
+// x = Z.re hi, y = Z.re lo, z = Z.im hi, w = Z.im lo
+@group(0) @binding(4)
+var<storage, read> reference_orbit_df: array<vec4<f32>>;
+
+// BINDING 5: Bilinear approximation table built from the reference orbit, see math.rs
+@group(0) @binding(5)
+var<storage, read> bla_table: array<BlaStep>;
+
+fn cmul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// --- Double-single arithmetic ---
+// A real is carried as vec2(hi, lo) with |lo| <= half an ulp of hi, about 48 bits.
+// A complex is vec4(re hi, re lo, im hi, im lo), the layout of reference_orbit_df.
+// The error terms rely on the compiler keeping f32 rounding as written.
+
+// a + b exactly, as a rounded sum and its error
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let v = s - a;
+    return vec2<f32>(s, (a - (s - v)) + (b - v));
+}
+
+// Like two_sum, but only valid when |a| >= |b|
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    return vec2<f32>(s, b - (s - a));
+}
+
+fn ds_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    return quick_two_sum(s.x, s.y + a.y + b.y);
+}
+
+fn ds_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = a.x * b.x;
+    let error = fma(a.x, b.x, -p); // The low half of the product, exact with a fused op
+    return quick_two_sum(p, error + a.x * b.y + a.y * b.x);
+}
+
+fn dsc_add(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(ds_add(a.xy, b.xy), ds_add(a.zw, b.zw));
+}
+
+fn dsc_mul(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    let re = ds_add(ds_mul(a.xy, b.xy), -ds_mul(a.zw, b.zw));
+    let im = ds_add(ds_mul(a.xy, b.zw), ds_mul(a.zw, b.xy));
+    return vec4<f32>(re, im);
+}
+
+fn dsc_from(z: vec2<f32>) -> vec4<f32> {
+    return vec4<f32>(z.x, 0.0, z.y, 0.0);
+}
+
+fn dsc_to_f32(z: vec4<f32>) -> vec2<f32> {
+    return vec2<f32>(z.x + z.y, z.z + z.w);
+}
+
+// The longest BLA step that applies at orbit index ref_i to a delta with |dz|^2 = dz_norm
+// and takes at most `budget` iterations: its index in bla_table and its length, or
+// length 0 for an ordinary step. Runs of 2^k start at indices 1 + j*2^k, longest first.
+fn find_bla(ref_i: u32, dz_norm: f32, budget: u32) -> vec2<u32> {
+    let m = ref_i - 1u;
+    var level = min(countTrailingZeros(m), uniforms.bla_levels - 1u);
+    var offset = 0u; // Where `level` starts in the table
+    for (var k = 0u; k < level; k = k + 1u) {
+        offset = offset + (uniforms.bla_len >> k);
+    }
+    loop {
+        let length = 1u << level;
+        let j = m >> level;
+        if (j < (uniforms.bla_len >> level) && length <= budget) {
+            let radius = bla_table[offset + j].radius;
+            if (dz_norm < radius * radius) {
+                return vec2<u32>(offset + j, length);
+            }
+        }
+        if (level == 0u) {
+            break;
+        }
+        level = level - 1u;
+        offset = offset - (uniforms.bla_len >> level);
+    }
+    return vec2<u32>(0u, 0u);
+}
+
+// Picks the gradient for `uniforms.palette_id`. Every palette depends on the absolute
+// iteration count, so it won't flicker when the reference orbit length changes.
+fn palette(iter: f32) -> vec3<f32> {
+    let t = iter / PALETTE_PERIOD;
+    switch uniforms.palette_id {
+        case PALETTE_ULTRA_FRACTAL: {
+            // The default gradient of Ultra Fractal: deep blue, white, orange and near black
+            return cyclic_gradient(t, array<vec3<f32>, 5>(
+                vec3<f32>(0.0, 0.027, 0.392),
+                vec3<f32>(0.125, 0.42, 0.796),
+                vec3<f32>(0.929, 1.0, 1.0),
+                vec3<f32>(1.0, 0.667, 0.0),
+                vec3<f32>(0.0, 0.008, 0.0),
+            ));
+        }
+        case PALETTE_FIRE: {
+            return cyclic_gradient(t, array<vec3<f32>, 5>(
+                vec3<f32>(0.0, 0.0, 0.0),
+                vec3<f32>(0.5, 0.0, 0.02),
+                vec3<f32>(0.95, 0.3, 0.0),
+                vec3<f32>(1.0, 0.85, 0.2),
+                vec3<f32>(1.0, 1.0, 0.85),
+            ));
+        }
+        case PALETTE_GRAYSCALE: {
+            return vec3<f32>(0.5 - 0.5 * cos(6.2831853 * t));
+        }
+        case PALETTE_BLUE: {
+            return cyclic_gradient(t, array<vec3<f32>, 5>(
+                vec3<f32>(0.0, 0.0, 0.1),
+                vec3<f32>(0.05, 0.2, 0.5),
+                vec3<f32>(0.5, 0.75, 1.0),
+                vec3<f32>(1.0, 1.0, 1.0),
+                vec3<f32>(0.1, 0.3, 0.6),
+            ));
+        }
+        default: {
+            return sine_palette(iter);
+        }
+    }
+}
+
+// Evenly spaced stops, the last one blends back into the first so cycles don't show a seam
+fn cyclic_gradient(t: f32, stops: array<vec3<f32>, 5>) -> vec3<f32> {
+    var colors = stops;
+    let position = fract(t) * 5.0;
+    let index = min(u32(position), 4u);
+    return mix(colors[index], colors[(index + 1u) % 5u], position - f32(index));
+}
+
+// Rainbow palette from three phase-shifted sines
+fn sine_palette(iter: f32) -> vec3<f32> {
+    let freq = 0.1;
+
+    let r = 0.5 + 0.5 * sin(freq * iter + 0.0);
+    let g = 0.5 + 0.5 * sin(freq * iter + 2.09); // +120 deg
+    let b = 0.5 + 0.5 * sin(freq * iter + 4.18); // +240 deg
+
+    return vec3<f32>(r, g, b);
+}
+
+// Maps an iteration count to a palette position.
+// Log and sqrt are scaled so low counts still spread over a few palette cycles.
+// The offset and gain are 0 and 1 unless auto exposure renormalizes the view.
+fn color_scale(iter: f32) -> f32 {
+    let t = max((iter - uniforms.color_offset) * uniforms.color_gain, 0.0);
+    switch uniforms.color_scale_mode {
+        case COLOR_SCALE_LOG: {
+            return 20.0 * log(t + 1.0);
+        }
+        case COLOR_SCALE_SQRT: {
+            return 8.0 * sqrt(t);
+        }
+        case COLOR_SCALE_POWER: {
+            return pow(t, uniforms.color_exponent);
+        }
+        default: {
+            return t;
+        }
+    }
+}
+
+// Blends escaped pixels toward the exterior color, fully at the first
+// iteration and fading out over `exterior_ramp` iterations.
+fn exterior_blend(color: vec3<f32>, iter: f32) -> vec3<f32> {
+    var t = 1.0;
+    if (uniforms.exterior_ramp > 0.0) {
+        t = clamp((iter - 1.0) / uniforms.exterior_ramp, 0.0, 1.0);
+    } else if (iter <= 1.0) {
+        t = 0.0;
+    }
+    let amount = uniforms.exterior_color.a * (1.0 - t);
+    return mix(color, uniforms.exterior_color.rgb, amount);
+}
+
+// Inside the set (Black, or fully transparent over the desktop)
+fn interior_color() -> vec4<f32> {
+    if (uniforms.transparent_interior != 0u) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
+
+// Where perturbation broke down (glitch_mode 1 or 2)
+fn glitch_output() -> vec4<f32> {
+    // Alpha 0 marks the pixel for the fill pass. A see-through interior already uses
+    // alpha, so there glitches are only painted.
+    if (uniforms.glitch_mode == GLITCH_MODE_FILL && uniforms.transparent_interior == 0u) {
+        return vec4<f32>(uniforms.glitch_color.rgb, 0.0);
+    }
+    return vec4<f32>(uniforms.glitch_color.rgb, 1.0);
+}
+
+// Heat map of the f32 bits a pixel used up: cancellation when adding the delta to the
+// reference (|Z| or |dz| much bigger than |Z + dz|), plus the bits needed to tell this
+// pixel's delta_c apart from its neighbour's. Green has bits to spare, red has none left.
+fn precision_color(delta_c: vec2<f32>, max_cancellation: f32) -> vec4<f32> {
+    let cancel_bits = 0.5 * log2(max(max_cancellation, 1.0)); // Ratio of squared lengths
+    var spacing_bits = 0.0;
+    if (uniforms.pixel_size > 0.0) {
+        spacing_bits = max(log2(length(delta_c) / uniforms.pixel_size), 0.0);
+    }
+    let used = clamp((cancel_bits + spacing_bits) / F32_MANTISSA_BITS, 0.0, 1.0);
+    let color = vec3<f32>(min(2.0 * used, 1.0), min(2.0 * (1.0 - used), 1.0), 0.0);
+    return vec4<f32>(color, 1.0);
+}
 
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
@@ -34,57 +326,264 @@ fn vs_main(model: VertexInput) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var uv = in.coord;
     uv.x = uv.x * uniforms.aspect;
+    // Turn the screen axes into the rotated view's complex plane axes
+    let r = uniforms.rotation;
+    uv = vec2<f32>(r.x * uv.x - r.y * uv.y, r.y * uv.x + r.x * uv.y);
 
     // Delta C calculation:
     // We add the offset (Camera - Reference) to the pixel coordinate
     let delta_c = (uv + uniforms.offset) / uniforms.zoom;
 
+    // Averaging counts would blur them into values no pixel has, raw exports take one sample
+    let n = max(uniforms.aa_samples, 1u);
+    if (n == 1u || uniforms.raw_output != 0u) {
+        return sample_color(delta_c);
+    }
+
+    // --- Supersampling ---
+    // Average an NxN grid of samples spread evenly over the pixel.
+    // Every sample runs the full iteration, so this costs N^2 times as much.
+    var sum = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    for (var sy = 0u; sy < n; sy = sy + 1u) {
+        for (var sx = 0u; sx < n; sx = sx + 1u) {
+            let sub = (vec2<f32>(f32(sx), f32(sy)) + 0.5) / f32(n) - 0.5;
+            sum = sum + sample_color(delta_c + sub * uniforms.pixel_size);
+        }
+    }
+    return sum / f32(n * n);
+}
+
+// Iterates and colors the point `delta_c` away from the reference.
+fn sample_color(delta_c: vec2<f32>) -> vec4<f32> {
+    // Mandelbrot: the pixel varies c, so every step adds delta_c.
+    // Julia: c is fixed and the pixel varies the starting point instead.
+    let julia = uniforms.fractal_mode == FRACTAL_MODE_JULIA;
+    var step_c = delta_c;
     var dz = vec2<f32>(0.0, 0.0);
-    let max_iter = uniforms.iter_count;
+    if (julia) {
+        step_c = vec2<f32>(0.0, 0.0);
+        dz = delta_c;
+    }
+    var dd = vec2<f32>(0.0, 0.0); // Perturbation of the derivative
+
+    // --- Series Approximation ---
+    // The first series_skip steps follow a cubic in the pixel's offset, start after them.
+    // dd is the cubic's derivative less the reference's own, the linear coefficient.
+    // Only set for Mandelbrot views, the CPU leaves it at 0 where it doesn't apply.
+    var first_iter = 0u;
+    if (uniforms.series_skip > 0u) {
+        let u = delta_c / uniforms.series_radius;
+        let u2 = cmul(u, u);
+        dz = cmul(uniforms.series_a, u) + cmul(uniforms.series_b, u2)
+            + cmul(uniforms.series_c, cmul(u2, u));
+        dd = (2.0 * cmul(uniforms.series_b, u) + 3.0 * cmul(uniforms.series_c, u2))
+            / uniforms.series_radius;
+        first_iter = uniforms.series_skip;
+    }
+    // Double-single copies of dz and the step. The pixel's delta_c is only f32, the gain is
+    // in not rounding dz and Z + dz to 24 bits on every step.
+    let double_single = uniforms.double_single != 0u;
+    var dz_ds = dsc_from(dz);
+    let step_c_ds = dsc_from(step_c);
+    let ref_len = uniforms.iter_count;
+    var ref_i = first_iter; // Position in the reference orbit, can restart on rebase
     var final_iter = 0u;
+    var final_z = vec2<f32>(0.0, 0.0);
+    var final_der = vec2<f32>(0.0, 0.0);
     var escaped = false;
+    var glitched = false;
+
+    // Stripe average: running sum of 0.5 + 0.5 * sin(k * arg(z)), plus the last term
+    // so the average can be interpolated between the last two iterations
+    var stripe_sum = 0.0;
+    var stripe_last = 0.0;
+    var stripe_count = 0u;
 
+    // Closest approach of the orbit to the trap center, relative to it
+    var trap_point = vec2<f32>(0.0, 0.0);
+    var trap_distance = 1e30;
+
+    // Worst |Z|^2 or |dz|^2 relative to |Z + dz|^2 seen so far, for the precision view
+    var max_cancellation = 1.0;
 
     // --- Perturbation Loop ---
-    for (var i = 0u; i < max_iter; i = i + 1u) {
-        let z_ref = reference_orbit[i];
+    for (var i = first_iter; i < uniforms.max_iter; i = i + 1u) {
+        if (ref_i + 1u >= ref_len) {
+            break;
+        }
+
+        // --- Bilinear Approximation ---
+        // While dz is tiny next to Z, a run of steps is linear in dz and dc, take it at once.
+        // The reference's derivative follows the same linear map, so dd just scales by a.
+        // The CPU leaves bla_levels at 0 where every step has to be seen.
+        if (uniforms.bla_levels > 0u && ref_i > 0u) {
+            let bla = find_bla(ref_i, dot(dz, dz), uniforms.max_iter - i);
+            if (bla.y > 0u) {
+                let step = bla_table[bla.x];
+                dz = cmul(step.a, dz) + cmul(step.b, step_c);
+                dd = cmul(step.a, dd);
+                ref_i = ref_i + bla.y;
+                i = i + bla.y - 1u; // The loop counts the last one
+                continue;
+            }
+        }
+
+        let orbit_point = reference_orbit[ref_i];
+        let z_ref = orbit_point.xy;
+        let der_ref = orbit_point.zw;
+
+        // Derivative Perturbation (uses dz before it is advanced)
+        // dd = 2*(Z*dd + dz*(dZ + dd))
+        dd = 2.0 * (cmul(z_ref, dd) + cmul(dz, der_ref + dd));
 
         // Perturbation Math
         // dz = 2*Z*dz + dz^2 + dc
-        let term1_x = 2.0 * (z_ref.x * dz.x - z_ref.y * dz.y);
-        let term1_y = 2.0 * (z_ref.x * dz.y + z_ref.y * dz.x);
-        let term2_x = dz.x * dz.x - dz.y * dz.y;
-        let term2_y = 2.0 * dz.x * dz.y;
-
-        dz.x = term1_x + term2_x + delta_c.x;
-        dz.y = term1_y + term2_y + delta_c.y;
-
-        // Check absolute escape
-        let z_x = z_ref.x + dz.x;
-        let z_y = z_ref.y + dz.y;
-        
+        var z_ds = vec4<f32>(0.0);
+        if (double_single) {
+            // Doubling is exact, so 2*Z*dz is just the product scaled
+            let z_ref_ds = reference_orbit_df[ref_i];
+            dz_ds = dsc_add(dsc_add(2.0 * dsc_mul(z_ref_ds, dz_ds), dsc_mul(dz_ds, dz_ds)), step_c_ds);
+            dz = dsc_to_f32(dz_ds);
+        } else {
+            dz = 2.0 * cmul(z_ref, dz) + cmul(dz, dz) + step_c;
+        }
+
+        // Check absolute escape against the reference at the same step
+        ref_i = ref_i + 1u;
+        let next_point = reference_orbit[ref_i];
+        var z = next_point.xy + dz;
+        if (double_single) {
+            z_ds = dsc_add(reference_orbit_df[ref_i], dz_ds);
+            z = dsc_to_f32(z_ds);
+        }
+
+        if (uniforms.precision_view != 0u) {
+            let magnitude = max(dot(next_point.xy, next_point.xy), dot(dz, dz));
+            max_cancellation = max(max_cancellation, magnitude / max(dot(z, z), 1e-30));
+        }
+
         // Escape Threshold 4.0
-        if (z_x * z_x + z_y * z_y > 4.0) {
-            final_iter = i;
+        if (dot(z, z) > 4.0) {
+            final_iter = i + 1u;
+            final_z = z;
+            final_der = next_point.zw + dd;
             escaped = true;
             break;
         }
+
+        if (uniforms.color_mode == COLOR_MODE_STRIPE) {
+            stripe_last = 0.5 + 0.5 * sin(uniforms.stripe_frequency * atan2(z.y, z.x));
+            stripe_sum = stripe_sum + stripe_last;
+            stripe_count = stripe_count + 1u;
+        }
+
+        if (uniforms.color_mode == COLOR_MODE_TRAP) {
+            let from_trap = z - uniforms.trap_center;
+            let distance = dot(from_trap, from_trap);
+            if (distance < trap_distance) {
+                trap_distance = distance;
+                trap_point = from_trap;
+            }
+        }
+
+        // --- Rebasing ---
+        // When the pixel orbit gets closer to the start of the reference orbit than to
+        // the reference, or the reference has escaped, restart from Z_0 and carry the
+        // difference as the new delta. Z_0 is 0 for Mandelbrot, the reference point for Julia.
+        // Pauldelbrot's criterion: when the delta swallowed the reference, the rest is noise.
+        // Glitch mode "rebase" restarts such pixels too, the others flag them.
+        let start = reference_orbit[0];
+        let from_start = z - start.xy;
+        let collapsed = dot(z, z) < GLITCH_TOLERANCE * dot(next_point.xy, next_point.xy);
+        let drifted = dot(from_start, from_start) < dot(dz, dz) || ref_i + 1u >= ref_len;
+        if ((uniforms.rebase != 0u && drifted)
+            || (uniforms.glitch_mode == GLITCH_MODE_REBASE && collapsed)) {
+            dz = from_start;
+            dd = next_point.zw + dd - start.zw;
+            ref_i = 0u;
+            if (double_single) {
+                let start_ds = reference_orbit_df[0];
+                dz_ds = dsc_add(z_ds, -start_ds);
+                dz = dsc_to_f32(dz_ds);
+            }
+        } else if (uniforms.glitch_mode != GLITCH_MODE_SHOW && uniforms.raw_output == 0u
+            && collapsed) {
+            glitched = true;
+            break;
+        }
+    }
+
+    if (uniforms.precision_view != 0u && uniforms.raw_output == 0u) {
+        return precision_color(delta_c, max_cancellation);
+    }
+
+    if (glitched) {
+        return glitch_output();
     }
 
     if (escaped) {
-        // --- Smooth Sine Coloring ---
-        // This palette depends on the absolute iteration count, 
-        // so it won't flicker when the reference orbit length changes.
         let f_iter = f32(final_iter);
-        let freq = 0.1; 
-        
-        let r = 0.5 + 0.5 * sin(freq * f_iter + 0.0);
-        let g = 0.5 + 0.5 * sin(freq * f_iter + 2.09); // +120 deg
-        let b = 0.5 + 0.5 * sin(freq * f_iter + 4.18); // +240 deg
 
-        return vec4<f32>(r, g, b, 1.0);
+        // Continuous iteration count keeps the hue from banding
+        let r = length(final_z);
+        let smooth_iter = f_iter + 1.0 - log2(log2(r));
+
+        if (uniforms.raw_output != 0u) {
+            return vec4<f32>(f_iter, smooth_iter, 0.0, 0.0);
+        }
+
+        // DE = |z| * ln|z| / |dz/dc|, the distance to the set in complex units
+        let de = 0.5 * r * log(r) / length(final_der);
+
+        // The banded look is still available, the integer count is what the orbit really did
+        let color_iter = select(f_iter, smooth_iter, uniforms.smooth_coloring != 0u);
+
+        var color: vec3<f32>;
+        if (uniforms.color_mode == COLOR_MODE_DISTANCE) {
+            // --- Distance Estimation ---
+            // Scaled by zoom so the shading is relative to the view
+            let shade = clamp(sqrt(de * uniforms.zoom * 200.0), 0.0, 1.0);
+            color = exterior_blend(palette(color_scale(color_iter)), smooth_iter) * shade;
+        } else if (uniforms.color_mode == COLOR_MODE_STRIPE) {
+            // --- Stripe Average Coloring (Härkönen) ---
+            // Blend the averages with and without the last term by the fractional
+            // escape count, so the stripes flow smoothly across iteration bands
+            let count = f32(max(stripe_count, 1u));
+            let avg = stripe_sum / count;
+            let prev_avg = (stripe_sum - stripe_last) / max(count - 1.0, 1.0);
+            let frac = clamp(smooth_iter - f_iter, 0.0, 1.0);
+            let stripe = mix(prev_avg, avg, frac);
+
+            color = exterior_blend(palette(stripe * STRIPE_PALETTE_SPAN), smooth_iter);
+        } else {
+            // --- Smooth Sine Coloring ---
+            color = exterior_blend(palette(color_scale(color_iter)), smooth_iter);
+        }
+
+        if (uniforms.color_mode == COLOR_MODE_TRAP && uniforms.trap_size.y > 0.0) {
+            // --- Image Orbit Trap ---
+            // Where the orbit came closest to the trap center picks the texel.
+            // Image rows go down, the imaginary axis goes up.
+            let tex_uv = vec2<f32>(trap_point.x, -trap_point.y) / uniforms.trap_size + 0.5;
+            if (all(tex_uv >= vec2<f32>(0.0)) && all(tex_uv <= vec2<f32>(1.0))) {
+                // Explicit level, the loop above makes this non-uniform control flow
+                let texel = textureSampleLevel(trap_texture, trap_sampler, tex_uv, 0.0);
+                color = mix(color, texel.rgb, texel.a);
+            }
+        }
+
+        // --- Boundary Coverage AA ---
+        // A pixel closer to the set than its own size is partly covered by the interior.
+        // Treat de / pixel_size as the exterior fraction and blend toward the interior.
+        var coverage = 1.0;
+        if (uniforms.boundary_aa != 0u && uniforms.pixel_size > 0.0) {
+            coverage = clamp(de / uniforms.pixel_size, 0.0, 1.0);
+        }
+        return mix(interior_color(), vec4<f32>(color, 1.0), coverage);
+    } else if (uniforms.raw_output != 0u) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0); // Escape counts start at 1, 0 marks the interior
     } else {
-        // Inside the set (Black)
-        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        return interior_color();
     }
-}
\ No newline at end of file
+}
+
@@ -0,0 +1,65 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Interval used when recording is started without any trigger configured
+pub const DEFAULT_TIMELAPSE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Saves numbered frames of an interactive session into one folder.
+/// A frame is due when either the time interval or the zoom-decade step has passed.
+pub struct Timelapse {
+    pub dir: PathBuf,
+    frame: u32,
+    interval: Option<Duration>,
+    decades: Option<f64>,
+    last_capture: Option<Instant>, // None until the first frame, which is due immediately
+    last_log_zoom: f64,
+}
+
+impl Timelapse {
+    /// Creates the output folder. `interval` and `decades` are the triggers, either may be unset.
+    pub fn start(
+        dir: PathBuf,
+        interval: Option<Duration>,
+        decades: Option<f64>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            frame: 0,
+            interval,
+            decades,
+            last_capture: None,
+            last_log_zoom: 0.0,
+        })
+    }
+
+    /// Whether a frame should be captured at the current zoom.
+    pub fn is_due(&self, log_zoom: f64) -> bool {
+        let Some(last_capture) = self.last_capture else {
+            return true;
+        };
+
+        let time_due = self
+            .interval
+            .is_some_and(|interval| last_capture.elapsed() >= interval);
+        // Zooming back out counts too
+        let zoom_due = self
+            .decades
+            .is_some_and(|decades| (log_zoom - self.last_log_zoom).abs() >= decades);
+
+        time_due || zoom_due
+    }
+
+    /// Records a capture and returns the path for its frame.
+    pub fn next_frame(&mut self, log_zoom: f64) -> PathBuf {
+        self.last_capture = Some(Instant::now());
+        self.last_log_zoom = log_zoom;
+        self.frame += 1;
+
+        self.dir.join(format!("frame_{:05}.png", self.frame))
+    }
+}
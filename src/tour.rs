@@ -0,0 +1,364 @@
+use std::fs;
+
+use clap::ValueEnum;
+use rug::{Assign, Complex, Float};
+
+use crate::{
+    animation::Easing,
+    cli::{Args, ColorScale, TourArgs},
+    math::PRECISION,
+    metadata::{ViewMetadata, save_png},
+    primitives::{COLOR_MODE_DISTANCE, COLOR_MODE_ESCAPE_TIME, COLOR_MODE_STRIPE, COLOR_MODE_TRAP},
+    render::parse_float,
+    video::{VideoEncoder, encode_command},
+    wgpu::WgpuState,
+};
+
+// Seconds spent flying to a keyframe when the script doesn't say
+const DEFAULT_TRANSITION: f64 = 3.0;
+
+/// One stop of a tour. The camera flies here over `transition` seconds, then stays for `hold`.
+struct Keyframe {
+    center: Complex,
+    zoom: Float,
+    iterations: Option<u32>, // None = zoom-based budget
+    color_scale: Option<ColorScale>,
+    color_mode: Option<u32>,
+    hold: f64,
+    transition: f64,
+}
+
+/// Parses a tour script. Each non-empty line is a keyframe of `key=value` pairs:
+///
+/// ```text
+/// # Comments start with '#'
+/// re=-0.75 im=0 zoom=1 hold=2
+/// re=-0.743643887037151 im=0.131825904205330 zoom=1e10 iterations=3000 color_scale=log transition=8
+/// ```
+///
+/// `re`, `im` and `zoom` are required and parsed in full precision. `iterations`,
+/// `color_scale`, `coloring` (escape/distance/stripe), `hold` and `transition` are optional.
+fn parse_script(script: &str) -> Result<Vec<Keyframe>, String> {
+    let mut keyframes = Vec::new();
+
+    for (number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        parse_keyframe(line)
+            .map(|keyframe| keyframes.push(keyframe))
+            .map_err(|e| format!("line {}: {}", number + 1, e))?;
+    }
+
+    if keyframes.is_empty() {
+        return Err("the script has no keyframes".to_string());
+    }
+    Ok(keyframes)
+}
+
+fn parse_keyframe(line: &str) -> Result<Keyframe, String> {
+    let (mut re, mut im, mut zoom) = (None, None, None);
+    let mut keyframe = Keyframe {
+        center: Complex::new(PRECISION),
+        zoom: Float::with_val(PRECISION, 1.0),
+        iterations: None,
+        color_scale: None,
+        color_mode: None,
+        hold: 0.0,
+        transition: DEFAULT_TRANSITION,
+    };
+
+    for pair in line.split_whitespace() {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+
+        match key {
+            "re" => re = Some(parse_float(key, value)?),
+            "im" => im = Some(parse_float(key, value)?),
+            "zoom" => zoom = Some(parse_float(key, value)?),
+            "iterations" => keyframe.iterations = Some(parse_number(key, value)?),
+            "color_scale" => {
+                keyframe.color_scale = Some(
+                    ColorScale::from_str(value, true)
+                        .map_err(|e| format!("invalid color_scale '{}': {}", value, e))?,
+                )
+            }
+            "coloring" => {
+                keyframe.color_mode = Some(match value {
+                    "escape" => COLOR_MODE_ESCAPE_TIME,
+                    "distance" => COLOR_MODE_DISTANCE,
+                    "stripe" => COLOR_MODE_STRIPE,
+                    "trap" => COLOR_MODE_TRAP,
+                    _ => {
+                        return Err(format!(
+                            "invalid coloring '{}', expected escape, distance, stripe or trap",
+                            value
+                        ));
+                    }
+                })
+            }
+            "hold" => keyframe.hold = parse_seconds(key, value)?,
+            "transition" => keyframe.transition = parse_seconds(key, value)?,
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+    }
+
+    let (Some(re), Some(im), Some(zoom)) = (re, im, zoom) else {
+        return Err("re, im and zoom are required".to_string());
+    };
+    if zoom <= 0 {
+        return Err("zoom must be positive".to_string());
+    }
+    keyframe.center.assign((&re, &im));
+    keyframe.zoom = zoom;
+
+    Ok(keyframe)
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|e| format!("invalid {} '{}': {}", key, value, e))
+}
+
+fn parse_seconds(key: &str, value: &str) -> Result<f64, String> {
+    let seconds: f64 = value
+        .parse()
+        .map_err(|e| format!("invalid {} '{}': {}", key, value, e))?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("{} must be a non-negative number of seconds", key));
+    }
+    Ok(seconds)
+}
+
+/// The view at one point in time along the tour.
+struct TourFrame {
+    center: Complex,
+    zoom: Float,
+    iterations: Option<u32>,
+    color_scale: Option<ColorScale>,
+    color_mode: Option<u32>,
+}
+
+/// Total running time in seconds. The first keyframe is where the tour starts,
+/// so its transition is skipped.
+fn duration(keyframes: &[Keyframe]) -> f64 {
+    keyframes
+        .iter()
+        .enumerate()
+        .map(|(i, keyframe)| keyframe.hold + if i > 0 { keyframe.transition } else { 0.0 })
+        .sum()
+}
+
+/// Samples the tour at `time` seconds.
+fn sample(keyframes: &[Keyframe], time: f64, easing: Easing) -> TourFrame {
+    let mut start = 0.0;
+    let mut previous = &keyframes[0];
+
+    for (i, keyframe) in keyframes.iter().enumerate() {
+        if i > 0 {
+            let end = start + keyframe.transition;
+            if time < end && keyframe.transition > 0.0 {
+                let t = (time - start) / keyframe.transition;
+                return interpolate(previous, keyframe, easing.apply(t));
+            }
+            start = end;
+        }
+
+        let end = start + keyframe.hold;
+        if time < end {
+            return stop(keyframe);
+        }
+        start = end;
+        previous = keyframe;
+    }
+
+    stop(previous)
+}
+
+fn stop(keyframe: &Keyframe) -> TourFrame {
+    TourFrame {
+        center: keyframe.center.clone(),
+        zoom: keyframe.zoom.clone(),
+        iterations: keyframe.iterations,
+        color_scale: keyframe.color_scale,
+        color_mode: keyframe.color_mode,
+    }
+}
+
+/// Eased flight from `a` to `b`, `s` in [0, 1]. Zoom moves geometrically. The center
+/// moves so the destination slides toward the middle of the screen at a steady on-screen
+/// pace (mirrored when zooming out), instead of racing past at shallow zooms and crawling
+/// when deep. The coloring of `a` is kept until the flight lands on `b`.
+fn interpolate(a: &Keyframe, b: &Keyframe, s: f64) -> TourFrame {
+    let from_log_zoom = a.zoom.clone().log10().to_f64();
+    let to_log_zoom = b.zoom.clone().log10().to_f64();
+    let log_zoom = from_log_zoom + (to_log_zoom - from_log_zoom) * s;
+
+    // Fraction of the way from a to b. Zooming in, b's offset in screen units shrinks
+    // linearly. Zooming out, a's offset grows linearly. Both reduce to `s` at equal zoom.
+    let w = if to_log_zoom >= from_log_zoom {
+        1.0 - (1.0 - s) * 10f64.powf(from_log_zoom - log_zoom)
+    } else {
+        s * 10f64.powf(to_log_zoom - log_zoom)
+    };
+
+    let mut center = Complex::with_val(PRECISION, &b.center - &a.center);
+    center *= w;
+    center += &a.center;
+
+    // Iterations follow the zoom geometrically when both ends pin them
+    let iterations = match (a.iterations, b.iterations) {
+        (Some(from), Some(to)) => {
+            let log_iter = (from as f64).ln() + ((to as f64).ln() - (from as f64).ln()) * s;
+            Some(log_iter.exp().round() as u32)
+        }
+        (_, to) => to,
+    };
+
+    TourFrame {
+        center,
+        zoom: Float::with_val(PRECISION, log_zoom).exp10(),
+        iterations,
+        color_scale: a.color_scale,
+        color_mode: a.color_mode,
+    }
+}
+
+/// Renders every frame of a keyframe script to numbered PNGs, or a video with `--out`.
+pub async fn run(args: &TourArgs, global: &Args) -> Result<(), String> {
+    if args.width == 0 || args.height == 0 {
+        return Err("width and height must be greater than zero".to_string());
+    }
+    if args.fps == 0 {
+        return Err("--fps must be at least 1".to_string());
+    }
+
+    let script = fs::read_to_string(&args.script)
+        .map_err(|e| format!("Failed to read {}: {}", args.script.display(), e))?;
+    let keyframes =
+        parse_script(&script).map_err(|e| format!("{}: {}", args.script.display(), e))?;
+
+    let mut state =
+        WgpuState::new_headless(args.width, args.height, global.shader.as_deref()).await?;
+
+    let max_dim = state.max_texture_dimension();
+    if args.width > max_dim || args.height > max_dim {
+        return Err(format!(
+            "{}x{} exceeds the GPU texture limit of {}",
+            args.width, args.height, max_dim
+        ));
+    }
+
+    let most_iterations = keyframes.iter().filter_map(|k| k.iterations).max();
+    if let Some(most) = most_iterations
+        && most > state.max_iter()
+    {
+        state.set_max_iter(most)?;
+    }
+
+    let uniforms = &mut state.uniform_data.uniforms;
+    uniforms.color_exponent = global.color_exponent;
+    uniforms.stripe_frequency = global.stripe_frequency;
+    uniforms.smooth_coloring = !global.banded as u32;
+    uniforms.palette_id = global.palette.uniform_value();
+    uniforms.boundary_aa = global.boundary_aa as u32;
+    uniforms.aa_samples = global.shader_aa;
+    uniforms.set_exterior(global.exterior_color, global.exterior_ramp);
+    uniforms.set_glitches(global.glitches.uniform_value(), global.glitch_color);
+    state.ocean_threshold = global.ocean_threshold;
+    state.max_orbit_precision = global.max_precision;
+    state.set_double_single(global.double_single);
+    state.rotation = global.rotation.to_radians();
+
+    // Stops without a coloring use the trap image when there is one
+    let mut default_coloring = COLOR_MODE_ESCAPE_TIME;
+    if let Some(path) = &global.trap_image {
+        state.load_trap_image(path, global.trap_center, global.trap_size)?;
+        default_coloring = COLOR_MODE_TRAP;
+    }
+
+    // Start the reference at the first stop, otherwise it would sit at the origin
+    let fractal_state = &mut state.uniform_data.fractal_state;
+    fractal_state.reference.assign(&keyframes[0].center);
+
+    let mut encoder = match &args.out {
+        Some(out) => match VideoEncoder::spawn(out, args.width, args.height, args.fps) {
+            Ok(encoder) => Some(encoder),
+            Err(e) => {
+                eprintln!(
+                    "Couldn't start ffmpeg ({}), saving frames to {} instead",
+                    e,
+                    args.out_dir.display()
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    if encoder.is_none() {
+        fs::create_dir_all(&args.out_dir)
+            .map_err(|e| format!("Failed to create {}: {}", args.out_dir.display(), e))?;
+    }
+
+    let frame_count = ((duration(&keyframes) * args.fps as f64).ceil() as u32).max(1);
+    let aspect = args.width as f32 / args.height as f32;
+    let destination = match (&args.out, &encoder) {
+        (Some(out), Some(_)) => out,
+        _ => &args.out_dir,
+    };
+    println!(
+        "Rendering {} frames ({:.1}s at {} fps) to {}",
+        frame_count,
+        duration(&keyframes),
+        args.fps,
+        destination.display()
+    );
+
+    for index in 0..frame_count {
+        let frame = sample(&keyframes, index as f64 / args.fps as f64, global.easing);
+
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        fractal_state.camera.assign(&frame.center);
+        fractal_state.zoom.assign(&frame.zoom);
+        state.iteration_override = frame.iterations;
+
+        let uniforms = &mut state.uniform_data.uniforms;
+        uniforms.color_scale_mode = frame
+            .color_scale
+            .unwrap_or(global.color_scale)
+            .uniform_value();
+        uniforms.color_mode = frame.color_mode.unwrap_or(default_coloring);
+
+        let image = state.render_to_image(args.width, args.height, aspect);
+        if let Some(encoder) = &mut encoder {
+            encoder.write_frame(&image)?;
+        } else {
+            let path = args.out_dir.join(format!("frame_{:05}.png", index + 1));
+            save_png(&image, &path, &ViewMetadata::from_state(&state))
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+
+        if (index + 1) % args.fps == 0 || index + 1 == frame_count {
+            println!("Frame {}/{}", index + 1, frame_count);
+        }
+    }
+
+    match (&args.out, encoder) {
+        (Some(out), Some(encoder)) => {
+            encoder.finish()?;
+            println!("Encoded {}", out.display());
+        }
+        (Some(out), None) => println!(
+            "Encode the frames with:\n  {}",
+            encode_command(&args.out_dir, args.fps, out)
+        ),
+        (None, _) => {}
+    }
+
+    Ok(())
+}
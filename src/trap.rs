@@ -0,0 +1,103 @@
+use std::path::Path;
+
+/// Image sampled by the orbit trap coloring, bound next to the orbit of every view.
+/// Without `--trap-image` a transparent pixel stands in, so the bindings always exist.
+pub struct TrapTexture {
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TrapTexture {
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::upload(device, queue, 1, 1, &[0; 4])
+    }
+
+    /// Loads `path` into a texture, failing if the GPU can't hold it.
+    pub fn load(device: &wgpu::Device, queue: &wgpu::Queue, path: &Path) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .to_rgba8();
+
+        let max_dim = device.limits().max_texture_dimension_2d;
+        if image.width() > max_dim || image.height() > max_dim {
+            return Err(format!(
+                "{} is {}x{}, the GPU texture limit is {}",
+                path.display(),
+                image.width(),
+                image.height(),
+                max_dim
+            ));
+        }
+
+        Ok(Self::upload(
+            device,
+            queue,
+            image.width(),
+            image.height(),
+            image.as_raw(),
+        ))
+    }
+
+    /// Width over height of the image.
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Trap Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Pictures are stored in sRGB, sampling converts them to the shader's linear colors
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Trap Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler,
+            width,
+            height,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use image::RgbaImage;
+
+/// Streams raw RGBA frames into an ffmpeg subprocess that encodes them to a video file.
+/// The container and codec follow the output's extension: GIF gets a generated palette,
+/// anything else is encoded as H.264.
+pub struct VideoEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl VideoEncoder {
+    /// Starts ffmpeg. Fails with `NotFound` if it isn't installed.
+    pub fn spawn(out: &Path, width: u32, height: u32, fps: u32) -> io::Result<Self> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &fps.to_string()])
+            .args(["-i", "-"])
+            .args(codec_args(out))
+            .arg(out)
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        Ok(Self { child, stdin })
+    }
+
+    pub fn write_frame(&mut self, image: &RgbaImage) -> Result<(), String> {
+        let Some(stdin) = &mut self.stdin else {
+            return Err("ffmpeg input is already closed".to_string());
+        };
+        stdin
+            .write_all(image.as_raw())
+            .map_err(|e| format!("ffmpeg stopped accepting frames: {}", e))
+    }
+
+    /// Closes the input and waits for ffmpeg to finish writing the file.
+    pub fn finish(mut self) -> Result<(), String> {
+        drop(self.stdin.take());
+        let status = self.child.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("ffmpeg exited with {}", status))
+        }
+    }
+}
+
+/// The ffmpeg command that encodes frames saved by the tour renderer, for when it
+/// couldn't be run directly.
+pub fn encode_command(frames_dir: &Path, fps: u32, out: &Path) -> String {
+    let quote = |arg: &str| {
+        if arg.contains([' ', ';', '[', ']', '(', ')', '*']) {
+            format!("\"{}\"", arg)
+        } else {
+            arg.to_string()
+        }
+    };
+
+    let input = frames_dir.join("frame_%05d.png");
+    let mut command = vec![
+        "ffmpeg".to_string(),
+        "-framerate".to_string(),
+        fps.to_string(),
+        "-i".to_string(),
+        quote(&input.to_string_lossy()),
+    ];
+    command.extend(codec_args(out).iter().map(|arg| quote(arg)));
+    command.push(quote(&out.to_string_lossy()));
+    command.join(" ")
+}
+
+fn codec_args(out: &Path) -> &'static [&'static str] {
+    let is_gif = out
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+
+    if is_gif {
+        // A palette built from the frames looks far better than ffmpeg's default one
+        &[
+            "-vf",
+            "split[a][b];[a]palettegen[p];[b][p]paletteuse",
+            "-loop",
+            "0",
+        ]
+    } else {
+        // yuv420p keeps the file playable in browsers and most players, but needs even sizes
+        &[
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-c:v",
+            "libx264",
+            "-pix_fmt",
+            "yuv420p",
+            "-crf",
+            "18",
+        ]
+    }
+}
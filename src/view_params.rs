@@ -0,0 +1,72 @@
+use std::{fs, path::Path};
+
+use rug::Assign;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::Palette, render::parse_float, wgpu::WgpuState};
+
+/// A bookmarked view as JSON, small enough to paste into a message. The center and zoom
+/// are decimal strings with every digit of the view's precision, so deep locations
+/// come back exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewParams {
+    pub re: String,
+    pub im: String,
+    pub zoom: String,
+    pub max_iter: u32, // Iterations the view was saved with, informational unless pinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration_override: Option<u32>, // Pinned count, None = zoom-based budget
+    #[serde(default)]
+    pub palette: Palette,
+}
+
+impl ViewParams {
+    pub fn from_state(state: &WgpuState<'_>) -> Self {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let uniforms = &state.uniform_data.uniforms;
+
+        Self {
+            re: fractal_state.camera.real().to_string_radix(10, None),
+            im: fractal_state.camera.imag().to_string_radix(10, None),
+            zoom: fractal_state.zoom.to_string_radix(10, None),
+            max_iter: uniforms.max_iter,
+            iteration_override: state.iteration_override,
+            palette: Palette::from_uniform_value(uniforms.palette_id),
+        }
+    }
+
+    /// Moves `state` to the stored view.
+    pub fn apply(&self, state: &mut WgpuState<'_>) -> Result<(), String> {
+        let re = parse_float("re", &self.re)?;
+        let im = parse_float("im", &self.im)?;
+        let zoom = parse_float("zoom", &self.zoom)?;
+        if zoom <= 0 {
+            return Err(format!("zoom must be positive, got '{}'", self.zoom));
+        }
+
+        // Grow for the new zoom first, so the camera keeps its digits
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        fractal_state.zoom.assign(&zoom);
+        fractal_state.grow_precision();
+        fractal_state.camera.assign((&re, &im));
+        fractal_state.reference.assign(&fractal_state.camera);
+
+        state.iteration_override = None;
+        if let Some(iterations) = self.iteration_override {
+            state.pin_iterations(iterations)?;
+        }
+        state.uniform_data.uniforms.palette_id = self.palette.uniform_value();
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
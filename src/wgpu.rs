@@ -1,38 +1,217 @@
-use std::sync::Arc;
+use std::{
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use rug::{Assign, Float};
+use rug::{Assign, Complex, Float};
 use wgpu::{BindingType, BufferBindingType, ShaderStages, util::DeviceExt};
 use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
-    math::{HighPrecisionState, MAX_ITER},
-    primitives::{QUAD_VERTICES, Uniforms, Vertex},
+    capabilities::GpuCapabilities,
+    hud::Hud,
+    julia::{JULIA_ASPECT, JULIA_MAX_ITER, JULIA_ZOOM, julia_rect},
+    math::{
+        FractalMode, HighPrecisionState, MAX_ITER, MAX_ORBIT_PRECISION, OCEAN_THRESHOLD,
+        ORBIT_TRACE_MAX_ITER, PRECISION, bla_table, escape_range, series_approximation,
+        trace_orbit, zoom_iterations,
+    },
+    metadata::Rgba16Image,
+    minimap::{
+        MINIMAP_ASPECT, MINIMAP_CENTER, MINIMAP_MAX_ITER, MINIMAP_ZOOM, minimap_rect, view_marker,
+    },
+    orbit_worker::{MAX_REFERENCE_DISTANCE, OrbitJob, OrbitResult, OrbitSettings, OrbitWorker},
+    panel::SettingsPanel,
+    primitives::{
+        BlaStep, COLOR_MODE_STRIPE, COLOR_MODE_TRAP, FRACTAL_MODE_JULIA, FRACTAL_MODE_MANDELBROT,
+        GLITCH_MODE_FILL, GLITCH_MODE_SHOW, QUAD_VERTICES, RECT_OUTLINE_VERTEX_COUNT, Uniforms,
+        Vertex,
+    },
+    scale::RenderScaler,
+    trap::TrapTexture,
 };
 
+// Iterations' worth of palette the escape counts in view are spread over by auto exposure
+const AUTO_EXPOSURE_SPAN: f32 = 200.0;
+// Limits on the auto exposure gain, so nearly uniform views don't turn into noise
+const AUTO_EXPOSURE_MAX_GAIN: f32 = 4.0;
+const AUTO_EXPOSURE_MIN_GAIN: f32 = 0.05;
+
+// Tries at getting the next swap chain texture before a frame fails
+const SURFACE_ACQUIRE_ATTEMPTS: u32 = 3;
+
+// Furthest the iteration keys double or halve the zoom-based count
+const MAX_ITERATION_SHIFT: i32 = 16;
+
+// Half-size of the locked reference marker in NDC
+const REFERENCE_MARKER_SIZE: f32 = 0.015;
+
+// The shader compiled into the binary, used when no custom shader is given or it fails
+const BASELINE_SHADER: &str = include_str!("shaders/mandelbrot_adaptive.wgsl");
+
+// Target for 16-bit exports. Renderable and blendable everywhere, unlike the 16-bit norm formats.
+const HDR_EXPORT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Target for raw exports: escape count and smooth count per pixel, exact up to 2^24 iterations
+const RAW_EXPORT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Float;
+
+// Progressive refinement steps from the preview to full quality, each 4x the iterations
+const REFINEMENT_LEVELS: u32 = 3;
+// Progressive previews never go below this many iterations, cheaper views are drawn in full
+const PREVIEW_MIN_ITER: u32 = 1000;
+
+// GPU memory one iteration of the orbit ceiling takes in the largest buffer, the BLA
+// table, which holds up to two steps per orbit entry
+const BYTES_PER_ITERATION: u64 = 2 * std::mem::size_of::<BlaStep>() as u64;
+
+// Samples per pixel of the window pass while MSAA is on
+pub const MSAA_SAMPLES: u32 = 4;
+
+/// Pipelines and target for drawing the window pass multisampled, see `set_msaa`.
+/// The fractal is still shaded once per pixel, the samples smooth the overlay lines and
+/// the edges of the minimap and Julia panes.
+struct Msaa {
+    render_pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+    target: Option<(wgpu::Texture, wgpu::TextureView)>, // Sized to the surface, only while on
+}
+
+/// A texture copy on its way to the CPU, see `encode_readback`.
+struct Readback {
+    buffer: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+    unpadded_bytes_per_row: u32,
+    height: u32,
+}
+
+/// What `capture` draws into.
+#[derive(Clone, Copy, PartialEq)]
+enum CaptureTarget {
+    Surface, // The window's format, 8 bits per channel
+    Hdr,     // HDR_EXPORT_FORMAT
+    Raw,     // RAW_EXPORT_FORMAT, iteration counts instead of colors
+}
+
 pub struct WgpuState<'a> {
     #[allow(dead_code)]
-    instance: wgpu::Instance,
-    surface: wgpu::Surface<'a>,
+    instance: Option<wgpu::Instance>, // None when the device came from outside
+    surface: Option<wgpu::Surface<'a>>, // None when rendering headless
     device: wgpu::Device,
     queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub size: PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
+    hdr_pipeline: wgpu::RenderPipeline, // Same shader, drawing into HDR_EXPORT_FORMAT
+    raw_pipeline: Option<wgpu::RenderPipeline>, // Built on the first raw export
     vertex_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout, // Kept to rebind a resized orbit buffer
     pub uniform_data: UniformData,
+    max_iter: u32,      // Iteration ceiling, the orbit buffer holds this many entries
+    iteration_cap: u32, // Most the zoom-based count may reach, at most `max_iter`
+
+    overlay_pipeline: wgpu::RenderPipeline,
+    overlay_buffer: wgpu::Buffer,
+    pub selection: Option<[f32; 4]>, // Box-zoom rectangle in NDC [x0, y0, x1, y1]
+    orbit_trace: Vec<Complex>,       // Traced orbit of a clicked point, empty when off
+    orbit_trace_buffer: Option<wgpu::Buffer>, // Line list for `orbit_trace`
+    pub iteration_override: Option<u32>, // Replaces the zoom-based iteration count when set
+    iteration_shift: i32,            // Powers of two applied to the zoom-based count
+    pub force_camera_reference: bool, // Use the camera as the reference on the next update
+    pub manual_reference: bool,      // The current reference was forced, not picked
+    pub reference_locked: bool,      // Keep the current reference, skip the selection heuristics
+    pub ocean_threshold: u32, // Camera escape time below which the reference search is skipped
+    reference_marker_buffer: wgpu::Buffer, // Outline around a locked reference
+    last_frame_hash: Option<u64>, // Identifies the frame currently on screen
+
+    minimap: UniformData, // Fixed full-set view for the inset
+    minimap_marker_buffer: wgpu::Buffer,
+    pub show_minimap: bool,
+
+    julia: UniformData, // Julia preview for the c under the cursor
+    pub show_julia: bool,
+    pub split_screen: bool, // Mandelbrot on the left half, Julia on the right
+    pub rotation: f64,      // View angle in radians, counterclockwise
+
+    scaler: RenderScaler, // Offscreen target and cross-fade for render scales other than 1
+    trap: TrapTexture,    // Image for the orbit trap coloring, shared by all views
+    render_scale: f32,    // Fractal resolution relative to the window
+
+    hud: Option<Hud>,                          // Only created for windows
+    pub panel: Option<SettingsPanel>,          // egui settings, only created for windows
+    pub capabilities: Option<GpuCapabilities>, // Unknown when the device came from outside
+    pub hud_text: Option<String>,              // Drawn over the window when set
+    pub scale_factor: f32,                     // Window DPI scale, used to size the HUD
+    pub glitch_fraction: f32,                  // Estimated glitched share of the last orbit
+    pub max_orbit_precision: u32,              // Cap for the glitch-driven precision escalation
+    pub precision_limited: bool,               // The last orbit still glitched at the cap
+    warned_precision_limit: bool,              // `precision_limited` on the last update
+    pub incremental_upload: bool,              // Only upload the changed part of the orbit
+    orbit_upload_bytes: u64,                   // Orbit bytes sent by the last update
+    orbit_worker: Option<OrbitWorker>, // Computes orbits off the event loop, only for windows
+    orbit_reference: Complex,          // Reference of the orbit on the GPU, may trail the view's
+    orbit_job_id: u64,                 // Id of the latest `OrbitJob`
+    applied_orbit_id: u64,             // Id of the job whose orbit is on the GPU
+    bla_key: Option<(u32, f64)>,       // Orbit length and |dc| bound the BLA table was built for
+    orbit_settings: Option<OrbitSettings>, // What the current orbit was computed with
+    pub bla_skip_ratio: Option<f32>,   // Iterations per shader step with the table, None = off
+    pub progressive: bool, // Preview a moved view at fewer iterations, see `refined_iterations`
+    refinement_level: u32, // Steps of the current view's refinement done, REFINEMENT_LEVELS = full
+    refined_view: Option<(Complex, Float)>, // Camera and zoom being refined
+    auto_exposure: bool,   // Renormalize colors when the target moves
+    exposure_iters: Option<u32>, // Target the exposure was measured at
+    warned_gpu_zoom_limit: bool, // The view was past `gpu_zoom_limit` on the last update
+    gpu_time_micros: Arc<AtomicU64>, // Submit-to-done time of the last frame, set by the queue
+    screenshot_requested: bool, // Copy the next presented frame into `screenshot`
+    screenshot: Option<image::RgbaImage>, // Last requested frame, until taken
+    transparent: bool,     // The window is composited with alpha, clear to transparent
+    msaa: Option<Msaa>,    // None when the surface format can't be multisampled
+    msaa_enabled: bool,
+}
+
+/// Why `render` couldn't get a texture to draw into, see `wgpu::CurrentSurfaceTexture`.
+#[derive(Debug)]
+pub enum SurfaceError {
+    Timeout,
+    Occluded, // Nothing to draw into while the window is hidden
+    Outdated,
+    Lost,
+    Validation,
+}
+
+/// A view to render, independent of any window: see `set_view` and `render_params_to_image`.
+pub struct RenderParams {
+    pub camera: Complex,
+    pub zoom: Float,
+    pub max_iter: Option<u32>, // Pinned count, None = zoom-based budget
 }
 
 pub struct UniformData {
-    uniforms: Uniforms,
+    pub uniforms: Uniforms,
     uniform_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
 
     orbit_buffer: wgpu::Buffer,
+    orbit_df_buffer: wgpu::Buffer, // Double-single orbit, only filled while it's used
+    bla_buffer: wgpu::Buffer,      // Bilinear approximation table, only filled while it's used
+    orbit: Vec<[f32; 4]>,          // CPU copy of the last uploaded orbit
     pub fractal_state: HighPrecisionState,
 }
 
-impl WgpuState<'_> {
-    pub async fn new(window: Arc<Window>) -> Self {
+impl<'a> WgpuState<'a> {
+    /// `alpha_mode` requests a compositing mode for the window, falling back to the
+    /// surface's default if unsupported. Pre/post-multiplied modes make the interior transparent.
+    pub async fn new(
+        window: Arc<Window>,
+        custom_shader: Option<&Path>,
+        alpha_mode: Option<wgpu::CompositeAlphaMode>,
+    ) -> Self {
         let instance = wgpu::Instance::default();
         println!("Created WGPU instance: {:?}", instance);
 
@@ -49,6 +228,8 @@ impl WgpuState<'_> {
             .await
             .expect("Failed to find an appropriate adapter");
         println!("Found adapter: {:?}", adapter);
+        let capabilities = GpuCapabilities::from_adapter(&adapter);
+        println!("{}", capabilities);
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
@@ -68,14 +249,30 @@ impl WgpuState<'_> {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        let alpha_mode = match alpha_mode {
+            Some(wanted) if surface_caps.alpha_modes.contains(&wanted) => wanted,
+            Some(wanted) => {
+                eprintln!(
+                    "Alpha mode {:?} is not supported here (supported: {:?}), using {:?}.",
+                    wanted, surface_caps.alpha_modes, surface_caps.alpha_modes[0]
+                );
+                surface_caps.alpha_modes[0]
+            }
+            None => surface_caps.alpha_modes[0],
+        };
+
+        // Copying frames out is only needed for screenshots, so it's optional
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC);
+
         let size = window.inner_size();
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
             present_mode: surface_caps.present_modes[0], // usually Fifo (Vsync)
-            alpha_mode: surface_caps.alpha_modes[0],
+            alpha_mode,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
@@ -84,13 +281,138 @@ impl WgpuState<'_> {
         surface.configure(&device, &config);
         println!("Surface configured.");
 
+        let hud = Hud::new(&device, &queue, config.format);
+        let panel = SettingsPanel::new(&device, config.format, &window);
+
+        let msaa_supported = adapter
+            .get_texture_format_features(config.format)
+            .flags
+            .sample_count_supported(MSAA_SAMPLES);
+
+        let mut state = Self::from_parts(
+            Some(instance),
+            Some(surface),
+            device,
+            queue,
+            config,
+            custom_shader,
+            msaa_supported,
+        )
+        .await;
+        state.hud = Some(hud);
+        state.panel = Some(panel);
+        state.capabilities = Some(capabilities);
+        state.transparent = matches!(
+            alpha_mode,
+            wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied
+        );
+        state.uniform_data.uniforms.transparent_interior = state.transparent as u32;
+        state.scale_factor = window.scale_factor() as f32;
+        state
+    }
+
+    /// Creates a state without a window for offscreen rendering only.
+    /// `config` then just records the render size and format, nothing is presented.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        custom_shader: Option<&Path>,
+    ) -> Result<WgpuState<'static>, String> {
+        let instance = wgpu::Instance::default();
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .map_err(|e| format!("Failed to find an appropriate adapter: {}", e))?;
+        println!("Found adapter: {:?}", adapter.get_info().name);
+        let capabilities = GpuCapabilities::from_adapter(&adapter);
+        println!("{}", capabilities);
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("Failed to create device: {}", e))?;
+
+        let mut state = WgpuState::from_device(
+            device,
+            queue,
+            wgpu::TextureFormat::Rgba8UnormSrgb, // Matches the PNG layout, no swizzle needed
+            PhysicalSize::new(width, height),
+            custom_shader,
+        )
+        .await;
+        state.instance = Some(instance);
+        state.capabilities = Some(capabilities);
+
+        Ok(state)
+    }
+
+    /// Creates a state on a device owned by someone else, e.g. a host application
+    /// that wants the fractal as one part of its own frame. Nothing is presented;
+    /// draw into the host's target with `render_to_view`.
+    /// `format` must be the format of the textures it will be drawn into.
+    pub async fn from_device(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        custom_shader: Option<&Path>,
+    ) -> WgpuState<'static> {
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        WgpuState::from_parts(None, None, device, queue, config, custom_shader, false).await
+    }
+
+    /// Shared setup once a device exists, with or without a surface.
+    async fn from_parts(
+        instance: Option<wgpu::Instance>,
+        surface: Option<wgpu::Surface<'a>>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        custom_shader: Option<&Path>,
+        msaa_supported: bool,
+    ) -> Self {
+        let size = PhysicalSize::new(config.width, config.height);
+
         let bind_group_layout = Self::create_bind_group_layout(&device);
         println!("Bind group layout created.");
 
-        let uniform_data = Self::create_uniform_data(&device, &bind_group_layout);
+        let trap = TrapTexture::placeholder(&device, &queue);
+        let mut uniform_data =
+            Self::create_uniform_data(&device, &bind_group_layout, MAX_ITER, &trap);
+        uniform_data.fractal_state = HighPrecisionState::with_default_view(
+            config.width as f64 / config.height.max(1) as f64,
+        );
         println!("Uniform data created.");
 
-        let render_pipeline = Self::create_render_pipeline(&device, &config, &bind_group_layout);
+        let minimap = Self::create_minimap_data(&device, &queue, &bind_group_layout, &trap);
+        let julia = Self::create_julia_data(&device, &bind_group_layout, &trap);
+
+        let (render_pipeline, shader_source) =
+            Self::create_render_pipeline(&device, config.format, &bind_group_layout, custom_shader)
+                .await;
+        let hdr_pipeline = Self::try_create_render_pipeline(
+            &device,
+            HDR_EXPORT_FORMAT,
+            &bind_group_layout,
+            &shader_source,
+            1,
+        )
+        .await
+        .unwrap_or_else(|e| panic!("Shader failed to compile for 16-bit exports:\n{}", e));
         println!("Render pipeline created.");
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -100,6 +422,50 @@ impl WgpuState<'_> {
         });
         println!("Vertex buffer created.");
 
+        let overlay_pipeline = Self::create_overlay_pipeline(&device, &config, 1);
+        let scaler = RenderScaler::new(&device, config.format, msaa_supported);
+
+        let msaa = if msaa_supported {
+            let render_pipeline = Self::try_create_render_pipeline(
+                &device,
+                config.format,
+                &bind_group_layout,
+                &shader_source,
+                MSAA_SAMPLES,
+            )
+            .await
+            .unwrap_or_else(|e| panic!("Shader failed to compile for MSAA:\n{}", e));
+            Some(Msaa {
+                render_pipeline,
+                overlay_pipeline: Self::create_overlay_pipeline(&device, &config, MSAA_SAMPLES),
+                target: None,
+            })
+        } else {
+            None
+        };
+        let overlay_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            size: (RECT_OUTLINE_VERTEX_COUNT as usize * std::mem::size_of::<Vertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let minimap_marker_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Minimap Marker Buffer"),
+            size: (RECT_OUTLINE_VERTEX_COUNT as usize * std::mem::size_of::<Vertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let reference_marker_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Reference Marker Buffer"),
+            size: (RECT_OUTLINE_VERTEX_COUNT as usize * std::mem::size_of::<Vertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        println!("Overlay pipeline created.");
+
         println!("WGPU setup complete.");
 
         WgpuState {
@@ -110,8 +476,66 @@ impl WgpuState<'_> {
             config,
             size,
             render_pipeline,
+            hdr_pipeline,
+            raw_pipeline: None,
             vertex_buffer,
+            bind_group_layout,
             uniform_data,
+            max_iter: MAX_ITER,
+            iteration_cap: MAX_ITER,
+            overlay_pipeline,
+            overlay_buffer,
+            selection: None,
+            orbit_trace: Vec::new(),
+            orbit_trace_buffer: None,
+            iteration_override: None,
+            iteration_shift: 0,
+            force_camera_reference: false,
+            manual_reference: false,
+            reference_locked: false,
+            ocean_threshold: OCEAN_THRESHOLD,
+            reference_marker_buffer,
+            last_frame_hash: None,
+            minimap,
+            minimap_marker_buffer,
+            show_minimap: true,
+            julia,
+            show_julia: false,
+            split_screen: false,
+            rotation: 0.0,
+            scaler,
+            trap,
+            render_scale: 1.0,
+            hud: None,
+            panel: None,
+            capabilities: None,
+            hud_text: None,
+            glitch_fraction: 0.0,
+            max_orbit_precision: MAX_ORBIT_PRECISION,
+            precision_limited: false,
+            warned_precision_limit: false,
+            incremental_upload: true,
+            orbit_upload_bytes: 0,
+            orbit_worker: None,
+            orbit_reference: Complex::new(PRECISION),
+            orbit_job_id: 0,
+            applied_orbit_id: 0,
+            bla_key: None,
+            orbit_settings: None,
+            bla_skip_ratio: None,
+            progressive: false,
+            refinement_level: REFINEMENT_LEVELS,
+            refined_view: None,
+            auto_exposure: false,
+            exposure_iters: None,
+            warned_gpu_zoom_limit: false,
+            gpu_time_micros: Arc::new(AtomicU64::new(0)),
+            scale_factor: 1.0,
+            transparent: false,
+            screenshot_requested: false,
+            screenshot: None,
+            msaa,
+            msaa_enabled: false,
         }
     }
 
@@ -141,13 +565,56 @@ impl WgpuState<'_> {
                     },
                     count: None,
                 },
+                // Binding 2: Orbit trap image
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Binding 3: Sampler for the trap image
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Binding 4: Reference orbit as double-single hi/lo pairs
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Binding 5: Bilinear approximation table
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         })
     }
 
+    /// `orbit_len` is the most orbit entries the buffer can hold.
     fn create_uniform_data(
         device: &wgpu::Device,
         bind_group_layout: &wgpu::BindGroupLayout,
+        orbit_len: u32,
+        trap: &TrapTexture,
     ) -> UniformData {
         let uniforms = Uniforms::new();
 
@@ -160,18 +627,64 @@ impl WgpuState<'_> {
 
         // Create Orbit Buffer
         let fractal_state = HighPrecisionState::new();
-        let initial_orbit = vec![[0.0f32; 2]; MAX_ITER.try_into().unwrap()]; // Empty orbit
-        let orbit_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let initial_orbit = vec![[0.0f32; 4]; orbit_len as usize]; // Empty orbit
+        let orbit_buffer = Self::create_orbit_buffer(device, orbit_len);
+        let orbit_df_buffer = Self::create_orbit_buffer(device, orbit_len);
+        let bla_buffer = Self::create_bla_buffer(device, orbit_len);
+        let bind_group = Self::create_bind_group(
+            device,
+            bind_group_layout,
+            &uniform_buffer,
+            [&orbit_buffer, &orbit_df_buffer, &bla_buffer],
+            trap,
+        );
+
+        UniformData {
+            uniforms,
+            uniform_buffer,
+            bind_group,
+            orbit_buffer,
+            orbit_df_buffer,
+            bla_buffer,
+            orbit: initial_orbit,
+            fractal_state,
+        }
+    }
+
+    /// Zeroed storage buffer for `orbit_len` reference orbit entries.
+    fn create_orbit_buffer(device: &wgpu::Device, orbit_len: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Orbit Buffer"),
-            contents: bytemuck::cast_slice(&initial_orbit),
+            size: orbit_len as wgpu::BufferAddress * std::mem::size_of::<[f32; 4]>() as u64,
             // Note: Usage is STORAGE | COPY_DST so we can update it
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Storage buffer for the BLA table of an orbit with up to `orbit_len` entries.
+    fn create_bla_buffer(device: &wgpu::Device, orbit_len: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("BLA Buffer"),
+            size: orbit_len as wgpu::BufferAddress * BYTES_PER_ITERATION,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
 
+    /// `orbit_buffers` are the plain and the double-single reference orbit and the BLA table.
+    fn create_bind_group(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        orbit_buffers: [&wgpu::Buffer; 3],
+        trap: &TrapTexture,
+    ) -> wgpu::BindGroup {
+        let [orbit_buffer, orbit_df_buffer, bla_buffer] = orbit_buffers;
         // Connects the 'uniform_buffer' to 'binding: 0'
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Uniform Bind Group"),
-            layout: &bind_group_layout,
+            layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0, // <--- Connect the uniform buffer here
@@ -181,34 +694,155 @@ impl WgpuState<'_> {
                     binding: 1, // <--- Connect the orbit buffer here
                     resource: orbit_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&trap.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&trap.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: orbit_df_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: bla_buffer.as_entire_binding(),
+                },
             ],
-        });
+        })
+    }
 
-        UniformData {
-            uniforms,
-            uniform_buffer,
-            bind_group,
-            orbit_buffer,
-            fractal_state,
+    /// Uniforms and orbit for the minimap inset. Its view never changes,
+    /// so the orbit (of c = 0, which never escapes) is uploaded once here.
+    fn create_minimap_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        trap: &TrapTexture,
+    ) -> UniformData {
+        let mut minimap =
+            Self::create_uniform_data(device, bind_group_layout, MINIMAP_MAX_ITER, trap);
+
+        let (orbit, valid_len) = minimap.fractal_state.calculate_orbit(MINIMAP_MAX_ITER);
+        queue.write_buffer(&minimap.orbit_buffer, 0, bytemuck::cast_slice(&orbit));
+        minimap.orbit = orbit;
+
+        let uniforms = &mut minimap.uniforms;
+        uniforms.zoom = MINIMAP_ZOOM as f32;
+        uniforms.aspect = MINIMAP_ASPECT as f32;
+        // The reference sits at the origin
+        uniforms.offset = [
+            (MINIMAP_CENTER[0] * MINIMAP_ZOOM) as f32,
+            (MINIMAP_CENTER[1] * MINIMAP_ZOOM) as f32,
+        ];
+        uniforms.iter_count = valid_len;
+        uniforms.max_iter = MINIMAP_MAX_ITER;
+
+        minimap
+    }
+
+    /// Uniforms for the Julia preview. The orbit depends on c, see `set_julia_c`.
+    fn create_julia_data(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        trap: &TrapTexture,
+    ) -> UniformData {
+        let mut julia = Self::create_uniform_data(device, bind_group_layout, JULIA_MAX_ITER, trap);
+
+        let uniforms = &mut julia.uniforms;
+        uniforms.zoom = JULIA_ZOOM as f32;
+        uniforms.aspect = JULIA_ASPECT as f32;
+        uniforms.max_iter = JULIA_MAX_ITER;
+        uniforms.fractal_mode = FRACTAL_MODE_JULIA;
+
+        julia
+    }
+
+    /// Recomputes the Julia preview for the constant `c`. The reference is z0 = 0,
+    /// the center of the preview.
+    pub fn set_julia_c(&mut self, c: &Complex) {
+        let julia = &mut self.julia;
+        let (orbit, valid_len) = julia.fractal_state.orbit_math().julia_orbit(
+            &julia.fractal_state.reference,
+            c,
+            JULIA_MAX_ITER,
+            PRECISION,
+        );
+        self.queue
+            .write_buffer(&julia.orbit_buffer, 0, bytemuck::cast_slice(&orbit));
+        julia.orbit = orbit;
+        julia.uniforms.iter_count = valid_len;
+    }
+
+    /// Builds the render pipeline from `custom_shader` if given, falling back to
+    /// the built-in shader when it can't be read or fails to compile.
+    /// Also returns the source that was used, for pipelines with other targets.
+    async fn create_render_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        custom_shader: Option<&Path>,
+    ) -> (wgpu::RenderPipeline, String) {
+        if let Some(path) = custom_shader {
+            match std::fs::read_to_string(path) {
+                Ok(source) => {
+                    match Self::try_create_render_pipeline(
+                        device,
+                        format,
+                        bind_group_layout,
+                        &source,
+                        1,
+                    )
+                    .await
+                    {
+                        Ok(pipeline) => {
+                            println!("Using custom shader {}", path.display());
+                            return (pipeline, source);
+                        }
+                        Err(e) => eprintln!(
+                            "Custom shader {} failed to compile:\n{}\nFalling back to the built-in shader.",
+                            path.display(),
+                            e
+                        ),
+                    }
+                }
+                Err(e) => eprintln!(
+                    "Failed to read shader {}: {}. Falling back to the built-in shader.",
+                    path.display(),
+                    e
+                ),
+            }
         }
+
+        let pipeline =
+            Self::try_create_render_pipeline(device, format, bind_group_layout, BASELINE_SHADER, 1)
+                .await
+                .unwrap_or_else(|e| panic!("Built-in shader failed to compile:\n{}", e));
+        (pipeline, BASELINE_SHADER.to_string())
     }
 
-    fn create_render_pipeline(
+    /// Compiles `source` and builds the pipeline inside an error scope, so invalid
+    /// WGSL comes back as an error instead of hitting wgpu's panicking default handler.
+    async fn try_create_render_pipeline(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
         bind_group_layout: &wgpu::BindGroupLayout,
-    ) -> wgpu::RenderPipeline {
+        source: &str,
+        sample_count: u32,
+    ) -> Result<wgpu::RenderPipeline, wgpu::Error> {
+        let error_scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!("shaders/mandelbrot_adaptive.wgsl").into(),
-            ),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&bind_group_layout],
+                bind_group_layouts: &[Some(bind_group_layout)],
                 immediate_size: 0,
             });
 
@@ -230,10 +864,15 @@ impl WgpuState<'_> {
                 module: &shader,
                 entry_point: Some("fs_main"), // The function name in WGSL
                 targets: &[Some(wgpu::ColorTargetState {
-                    // IMPORTANT: This must match the surface configuration!
-                    format: config.format,
-                    // Replace pixels, don't blend them (for now)
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    // IMPORTANT: This must match the target's format!
+                    format,
+                    // Replace pixels, don't blend them (for now).
+                    // 32-bit float targets can't blend at all, they get no blend state.
+                    blend: format
+                        .guaranteed_format_features(device.features())
+                        .flags
+                        .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+                        .then_some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -252,7 +891,7 @@ impl WgpuState<'_> {
 
             depth_stencil: None, // We aren't checking depth (z-buffer) yet
             multisample: wgpu::MultisampleState {
-                count: 1, // No anti-aliasing (MSAA) yet
+                count: sample_count, // 1 everywhere but the MSAA window pass
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -260,7 +899,67 @@ impl WgpuState<'_> {
             cache: None,
         });
 
-        render_pipeline
+        // Covers both shader compilation and entry point/binding mismatches
+        match error_scope.pop().await {
+            Some(error) => Err(error),
+            None => Ok(render_pipeline),
+        }
+    }
+
+    /// Pipeline for flat colored lines drawn over the fractal, like the box-zoom selection.
+    fn create_overlay_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/overlay.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[],
+            immediate_size: 0,
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList, // Pairs of vertices = line segments
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview_mask: None,
+            cache: None,
+        })
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
@@ -269,12 +968,36 @@ impl WgpuState<'_> {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             // We must reconfigure the surface every time the window size changes
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            // The multisampled target has to match the new swap chain
+            self.prepare_msaa();
+            // The new swap chain starts out empty, so the next frame must be drawn
+            self.last_frame_hash = None;
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        if self.surface.is_none() {
+            return Ok(()); // Headless, nothing to present
+        }
+
+        self.scaler.finish_fade();
+
+        // The trace and marker live in the complex plane, so they have to follow the camera
+        let trace_vertices = self.orbit_trace_vertices();
+        let reference_marker = self.reference_marker();
+
+        // Nothing changed since the last presented frame, keep showing it. The hash doesn't
+        // cover the settings panel, so frames with it open are always drawn.
+        let panel_visible = self.panel.as_ref().is_some_and(|panel| panel.visible);
+        let frame_hash = self.frame_hash(&trace_vertices, reference_marker);
+        if self.last_frame_hash == Some(frame_hash) && !panel_visible {
+            return Ok(());
+        }
+
+        let output = self.acquire_frame()?;
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -285,144 +1008,1584 @@ impl WgpuState<'_> {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
-
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.uniform_data.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-
-            render_pass.draw(0..QUAD_VERTICES.len() as u32, 0..1);
+        if let Some(rect) = self.selection {
+            self.queue.write_buffer(
+                &self.overlay_buffer,
+                0,
+                bytemuck::cast_slice(&Vertex::rect_outline(rect)),
+            );
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-
-        Ok(())
-    }
-
-    pub fn update(&mut self) {
-        // Calculate Target Iterations based on Zoom
-        let log_zoom = self
-            .uniform_data
-            .fractal_state
-            .zoom
-            .clone()
-            .log10()
-            .to_f32();
+        if let Some(buffer) = &self.orbit_trace_buffer {
+            self.queue
+                .write_buffer(buffer, 0, bytemuck::cast_slice(&trace_vertices));
+        }
 
-        let mut target_iters = 500 + (100.0 * log_zoom) as u32;
-        if target_iters > MAX_ITER {
-            target_iters = MAX_ITER;
+        if let Some(marker) = reference_marker {
+            self.queue.write_buffer(
+                &self.reference_marker_buffer,
+                0,
+                bytemuck::cast_slice(&Vertex::rect_outline(marker)),
+            );
         }
 
-        // Check Validity of current Reference and Camera
-        // We need to know if the points survive the NEW target iteration count
-        let current_ref_score = self
-            .uniform_data
-            .fractal_state
-            .get_escape_time(&self.uniform_data.fractal_state.reference, target_iters);
-        let is_ref_valid = current_ref_score == target_iters;
+        if let Some([_, _, pane_width, pane_height]) = self.julia_viewport() {
+            let julia = &mut self.julia.uniforms;
+            julia.copy_coloring(&self.uniform_data.uniforms);
+            julia.aspect = pane_width / pane_height;
+            julia.pixel_size = 2.0 / (JULIA_ZOOM as f32 * pane_height);
+            self.queue.write_buffer(
+                &self.julia.uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[self.julia.uniforms]),
+            );
+        }
 
-        let camera_score = self
-            .uniform_data
-            .fractal_state
-            .get_escape_time(&self.uniform_data.fractal_state.camera, target_iters);
-        let is_camera_valid = camera_score == target_iters;
+        if self.show_minimap {
+            // Follow the main view's coloring, only the framing is fixed
+            let (view_width, view_height) = self.view_size();
+            let minimap = &mut self.minimap.uniforms;
+            minimap.copy_coloring(&self.uniform_data.uniforms);
+            let inset_height = minimap_rect(view_width, view_height)[3];
+            minimap.pixel_size = 2.0 / (MINIMAP_ZOOM as f32 * inset_height);
+            self.queue.write_buffer(
+                &self.minimap.uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[self.minimap.uniforms]),
+            );
 
-        // Update Reference Strategy
-        if is_camera_valid {
-            // Case A: Camera is safe.
-            // Best possible reference is the camera itself (minimizes float error).
-            self.uniform_data
-                .fractal_state
-                .reference
-                .assign(&self.uniform_data.fractal_state.camera);
+            let fractal_state = &self.uniform_data.fractal_state;
+            let marker = view_marker(
+                &fractal_state.camera,
+                &fractal_state.zoom,
+                self.view_aspect(),
+            );
+            self.queue.write_buffer(
+                &self.minimap_marker_buffer,
+                0,
+                bytemuck::cast_slice(&Vertex::rect_outline(marker)),
+            );
+        }
 
-            // Optimization: Offset is exactly zero
-            self.uniform_data.uniforms.offset = [0.0, 0.0];
+        // The glitch fill happens while blitting, so it needs the target even at scale 1
+        if self.render_scale != 1.0 || self.scaler.is_fading() || self.fills_glitches() {
+            let (width, height) = self.scaled_size();
+            self.scaler.prepare(&self.device, width, height);
         } else {
-            // Case B: Camera is in the ocean.
-            // Check if we need to fix the reference.
-            if !is_ref_valid {
-                let (best_point, best_score) = self.uniform_data.fractal_state.find_best_reference(
-                    &self.uniform_data.fractal_state.camera,
-                    &self.uniform_data.fractal_state.zoom,
-                    target_iters,
-                    64,
-                );
+            self.scaler.release();
+        }
 
-                if best_score > current_ref_score {
-                    self.uniform_data
-                        .fractal_state
-                        .reference
-                        .assign(&best_point);
-                }
-            }
+        self.encode_render_pass(&mut encoder, &view, true, &self.render_pipeline);
 
-            // Calculate Offset (Reference -> Camera)
-            // We do this for both "Search" and "Keep Old" paths
-            let diff_re = Float::with_val(
-                128,
-                self.uniform_data.fractal_state.camera.real()
-                    - self.uniform_data.fractal_state.reference.real(),
-            );
-            let diff_im = Float::with_val(
-                128,
-                self.uniform_data.fractal_state.camera.imag()
-                    - self.uniform_data.fractal_state.reference.imag(),
+        // The HUD goes in its own pass on top
+        if let (Some(hud), Some(text)) = (&mut self.hud, &self.hud_text) {
+            hud.prepare(
+                &self.device,
+                &self.queue,
+                text,
+                self.config.width,
+                self.config.height,
+                self.scale_factor,
             );
+            hud.render(&mut encoder, &view);
+        }
 
-            let zoom = &self.uniform_data.fractal_state.zoom;
+        // The settings panel goes above everything else
+        let mut command_buffers = Vec::new();
+        if let Some(panel) = &mut self.panel
+            && panel.visible
+        {
+            command_buffers = panel.render(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &view,
+                [self.config.width, self.config.height],
+            );
+        }
+        let readback = std::mem::take(&mut self.screenshot_requested).then(|| {
+            self.encode_readback(
+                &mut encoder,
+                &output.texture,
+                self.config.width,
+                self.config.height,
+                4,
+            )
+        });
+
+        command_buffers.push(encoder.finish());
+        self.queue.submit(command_buffers);
 
-            // Convert high-precision diff to screen-space offset (f32)
-            self.uniform_data.uniforms.offset = [
-                Float::with_val(24, &diff_re * zoom).to_f32(),
-                Float::with_val(24, &diff_im * zoom).to_f32(),
-            ];
+        if let Some(readback) = readback {
+            let pixels = self.finish_readback(readback);
+            self.screenshot =
+                Some(self.surface_image(self.config.width, self.config.height, pixels));
         }
 
-        // Calculate Orbit (Using the Reference)
-        let (orbit, valid_len) = self
-            .uniform_data
-            .fractal_state
-            .calculate_orbit(target_iters);
+        // Time the GPU work from submission until the queue reports it done
+        let submitted = Instant::now();
+        let gpu_time_micros = self.gpu_time_micros.clone();
+        self.queue.on_submitted_work_done(move || {
+            gpu_time_micros.store(submitted.elapsed().as_micros() as u64, Ordering::Relaxed);
+        });
 
-        // Upload Orbit
-        self.queue.write_buffer(
-            &self.uniform_data.orbit_buffer,
-            0,
-            bytemuck::cast_slice(&orbit),
+        output.present();
+        // With the panel open, the frame after it closes must be drawn without it
+        self.last_frame_hash = (!panel_visible).then_some(frame_hash);
+
+        Ok(())
+    }
+
+    /// Gets the next texture to draw into. Timeouts and outdated or lost surfaces are
+    /// often transient under heavy GPU load or display changes, so those are retried
+    /// after reconfiguring the surface, up to `SURFACE_ACQUIRE_ATTEMPTS` times.
+    fn acquire_frame(&self) -> Result<wgpu::SurfaceTexture, SurfaceError> {
+        let Some(surface) = &self.surface else {
+            return Err(SurfaceError::Lost);
+        };
+
+        let mut attempt = 1;
+        loop {
+            let error = match surface.get_current_texture() {
+                // A suboptimal texture still shows the frame, the next resize reconfigures
+                wgpu::CurrentSurfaceTexture::Success(output)
+                | wgpu::CurrentSurfaceTexture::Suboptimal(output) => return Ok(output),
+                wgpu::CurrentSurfaceTexture::Timeout => SurfaceError::Timeout,
+                wgpu::CurrentSurfaceTexture::Occluded => return Err(SurfaceError::Occluded),
+                wgpu::CurrentSurfaceTexture::Outdated => SurfaceError::Outdated,
+                wgpu::CurrentSurfaceTexture::Lost => SurfaceError::Lost,
+                wgpu::CurrentSurfaceTexture::Validation => return Err(SurfaceError::Validation),
+            };
+            if attempt >= SURFACE_ACQUIRE_ATTEMPTS {
+                return Err(error);
+            }
+            eprintln!(
+                "Surface unavailable ({:?}), retrying ({}/{})",
+                error, attempt, SURFACE_ACQUIRE_ATTEMPTS
+            );
+            surface.configure(&self.device, &self.config);
+            attempt += 1;
+        }
+    }
+
+    /// Bytes of reference orbit the last update sent to the GPU.
+    pub fn orbit_upload_bytes(&self) -> u64 {
+        self.orbit_upload_bytes
+    }
+
+    /// How long the GPU took for the last presented frame.
+    pub fn gpu_time(&self) -> Duration {
+        Duration::from_micros(self.gpu_time_micros.load(Ordering::Relaxed))
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Whether glitched pixels are filled in from their neighbors on screen.
+    fn fills_glitches(&self) -> bool {
+        self.uniform_data.uniforms.glitch_mode == GLITCH_MODE_FILL
+    }
+
+    /// Renders the fractal at `scale` times the window resolution from the next frame on,
+    /// cross-fading from the frame currently on screen.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        if scale == self.render_scale {
+            return;
+        }
+
+        self.start_fade();
+
+        self.render_scale = scale;
+        self.uniform_data.uniforms.pixel_size = self.pixel_size(self.scaled_size().1);
+        self.write_uniforms();
+        self.invalidate_frame();
+    }
+
+    /// Keeps the frame on screen to cross-fade from over the next few renders.
+    fn start_fade(&mut self) {
+        // Before the first orbit there's nothing on screen worth fading from
+        let has_frame = self.surface.is_some() && self.uniform_data.uniforms.iter_count > 0;
+        if !has_frame {
+            return;
+        }
+
+        // At scale 1 the fractal went straight to the window, draw it into a target to fade from
+        if self.scaler.target_view().is_none() {
+            let (width, height) = self.scaled_size();
+            self.scaler.prepare(&self.device, width, height);
+            if let Some(target) = self.scaler.target_view() {
+                let mut encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Fade Snapshot Encoder"),
+                        });
+                self.encode_scaled_pass(&mut encoder, target);
+                self.queue.submit(std::iter::once(encoder.finish()));
+            }
+        }
+        self.scaler.start_fade();
+    }
+
+    /// Whether a cross-fade (render scale or reference swap) is still running and needs more frames.
+    pub fn is_fading(&self) -> bool {
+        self.scaler.is_fading()
+    }
+
+    /// Resolution the fractal is rendered at: the view size times the render scale.
+    fn scaled_size(&self) -> (u32, u32) {
+        let (width, height) = self.view_size();
+        let max_dim = self.max_texture_dimension();
+        let scale =
+            |size: u32| ((size as f32 * self.render_scale).round() as u32).clamp(1, max_dim);
+        (scale(width), scale(height))
+    }
+
+    /// Pixel size of the Mandelbrot view: the whole window, or its left half in split screen.
+    /// The view always starts at the window's left edge.
+    pub fn view_size(&self) -> (u32, u32) {
+        if self.split_screen {
+            ((self.config.width / 2).max(1), self.config.height)
+        } else {
+            (self.config.width, self.config.height)
+        }
+    }
+
+    /// Turns a vector in screen axes (x right, y up) into complex plane axes.
+    pub fn view_to_plane(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation.sin_cos();
+        (cos * x - sin * y, sin * x + cos * y)
+    }
+
+    /// Inverse of `view_to_plane`.
+    pub fn plane_to_view(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation.sin_cos();
+        (cos * x + sin * y, -sin * x + cos * y)
+    }
+
+    pub fn view_aspect(&self) -> f32 {
+        let (width, height) = self.view_size();
+        width as f32 / height as f32
+    }
+
+    /// Pixel rectangle [x, y, width, height] of the Julia view if it's shown:
+    /// the right half in split screen, otherwise the preview inset.
+    pub fn julia_viewport(&self) -> Option<[f32; 4]> {
+        let (view_width, view_height) = self.view_size();
+        if self.split_screen {
+            let right_width = self.config.width - view_width;
+            Some([
+                view_width as f32,
+                0.0,
+                right_width.max(1) as f32,
+                view_height as f32,
+            ])
+        } else if self.show_julia {
+            Some(julia_rect(view_width, view_height))
+        } else {
+            None
+        }
+    }
+
+    pub fn max_iter(&self) -> u32 {
+        self.max_iter
+    }
+
+    /// Longest orbit whose buffers the GPU can bind, the BLA table is the largest.
+    pub fn max_orbit_iterations(&self) -> u32 {
+        let limits = self.device.limits();
+        let limit = limits
+            .max_buffer_size
+            .min(limits.max_storage_buffer_binding_size);
+        (limit / BYTES_PER_ITERATION).min(u32::MAX as u64) as u32
+    }
+
+    /// Changes the iteration ceiling, reallocating the orbit buffer to fit.
+    /// Fails if the GPU can't bind a storage buffer that large.
+    pub fn set_max_iter(&mut self, max_iter: u32) -> Result<(), String> {
+        let max_iter = max_iter.max(1);
+        let limit = self.max_orbit_iterations();
+        if max_iter > limit {
+            return Err(format!(
+                "{} iterations need a {} byte BLA buffer, the GPU allows {} iterations",
+                max_iter,
+                max_iter as u64 * BYTES_PER_ITERATION,
+                limit
+            ));
+        }
+
+        let uniform_data = &mut self.uniform_data;
+        uniform_data.orbit_buffer = Self::create_orbit_buffer(&self.device, max_iter);
+        uniform_data.orbit_df_buffer = Self::create_orbit_buffer(&self.device, max_iter);
+        uniform_data.bla_buffer = Self::create_bla_buffer(&self.device, max_iter);
+        uniform_data.bind_group = Self::create_bind_group(
+            &self.device,
+            &self.bind_group_layout,
+            &uniform_data.uniform_buffer,
+            [
+                &uniform_data.orbit_buffer,
+                &uniform_data.orbit_df_buffer,
+                &uniform_data.bla_buffer,
+            ],
+            &self.trap,
+        );
+        // The old orbit is gone from the GPU, the next update recomputes it
+        uniform_data.orbit = vec![[0.0; 4]; max_iter as usize];
+        uniform_data.uniforms.iter_count = 0;
+        uniform_data.uniforms.bla_levels = 0;
+        uniform_data.fractal_state.mark_dirty();
+        self.bla_key = None;
+
+        self.max_iter = max_iter;
+        self.invalidate_frame();
+        Ok(())
+    }
+
+    /// Turns automatic exposure on or off. While on, the coloring is renormalized to the
+    /// escape counts in view every time the iteration target changes, so deep zooms keep
+    /// the same spread of colors. Off goes back to the plain iteration mapping.
+    pub fn set_auto_exposure(&mut self, enabled: bool) {
+        self.auto_exposure = enabled;
+        self.exposure_iters = None;
+        if !enabled {
+            self.uniform_data.uniforms.color_offset = 0.0;
+            self.uniform_data.uniforms.color_gain = 1.0;
+        }
+        self.invalidate_frame();
+    }
+
+    pub fn auto_exposure(&self) -> bool {
+        self.auto_exposure
+    }
+
+    /// Loads an image for the orbit trap coloring. It spans `height` complex units
+    /// around `center`, the width follows the image's aspect.
+    pub fn load_trap_image(
+        &mut self,
+        path: &Path,
+        center: [f32; 2],
+        height: f32,
+    ) -> Result<(), String> {
+        self.trap = TrapTexture::load(&self.device, &self.queue, path)?;
+        for data in [&mut self.uniform_data, &mut self.minimap, &mut self.julia] {
+            data.bind_group = Self::create_bind_group(
+                &self.device,
+                &self.bind_group_layout,
+                &data.uniform_buffer,
+                [&data.orbit_buffer, &data.orbit_df_buffer, &data.bla_buffer],
+                &self.trap,
+            );
+        }
+
+        let uniforms = &mut self.uniform_data.uniforms;
+        uniforms.trap_center = center;
+        uniforms.trap_size = [height * self.trap.aspect(), height];
+        self.invalidate_frame();
+        Ok(())
+    }
+
+    pub fn has_trap_image(&self) -> bool {
+        self.uniform_data.uniforms.trap_size[1] > 0.0
+    }
+
+    /// Searches harder for a reference than the per-frame update can afford, for views
+    /// that stay put for a long time. A reference that survives longer is swapped in behind
+    /// a cross-fade, so the glitches it clears up don't pop. Returns whether it changed.
+    pub fn refresh_reference(&mut self, samples: u32) -> bool {
+        // Leave a reference the user picked alone
+        if self.reference_locked || self.manual_reference {
+            return false;
+        }
+
+        let target_iters = self.uniform_data.uniforms.max_iter;
+        let fractal_state = &self.uniform_data.fractal_state;
+        let current_score = fractal_state.get_escape_time(&fractal_state.reference, target_iters);
+        // Surviving the whole budget can't be beaten
+        if current_score == target_iters {
+            return false;
+        }
+
+        let (candidate, score) = fractal_state.find_best_reference(
+            &fractal_state.camera,
+            &fractal_state.zoom,
+            target_iters,
+            samples,
+        );
+        if score <= current_score {
+            return false;
+        }
+
+        self.start_fade();
+        self.uniform_data.fractal_state.reference.assign(&candidate);
+        self.invalidate_frame();
+        true
+    }
+
+    /// Pins the iteration count from the next update on, raising the ceiling if needed.
+    pub fn pin_iterations(&mut self, iterations: u32) -> Result<(), String> {
+        let iterations = iterations.max(1);
+        if iterations > self.max_iter {
+            self.set_max_iter(iterations)?;
+        }
+        self.iteration_override = Some(iterations);
+        Ok(())
+    }
+
+    /// Doubles (positive `step`) or halves the zoom-based iteration count from the next
+    /// update on, still capped by the ceiling. A pinned count isn't affected.
+    pub fn shift_iterations(&mut self, step: i32) {
+        self.iteration_shift =
+            (self.iteration_shift + step).clamp(-MAX_ITERATION_SHIFT, MAX_ITERATION_SHIFT);
+    }
+
+    /// Powers of two the iteration keys applied, 0 = the plain zoom-based count.
+    pub fn iteration_shift(&self) -> i32 {
+        self.iteration_shift
+    }
+
+    /// Drops the iteration keys' doublings and halvings.
+    pub fn reset_iteration_shift(&mut self) {
+        self.iteration_shift = 0;
+    }
+
+    /// The zoom-based iteration count with `iteration_shift` applied, up to `iteration_cap`.
+    fn automatic_iterations(&self) -> u32 {
+        let base = zoom_iterations(&self.uniform_data.fractal_state.zoom);
+        let shifted = if self.iteration_shift >= 0 {
+            base.saturating_mul(1 << self.iteration_shift)
+        } else {
+            (base >> -self.iteration_shift).max(1)
+        };
+        shifted.min(self.iteration_cap)
+    }
+
+    /// Changes the most the zoom-based iteration count may reach, growing the orbit
+    /// buffer if needed. A cap past what the GPU can bind is clamped with a warning.
+    pub fn set_iteration_cap(&mut self, cap: u32) -> Result<(), String> {
+        let limit = self.max_orbit_iterations();
+        let cap = if cap > limit {
+            eprintln!(
+                "An iteration cap of {} doesn't fit the GPU's buffers, using {}",
+                cap, limit
+            );
+            limit
+        } else {
+            cap.max(1)
+        };
+
+        if cap > self.max_iter {
+            self.set_max_iter(cap)?;
+        }
+        self.iteration_cap = cap;
+        Ok(())
+    }
+
+    /// Makes the next `render` draw even if nothing changed.
+    pub fn invalidate_frame(&mut self) {
+        self.last_frame_hash = None;
+    }
+
+    /// Asks for a copy of the next frame as presented, overlays and all, see
+    /// `take_screenshot`. Returns false if the surface doesn't allow copying frames.
+    pub fn request_screenshot(&mut self) -> bool {
+        if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) || self.surface.is_none() {
+            return false;
+        }
+        self.screenshot_requested = true;
+        // The frame on screen may be up to date, but it's gone, so draw a new one
+        self.invalidate_frame();
+        true
+    }
+
+    /// The frame captured after `request_screenshot`, once it has been presented.
+    pub fn take_screenshot(&mut self) -> Option<image::RgbaImage> {
+        self.screenshot.take()
+    }
+
+    /// Shows the orbit of `c` as a polyline over the view, or clears it with `None`.
+    /// Returns the number of points traced.
+    pub fn set_orbit_trace(&mut self, c: Option<&Complex>) -> usize {
+        self.orbit_trace = match c {
+            Some(c) => trace_orbit(
+                c,
+                self.uniform_data
+                    .uniforms
+                    .max_iter
+                    .min(ORBIT_TRACE_MAX_ITER),
+            ),
+            None => Vec::new(),
+        };
+
+        // One segment per consecutive pair, as a line list
+        self.orbit_trace_buffer = (self.orbit_trace.len() >= 2).then(|| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Orbit Trace Buffer"),
+                size: ((self.orbit_trace.len() - 1) * 2 * std::mem::size_of::<Vertex>())
+                    as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        self.orbit_trace.len()
+    }
+
+    /// The traced orbit as line list vertices in NDC for the current view.
+    fn orbit_trace_vertices(&self) -> Vec<Vertex> {
+        if self.orbit_trace.len() < 2 {
+            return Vec::new();
+        }
+
+        let fractal_state = &self.uniform_data.fractal_state;
+        let aspect = self.view_aspect();
+        let to_ndc = |z: &Complex| {
+            // Offset in full precision, only the on-screen position goes to f32.
+            // Far off-screen points are clamped so clipping stays well behaved.
+            let delta =
+                Complex::with_val(PRECISION, z - &fractal_state.camera) * &fractal_state.zoom;
+            let (x, y) = self.plane_to_view(delta.real().to_f64(), delta.imag().to_f64());
+            let x = (x as f32 / aspect).clamp(-1e4, 1e4);
+            let y = (y as f32).clamp(-1e4, 1e4);
+            Vertex::new(x, y)
+        };
+
+        self.orbit_trace
+            .windows(2)
+            .flat_map(|pair| [to_ndc(&pair[0]), to_ndc(&pair[1])])
+            .collect()
+    }
+
+    /// Small box in NDC [x0, y0, x1, y1] around the reference, shown while it's locked.
+    fn reference_marker(&self) -> Option<[f32; 4]> {
+        if !self.reference_locked {
+            return None;
+        }
+
+        // The offset is camera - reference in view units, flip it and undo rotation and aspect
+        let [offset_x, offset_y] = self.uniform_data.uniforms.offset;
+        let (offset_x, offset_y) = self.plane_to_view(offset_x as f64, offset_y as f64);
+        let aspect = self.view_aspect();
+        let (x, y) = (
+            (-offset_x as f32 / aspect).clamp(-1e4, 1e4),
+            (-offset_y as f32).clamp(-1e4, 1e4),
         );
+        let half = REFERENCE_MARKER_SIZE;
+        Some([x - half / aspect, y - half, x + half / aspect, y + half])
+    }
+
+    /// Fingerprint of everything that ends up on screen: uniforms, the valid part
+    /// of the orbit, overlays and the surface size.
+    fn frame_hash(&self, trace_vertices: &[Vertex], reference_marker: Option<[f32; 4]>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        hasher.write(bytemuck::bytes_of(&self.uniform_data.uniforms));
+        let valid_len =
+            (self.uniform_data.uniforms.iter_count as usize).min(self.uniform_data.orbit.len());
+        hasher.write(bytemuck::cast_slice(&self.uniform_data.orbit[..valid_len]));
+
+        (self.config.width, self.config.height).hash(&mut hasher);
+        self.selection
+            .map(|rect| rect.map(f32::to_bits))
+            .hash(&mut hasher);
+        hasher.write(bytemuck::cast_slice(trace_vertices));
+        reference_marker
+            .map(|rect| rect.map(f32::to_bits))
+            .hash(&mut hasher);
+        self.show_minimap.hash(&mut hasher);
+        if self.show_minimap {
+            // The marker follows the full-precision camera, not just the f32 uniforms
+            let fractal_state = &self.uniform_data.fractal_state;
+            fractal_state
+                .camera
+                .real()
+                .to_f64()
+                .to_bits()
+                .hash(&mut hasher);
+            fractal_state
+                .camera
+                .imag()
+                .to_f64()
+                .to_bits()
+                .hash(&mut hasher);
+            fractal_state.zoom.to_f64().to_bits().hash(&mut hasher);
+        }
+        self.split_screen.hash(&mut hasher);
+        self.show_julia.hash(&mut hasher);
+        if self.julia_viewport().is_some() {
+            let julia = &self.julia;
+            let valid_len = (julia.uniforms.iter_count as usize).min(julia.orbit.len());
+            hasher.write(bytemuck::cast_slice(&julia.orbit[..valid_len]));
+        }
+        self.hud_text.hash(&mut hasher);
+        self.scale_factor.to_bits().hash(&mut hasher);
+        self.render_scale.to_bits().hash(&mut hasher);
+        self.scaler
+            .fade_progress()
+            .map(f32::to_bits)
+            .hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Whether the surface format can be multisampled, i.e. `set_msaa` can turn it on.
+    pub fn msaa_supported(&self) -> bool {
+        self.msaa.is_some()
+    }
+
+    pub fn double_single(&self) -> bool {
+        self.uniform_data.uniforms.double_single != 0
+    }
+
+    /// Switches the shader's perturbation deltas between f32 and double-single (about 48
+    /// bits). The double-single orbit is only computed while it's on.
+    pub fn set_double_single(&mut self, enabled: bool) {
+        self.uniform_data.uniforms.double_single = enabled as u32;
+        self.invalidate_frame();
+    }
+
+    pub fn msaa_enabled(&self) -> bool {
+        self.msaa_enabled
+    }
+
+    /// Turns multisampling of the window pass on or off. Returns false when it isn't
+    /// supported, the window then stays single-sampled.
+    pub fn set_msaa(&mut self, enabled: bool) -> bool {
+        if enabled && self.msaa.is_none() {
+            return false;
+        }
+        self.msaa_enabled = enabled;
+        self.prepare_msaa();
+        self.last_frame_hash = None;
+        true
+    }
+
+    /// Creates the multisampled target at the surface size while MSAA is on, frees it otherwise.
+    fn prepare_msaa(&mut self) {
+        let Some(msaa) = &mut self.msaa else {
+            return;
+        };
+        if !self.msaa_enabled {
+            msaa.target = None;
+            return;
+        }
+
+        let size = wgpu::Extent3d {
+            width: self.config.width,
+            height: self.config.height,
+            depth_or_array_layers: 1,
+        };
+        if msaa
+            .target
+            .as_ref()
+            .is_some_and(|(texture, _)| texture.size() == size)
+        {
+            return;
+        }
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: MSAA_SAMPLES,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        msaa.target = Some((texture, view));
+    }
+
+    /// Records the fractal draw into `view`. Shared by the window and offscreen paths.
+    /// Interactive overlays are only drawn when `overlays` is set, so they stay out of exports.
+    /// `pipeline` draws the fractal and must match the format of `view`.
+    fn encode_render_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        overlays: bool,
+        pipeline: &wgpu::RenderPipeline,
+    ) {
+        // At another render scale the fractal goes to its own target first,
+        // the main pass then stretches it over the view
+        let scaled_view = if overlays {
+            self.scaler.target_view()
+        } else {
+            None
+        };
+        if let Some(target) = scaled_view {
+            self.encode_scaled_pass(encoder, target);
+        }
+
+        // Only the window pass is multisampled, it resolves into `view`
+        let msaa = self
+            .msaa
+            .as_ref()
+            .filter(|_| overlays && self.msaa_enabled)
+            .and_then(|msaa| Some((msaa, &msaa.target.as_ref()?.1)));
+        let (pipeline, overlay_pipeline) = match msaa {
+            Some((msaa, _)) => (&msaa.render_pipeline, &msaa.overlay_pipeline),
+            None => (pipeline, &self.overlay_pipeline),
+        };
+
+        let mut render_pass = match msaa {
+            Some((_, msaa_view)) => {
+                self.begin_clear_pass(encoder, msaa_view, Some(view), "Render Pass")
+            }
+            None => self.begin_clear_pass(encoder, view, None, "Render Pass"),
+        };
+
+        // Exports always fill the target, only the window splits
+        let (view_width, view_height) = if overlays {
+            self.view_size()
+        } else {
+            (self.config.width, self.config.height)
+        };
+        let reset_viewport = |render_pass: &mut wgpu::RenderPass<'_>| {
+            if overlays {
+                render_pass.set_viewport(0.0, 0.0, view_width as f32, view_height as f32, 0.0, 1.0);
+            }
+        };
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.uniform_data.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        reset_viewport(&mut render_pass);
+        if scaled_view.is_some() {
+            self.scaler.draw(
+                &mut render_pass,
+                &self.vertex_buffer,
+                self.fills_glitches(),
+                msaa.is_some(),
+            );
+            render_pass.set_pipeline(pipeline);
+        } else {
+            render_pass.draw(0..QUAD_VERTICES.len() as u32, 0..1);
+        }
+
+        // The Julia view uses the same pipeline in its own viewport
+        if overlays && let Some([x, y, w, h]) = self.julia_viewport() {
+            render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+
+            render_pass.set_bind_group(0, &self.julia.bind_group, &[]);
+            render_pass.draw(0..QUAD_VERTICES.len() as u32, 0..1);
+
+            reset_viewport(&mut render_pass);
+        }
+
+        // The minimap goes in its own viewport, with a marker for the current view
+        if overlays && self.show_minimap {
+            let [x, y, w, h] = minimap_rect(view_width, view_height);
+            render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+
+            render_pass.set_bind_group(0, &self.minimap.bind_group, &[]);
+            render_pass.draw(0..QUAD_VERTICES.len() as u32, 0..1);
+
+            render_pass.set_pipeline(overlay_pipeline);
+            render_pass.set_vertex_buffer(0, self.minimap_marker_buffer.slice(..));
+            render_pass.draw(0..RECT_OUTLINE_VERTEX_COUNT, 0..1);
+
+            reset_viewport(&mut render_pass);
+        }
+
+        if overlays && self.reference_locked {
+            render_pass.set_pipeline(overlay_pipeline);
+            render_pass.set_vertex_buffer(0, self.reference_marker_buffer.slice(..));
+            render_pass.draw(0..RECT_OUTLINE_VERTEX_COUNT, 0..1);
+        }
+
+        if overlays && let Some(buffer) = &self.orbit_trace_buffer {
+            render_pass.set_pipeline(overlay_pipeline);
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            render_pass.draw(0..(self.orbit_trace.len() as u32 - 1) * 2, 0..1);
+        }
+
+        // Last draw: the box-zoom selection on top of the fractal
+        if overlays && self.selection.is_some() {
+            render_pass.set_pipeline(overlay_pipeline);
+            render_pass.set_vertex_buffer(0, self.overlay_buffer.slice(..));
+            render_pass.draw(0..RECT_OUTLINE_VERTEX_COUNT, 0..1);
+        }
+    }
+
+    /// Just the main fractal over the whole of `view`, for the render scale target.
+    fn encode_scaled_pass(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = self.begin_clear_pass(encoder, view, None, "Scaled Render Pass");
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_data.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..QUAD_VERTICES.len() as u32, 0..1);
+    }
+
+    /// Starts a pass on `view` cleared to the background. With a `resolve_target`, `view` is
+    /// multisampled and only the resolved pixels are kept.
+    fn begin_clear_pass<'e>(
+        &self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        label: &str,
+    ) -> wgpu::RenderPass<'e> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(if self.transparent {
+                        wgpu::Color::TRANSPARENT
+                    } else {
+                        wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }
+                    }),
+                    // The samples themselves aren't needed after the resolve
+                    store: if resolve_target.is_some() {
+                        wgpu::StoreOp::Discard
+                    } else {
+                        wgpu::StoreOp::Store
+                    },
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// Records the current view into `view` without overlays, for offscreen or embedded use.
+    /// The target's size sets the resolution; call `update` first to refresh the orbit.
+    pub fn render_to_view(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        self.encode_render_pass(encoder, view, false, &self.render_pipeline);
+    }
+
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    /// Moves to the view in `params`, with the reference starting at its center.
+    pub fn set_view(&mut self, params: &RenderParams) -> Result<(), String> {
+        // Grow for the new zoom first, so the camera keeps its digits
+        let fractal_state = &mut self.uniform_data.fractal_state;
+        fractal_state.zoom.assign(&params.zoom);
+        fractal_state.grow_precision();
+        fractal_state.camera.assign(&params.camera);
+        fractal_state.reference.assign(&params.camera);
+
+        self.iteration_override = None;
+        if let Some(max_iter) = params.max_iter {
+            self.pin_iterations(max_iter)?;
+        }
+        Ok(())
+    }
+
+    /// Renders `params` at `width`x`height` without any window events, for scripted
+    /// stills. Works on a `new_headless` state, the coloring stays as it is set.
+    pub fn render_params_to_image(
+        &mut self,
+        params: &RenderParams,
+        width: u32,
+        height: u32,
+    ) -> Result<image::RgbaImage, String> {
+        if width == 0 || height == 0 {
+            return Err("width and height must be greater than zero".to_string());
+        }
+        self.set_view(params)?;
+        Ok(self.render_to_image(width, height, width as f32 / height as f32))
+    }
+
+    /// Renders the current view into an offscreen texture and reads the pixels back.
+    /// `aspect` is independent of the window so exports can have any shape.
+    pub fn render_to_image(&mut self, width: u32, height: u32, aspect: f32) -> image::RgbaImage {
+        let (width, height, pixels) = self.capture(width, height, aspect, CaptureTarget::Surface);
+        self.surface_image(width, height, pixels)
+    }
+
+    /// Wraps pixels read back from a texture in the surface format as an RGBA image.
+    fn surface_image(&self, width: u32, height: u32, mut pixels: Vec<u8>) -> image::RgbaImage {
+        // Surfaces are usually BGRA, PNG wants RGBA
+        if matches!(
+            self.config.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        image::RgbaImage::from_raw(width, height, pixels).expect("Export buffer size mismatch")
+    }
+
+    /// Like `render_to_image` with 16 bits per channel, so smooth gradients don't band.
+    /// Rendered in half floats and sRGB-encoded here, as the 8-bit targets do in hardware.
+    pub fn render_to_image_16(&mut self, width: u32, height: u32, aspect: f32) -> Rgba16Image {
+        let (width, height, pixels) = self.capture(width, height, aspect, CaptureTarget::Hdr);
+
+        let channels = pixels
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(i, bytes)| {
+                let value = f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])).clamp(0.0, 1.0);
+                // Every fourth channel is alpha, which stays linear
+                let value = if i % 4 == 3 {
+                    value
+                } else {
+                    linear_to_srgb(value)
+                };
+                (value * 65535.0).round() as u16
+            })
+            .collect();
+
+        Rgba16Image::from_raw(width, height, channels).expect("Export buffer size mismatch")
+    }
+
+    /// Renders the escape count and smooth count of every pixel instead of colors,
+    /// interleaved row by row from the top. Interior pixels are 0 in both.
+    /// Always uses the built-in shader, custom shaders don't know the raw output.
+    pub async fn render_to_raw(
+        &mut self,
+        width: u32,
+        height: u32,
+        aspect: f32,
+    ) -> Result<(u32, u32, Vec<f32>), String> {
+        if self.raw_pipeline.is_none() {
+            let pipeline = Self::try_create_render_pipeline(
+                &self.device,
+                RAW_EXPORT_FORMAT,
+                &self.bind_group_layout,
+                BASELINE_SHADER,
+                1,
+            )
+            .await
+            .map_err(|e| format!("Raw export pipeline failed: {}", e))?;
+            self.raw_pipeline = Some(pipeline);
+        }
+
+        let (width, height, pixels) = self.capture(width, height, aspect, CaptureTarget::Raw);
+        let values = pixels
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        Ok((width, height, values))
+    }
+
+    /// Like `render_to_image_16`, but keeps the linear half floats as rendered: no sRGB
+    /// curve and no clamping, for float formats like EXR.
+    pub fn render_to_image_f32(
+        &mut self,
+        width: u32,
+        height: u32,
+        aspect: f32,
+    ) -> image::Rgba32FImage {
+        let (width, height, pixels) = self.capture(width, height, aspect, CaptureTarget::Hdr);
+
+        let channels = pixels
+            .chunks_exact(2)
+            .map(|bytes| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .collect();
+
+        image::Rgba32FImage::from_raw(width, height, channels).expect("Export buffer size mismatch")
+    }
+
+    /// Draws the view without overlays into a new texture and returns its size and
+    /// unpadded pixel bytes, in the format `target` picks.
+    fn capture(
+        &mut self,
+        width: u32,
+        height: u32,
+        aspect: f32,
+        target: CaptureTarget,
+    ) -> (u32, u32, Vec<u8>) {
+        // Textures can't exceed the device limit. Aspect is passed separately,
+        // so clamping only lowers the resolution, not the framing.
+        let max_dim = self.max_texture_dimension();
+        if width > max_dim || height > max_dim {
+            eprintln!(
+                "Export size {}x{} exceeds the GPU limit of {}, clamping.",
+                width, height, max_dim
+            );
+        }
+        let width = width.clamp(1, max_dim);
+        let height = height.clamp(1, max_dim);
+
+        // Make sure the orbit is current, then swap in the export aspect.
+        // The center pixel maps to `camera` for any aspect, only the horizontal extent changes.
+        // Exports are always drawn in full, never as a preview.
+        let progressive = std::mem::replace(&mut self.progressive, false);
+        self.update_view(false);
+        self.progressive = progressive;
+        self.uniform_data.uniforms.aspect = aspect;
+        self.uniform_data.uniforms.pixel_size = self.pixel_size(height);
+        self.uniform_data.uniforms.raw_output = (target == CaptureTarget::Raw) as u32;
+        // A wider export reaches past the radius these were fitted for
+        self.update_series();
+        self.update_bla(false);
+        // Exports skip the blit that would fill the marked glitches, show them as they are
+        let glitch_mode = self.uniform_data.uniforms.glitch_mode;
+        if glitch_mode == GLITCH_MODE_FILL {
+            self.uniform_data.uniforms.glitch_mode = GLITCH_MODE_SHOW;
+        }
+        self.write_uniforms();
+
+        let (format, pipeline, bytes_per_pixel) = match target {
+            CaptureTarget::Surface => (self.config.format, &self.render_pipeline, 4),
+            CaptureTarget::Hdr => (HDR_EXPORT_FORMAT, &self.hdr_pipeline, 8),
+            CaptureTarget::Raw => (
+                RAW_EXPORT_FORMAT,
+                self.raw_pipeline
+                    .as_ref()
+                    .expect("raw pipeline is built by render_to_raw"),
+                8,
+            ),
+        };
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Export Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format, // Must match the pipeline's color target
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Export Encoder"),
+            });
+
+        if target == CaptureTarget::Surface {
+            self.render_to_view(&mut encoder, &view);
+        } else {
+            self.encode_render_pass(&mut encoder, &view, false, pipeline);
+        }
+        let readback = self.encode_readback(&mut encoder, &texture, width, height, bytes_per_pixel);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        let pixels = self.finish_readback(readback);
+
+        // Restore the window aspect for the next interactive frame
+        self.uniform_data.uniforms.aspect = self.view_aspect();
+        self.uniform_data.uniforms.pixel_size = self.pixel_size(self.scaled_size().1);
+        self.uniform_data.uniforms.raw_output = 0;
+        self.uniform_data.uniforms.glitch_mode = glitch_mode;
+        self.update_series();
+        self.update_bla(false);
+        self.write_uniforms();
+
+        (width, height, pixels)
+    }
+
+    /// Records a copy of `texture` into a mappable buffer, read with `finish_readback`
+    /// once `encoder` is submitted. The texture needs `COPY_SRC` usage.
+    fn encode_readback(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+        bytes_per_pixel: u32,
+    ) -> Readback {
+        // Buffer rows must be padded to COPY_BYTES_PER_ROW_ALIGNMENT (256 bytes)
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Readback {
+            buffer,
+            padded_bytes_per_row,
+            unpadded_bytes_per_row,
+            height,
+        }
+    }
+
+    /// Waits for a submitted `encode_readback` copy and returns the pixels without row padding.
+    fn finish_readback(&self, readback: Readback) -> Vec<u8> {
+        // Block until the copy is done and the buffer is mapped
+        let buffer_slice = readback.buffer.slice(..);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map readback buffer");
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("Failed to wait for readback");
+
+        // Strip the row padding
+        let unpadded_bytes_per_row = readback.unpadded_bytes_per_row as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * readback.height as usize);
+        {
+            let data = buffer_slice.get_mapped_range();
+            for row in data.chunks(readback.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        readback.buffer.unmap();
+        pixels
+    }
+
+    pub fn update(&mut self) {
+        self.update_view(true);
+    }
+
+    /// Brings the GPU state up to date with the view. With `background` and a worker, a new
+    /// orbit is left to the worker and the last one drawn until it's done, otherwise the
+    /// orbit is computed right here, waiting out any job in flight.
+    fn update_view(&mut self, background: bool) {
+        // Past ~10^19 the base precision can't tell the pixels around the camera apart
+        if self.uniform_data.fractal_state.grow_precision() {
+            println!(
+                "View precision raised to {} bits",
+                self.uniform_data.fractal_state.precision
+            );
+        }
+
+        // Calculate Target Iterations based on Zoom
+        let target_iters = self
+            .iteration_override
+            .unwrap_or_else(|| self.automatic_iterations())
+            .min(self.max_iter);
+
+        let aspect = self.view_aspect();
+        let zoom = self.uniform_data.fractal_state.zoom.to_f32();
+        let fractal_mode = self.uniform_data.fractal_state.fractal_mode;
+
+        // Take the newest orbit the worker finished since the last update
+        let mut orbit_changed = false;
+        self.orbit_upload_bytes = 0;
+        if let Some(result) = self.orbit_worker.as_mut().and_then(OrbitWorker::take)
+            && self.is_usable_orbit(&result)
+        {
+            orbit_changed = self.apply_orbit(result);
+        }
+
+        // The reference search and orbit only rerun when something they depend on changed.
+        // Redraws for the panel, the HUD or animations that didn't move the view reuse them.
+        if !background && self.is_computing_orbit() {
+            // The job in flight would come too late, redo it here
+            self.uniform_data.fractal_state.mark_dirty();
+        }
+        let settings = OrbitSettings {
+            aspect,
+            rebase: self.uniform_data.uniforms.rebase,
+            glitch_mode: self.uniform_data.uniforms.glitch_mode,
+            double_single: self.uniform_data.uniforms.double_single,
+            reference_locked: self.reference_locked,
+            ocean_threshold: self.ocean_threshold,
+            max_orbit_precision: self.max_orbit_precision,
+        };
+        if self.force_camera_reference
+            || self.orbit_settings != Some(settings)
+            || self.uniform_data.fractal_state.is_dirty(target_iters)
+        {
+            self.orbit_job_id += 1;
+            let job = OrbitJob {
+                id: self.orbit_job_id,
+                fractal_state: self.uniform_data.fractal_state.clone(),
+                target_iters,
+                zoom,
+                settings,
+                force_camera_reference: std::mem::take(&mut self.force_camera_reference),
+            };
+            let worker = self.orbit_worker.as_ref().filter(|_| background);
+            if let Some(worker) = worker {
+                worker.submit(job);
+            } else {
+                orbit_changed |= self.apply_orbit(job.run());
+            }
+            self.uniform_data.fractal_state.mark_clean(target_iters);
+            self.orbit_settings = Some(settings);
+        }
+
+        // Follows the camera every frame, the orbit on the GPU may be from an earlier view
+        self.uniform_data.uniforms.offset = self.reference_offset();
+
+        // Like the glitch estimate, the sampled escape counts assume the Mandelbrot recurrence.
+        // They're measured on the orbit, so wait for the one the worker is computing.
+        if self.auto_exposure
+            && fractal_mode == FractalMode::Mandelbrot
+            && !self.is_computing_orbit()
+            && self.exposure_iters != Some(target_iters)
+        {
+            self.exposure_iters = Some(target_iters);
+            if let Some((low, high)) = escape_range(
+                &self.uniform_data.orbit,
+                self.uniform_data.uniforms.iter_count,
+                self.uniform_data.uniforms.offset,
+                zoom,
+                aspect,
+                target_iters,
+                self.uniform_data.uniforms.rebase != 0,
+            ) {
+                // Start the palette at the outermost band and spread the rest over a fixed span
+                let uniforms = &mut self.uniform_data.uniforms;
+                uniforms.color_offset = low as f32;
+                uniforms.color_gain = (AUTO_EXPOSURE_SPAN / (high - low).max(1) as f32)
+                    .clamp(AUTO_EXPOSURE_MIN_GAIN, AUTO_EXPOSURE_MAX_GAIN);
+            }
+        }
 
         // Update Uniforms
-        let aspect = self.config.width as f32 / self.config.height as f32;
         self.uniform_data.uniforms.aspect = aspect;
-        self.uniform_data.uniforms.zoom = self.uniform_data.fractal_state.zoom.to_f32();
-        self.uniform_data.uniforms.iter_count = valid_len;
+        self.uniform_data.uniforms.zoom = zoom;
+        self.uniform_data.uniforms.pixel_size = self.pixel_size(self.scaled_size().1);
+        self.uniform_data.uniforms.max_iter = self.refined_iterations(target_iters);
+        let (sin, cos) = self.rotation.sin_cos();
+        self.uniform_data.uniforms.rotation = [cos as f32, sin as f32];
+        self.update_series();
+        self.update_bla(orbit_changed);
 
         // Upload Uniforms
+        self.write_uniforms();
+
+        if self.precision_limited && !self.warned_precision_limit {
+            eprintln!(
+                "Glitches persist at the {}-bit precision cap, rendering with them. \
+                 Raise --max-precision to go further at more CPU cost.",
+                self.max_orbit_precision
+            );
+        }
+        self.warned_precision_limit = self.precision_limited;
+
+        // Warn once on the way down, not every frame
+        let past_limit = self.past_gpu_zoom_limit();
+        if past_limit && !self.warned_gpu_zoom_limit {
+            eprintln!(
+                "Zoom is past the GPU precision limit of 10^{:.1}: pixel deltas underflow f32 \
+                 and the image breaks into blocks. Going deeper needs extended-range deltas, \
+                 which this renderer doesn't have.",
+                self.gpu_zoom_limit()
+            );
+        }
+        self.warned_gpu_zoom_limit = past_limit;
+    }
+
+    /// Uploads a finished orbit and takes over the reference it was computed for.
+    /// Returns whether the orbit on the GPU changed.
+    fn apply_orbit(&mut self, result: OrbitResult) -> bool {
+        self.applied_orbit_id = result.id;
+        let computed = result.fractal_state;
+        self.uniform_data.fractal_state.adopt_reference(&computed);
+        self.uniform_data.uniforms.fractal_mode = computed.fractal_mode.uniform_value();
+        self.orbit_reference = computed.reference;
+        if let Some(manual_reference) = result.manual_reference {
+            self.manual_reference = manual_reference;
+        }
+        self.glitch_fraction = result.glitch_fraction;
+        self.precision_limited = result.precision_limited;
+
+        // Upload Orbit. Incremental uploads skip the prefix the GPU already has, which is
+        // all of it while the reference stays put, and only the new tail as the count grows.
+        let orbit = result.orbit;
+        let start = if self.incremental_upload {
+            self.uniform_data
+                .orbit
+                .iter()
+                .zip(&orbit)
+                .take_while(|(old, new)| old == new)
+                .count()
+        } else {
+            0
+        };
+        let entry_size = std::mem::size_of::<[f32; 4]>();
+        if start < orbit.len() {
+            self.queue.write_buffer(
+                &self.uniform_data.orbit_buffer,
+                (start * entry_size) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&orbit[start..]),
+            );
+        }
+        self.orbit_upload_bytes += ((orbit.len() - start) * entry_size) as u64;
+
+        if let Some(orbit_df) = result.orbit_df {
+            self.queue.write_buffer(
+                &self.uniform_data.orbit_df_buffer,
+                0,
+                bytemuck::cast_slice(&orbit_df),
+            );
+            self.orbit_upload_bytes += (orbit_df.len() * entry_size) as u64;
+        }
+        self.uniform_data.orbit = orbit;
+        self.uniform_data.uniforms.iter_count = result.valid_len;
+
+        start < self.uniform_data.orbit.len()
+    }
+
+    /// Whether a worker result is worth drawing. The latest job's always is. One a newer
+    /// job superseded still beats the orbit on the GPU while zooming keeps the worker busy,
+    /// as long as it's for the same set and its reference is in reach of the camera.
+    fn is_usable_orbit(&self, result: &OrbitResult) -> bool {
+        if result.id <= self.applied_orbit_id {
+            return false;
+        }
+        if result.id == self.orbit_job_id {
+            return true;
+        }
+
+        let fractal_state = &self.uniform_data.fractal_state;
+        let computed = &result.fractal_state;
+        let [offset_x, offset_y] = fractal_state.offset_from(&computed.reference);
+        computed.fractal_mode == fractal_state.fractal_mode
+            && computed.julia_c == fractal_state.julia_c
+            && offset_x.hypot(offset_y) <= MAX_REFERENCE_DISTANCE
+    }
+
+    /// Moves orbit computation to a thread of its own, see `OrbitWorker`.
+    pub fn start_orbit_worker(&mut self) {
+        self.orbit_worker = Some(OrbitWorker::spawn());
+    }
+
+    /// Whether the worker is still on the latest orbit, the window keeps checking for it.
+    pub fn is_computing_orbit(&self) -> bool {
+        self.orbit_worker.is_some() && self.applied_orbit_id < self.orbit_job_id
+    }
+
+    /// Whether the worker has an orbit for the next update to pick up.
+    pub fn poll_orbit_worker(&mut self) -> bool {
+        self.orbit_worker.as_mut().is_some_and(OrbitWorker::poll)
+    }
+
+    /// The iterations this frame is drawn with. With progressive refinement, a moved view
+    /// starts as a preview at 1/4^`REFINEMENT_LEVELS` of `target_iters` and gets 4x more
+    /// on every frame it holds still. The orbit is the full one throughout, so every level
+    /// shares a reference and only pixels that hadn't escaped yet change.
+    fn refined_iterations(&mut self, target_iters: u32) -> u32 {
+        if !self.progressive {
+            self.refinement_level = REFINEMENT_LEVELS;
+            return target_iters;
+        }
+
+        let fractal_state = &self.uniform_data.fractal_state;
+        let view = (fractal_state.camera.clone(), fractal_state.zoom.clone());
+        if self.refined_view.as_ref() != Some(&view) {
+            self.refined_view = Some(view);
+            self.refinement_level = 0;
+        } else if self.refinement_level < REFINEMENT_LEVELS {
+            self.refinement_level += 1;
+        }
+
+        let shift = 2 * (REFINEMENT_LEVELS - self.refinement_level);
+        let iterations = (target_iters >> shift).max(PREVIEW_MIN_ITER);
+        if iterations >= target_iters {
+            // Cheap enough to skip the remaining levels
+            self.refinement_level = REFINEMENT_LEVELS;
+            return target_iters;
+        }
+        iterations
+    }
+
+    /// Whether progressive refinement has more levels to draw, the window keeps redrawing.
+    pub fn is_refining(&self) -> bool {
+        self.refinement_level < REFINEMENT_LEVELS
+    }
+
+    /// Fits the series approximation to the uploaded orbit and the current framing, or
+    /// turns it off where the shader couldn't use it: Julia views, double-single deltas,
+    /// which the f32 series can't start accurately enough, and the stripe and trap
+    /// colorings, which need every step of the orbit.
+    fn update_series(&mut self) {
+        let uniforms = &self.uniform_data.uniforms;
+        let usable = uniforms.fractal_mode == FRACTAL_MODE_MANDELBROT
+            && uniforms.double_single == 0
+            && uniforms.color_mode != COLOR_MODE_STRIPE
+            && uniforms.color_mode != COLOR_MODE_TRAP;
+
+        let radius = self.sample_radius();
+        let series = if usable {
+            series_approximation(&self.uniform_data.orbit, uniforms.iter_count, radius)
+        } else {
+            None
+        };
+        let uniforms = &mut self.uniform_data.uniforms;
+        match series {
+            Some(series) => {
+                let [a, b, c] = series.coefficients.map(|[re, im]| [re as f32, im as f32]);
+                uniforms.series_skip = series.skip;
+                uniforms.series_a = a;
+                uniforms.series_b = b;
+                uniforms.series_c = c;
+                uniforms.series_radius = radius as f32;
+            }
+            None => uniforms.series_skip = 0,
+        }
+    }
+
+    /// Rebuilds and uploads the BLA table when the orbit (`orbit_changed`), its length or
+    /// the |dc| bound changed. The bound is rounded up to a power of two, so zooming only
+    /// rebuilds it every octave. Off where the shader has to see every step: double-single
+    /// deltas, which it couldn't carry through, and the stripe and trap colorings.
+    fn update_bla(&mut self, orbit_changed: bool) {
+        let uniforms = &self.uniform_data.uniforms;
+        let usable = uniforms.double_single == 0
+            && uniforms.color_mode != COLOR_MODE_STRIPE
+            && uniforms.color_mode != COLOR_MODE_TRAP;
+        if !usable {
+            self.uniform_data.uniforms.bla_levels = 0;
+            self.bla_key = None;
+            self.bla_skip_ratio = None;
+            return;
+        }
+
+        // Julia pixels only differ in where they start, dc never enters
+        let julia = uniforms.fractal_mode == FRACTAL_MODE_JULIA;
+        let dc_max = if julia {
+            0.0
+        } else {
+            2f64.powf(self.sample_radius().log2().ceil())
+        };
+        let key = (uniforms.iter_count, dc_max);
+        if !orbit_changed && self.bla_key == Some(key) {
+            return;
+        }
+
+        let orbit = &self.uniform_data.orbit;
+        let table = bla_table(orbit, uniforms.iter_count, dc_max);
+        self.queue.write_buffer(
+            &self.uniform_data.bla_buffer,
+            0,
+            bytemuck::cast_slice(&table.steps),
+        );
+        self.orbit_upload_bytes += std::mem::size_of_val(table.steps.as_slice()) as u64;
+
+        // Like the glitch estimate, the measurement only knows the Mandelbrot recurrence
+        let valid_len = (uniforms.iter_count as usize).min(orbit.len());
+        self.bla_skip_ratio = (!julia && table.levels > 0).then(|| {
+            table.skip_ratio(
+                &orbit[..valid_len],
+                uniforms.offset,
+                uniforms.zoom,
+                uniforms.aspect,
+                uniforms.max_iter,
+                uniforms.rebase != 0,
+            )
+        });
+
+        let uniforms = &mut self.uniform_data.uniforms;
+        uniforms.bla_len = table.len;
+        uniforms.bla_levels = table.levels;
+        self.bla_key = Some(key);
+    }
+
+    /// Farthest a sample can be from the reference in complex units: a corner, past the
+    /// camera's offset, plus half a pixel for supersampling.
+    fn sample_radius(&self) -> f64 {
+        let uniforms = &self.uniform_data.uniforms;
+        let [offset_x, offset_y] = uniforms.offset;
+        let reach = uniforms.aspect.hypot(1.0)
+            + offset_x.hypot(offset_y)
+            + uniforms.pixel_size * uniforms.zoom;
+        reach as f64 / uniforms.zoom as f64
+    }
+
+    /// Size of one pixel in complex units when the view is `height` pixels tall.
+    /// Computed in full precision, only the result has to fit in f32.
+    fn pixel_size(&self, height: u32) -> f32 {
+        let view_height = Float::with_val(PRECISION, 2.0) / &self.uniform_data.fractal_state.zoom;
+        (view_height / height.max(1)).to_f32()
+    }
+
+    /// log10 of the deepest zoom the shader can resolve. Pixel deltas are f32, so once a
+    /// pixel is smaller than the smallest normal f32 they lose precision and then flush
+    /// to zero, and neighbouring pixels merge into blocks.
+    pub fn gpu_zoom_limit(&self) -> f64 {
+        let (_, height) = self.scaled_size();
+        (2.0 / (f32::MIN_POSITIVE as f64 * height as f64)).log10()
+    }
+
+    /// Whether the view is deeper than `gpu_zoom_limit`.
+    pub fn past_gpu_zoom_limit(&self) -> bool {
+        let log_zoom = self
+            .uniform_data
+            .fractal_state
+            .zoom
+            .clone()
+            .log10()
+            .to_f64();
+        log_zoom > self.gpu_zoom_limit()
+    }
+
+    /// Offset (Reference -> Camera) in screen space for the orbit on the GPU.
+    fn reference_offset(&self) -> [f32; 2] {
+        self.uniform_data
+            .fractal_state
+            .offset_from(&self.orbit_reference)
+    }
+
+    /// Writes the current reference orbit to a CSV file for offline inspection.
+    /// Header lines hold the full precision reference point and the orbit length.
+    pub fn dump_orbit(&self, path: &Path) -> std::io::Result<()> {
+        let fractal_state = &self.uniform_data.fractal_state;
+        let valid_len = self.uniform_data.uniforms.iter_count;
+
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(
+            file,
+            "# reference_re,{}",
+            self.orbit_reference.real().to_string_radix(10, None)
+        )?;
+        writeln!(
+            file,
+            "# reference_im,{}",
+            self.orbit_reference.imag().to_string_radix(10, None)
+        )?;
+        writeln!(file, "# orbit_precision,{}", fractal_state.orbit_precision)?;
+        writeln!(file, "# valid_len,{}", valid_len)?;
+        writeln!(file, "iteration,z_re,z_im,dz_re,dz_im")?;
+
+        for (i, [z_re, z_im, dz_re, dz_im]) in self
+            .uniform_data
+            .orbit
+            .iter()
+            .take(valid_len as usize)
+            .enumerate()
+        {
+            writeln!(file, "{},{},{},{},{}", i, z_re, z_im, dz_re, dz_im)?;
+        }
+
+        file.flush()
+    }
+
+    fn write_uniforms(&self) {
         self.queue.write_buffer(
             &self.uniform_data.uniform_buffer,
             0,
@@ -430,3 +2593,126 @@ impl WgpuState<'_> {
         );
     }
 }
+
+/// Decodes an IEEE half float, as stored in `HDR_EXPORT_FORMAT` textures.
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24), // Subnormal
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// The sRGB transfer function, what *Srgb texture formats apply on write.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl Drop for WgpuState<'_> {
+    fn drop(&mut self) {
+        // Let queued work (exports, readbacks, the last frame) finish before the
+        // buffers and device are released, instead of tearing down mid-submission
+        match self.device.poll(wgpu::PollType::wait_indefinitely()) {
+            Ok(_) => println!("GPU idle, releasing resources"),
+            Err(e) => eprintln!("Failed to wait for the GPU before shutdown: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_aspect_keeps_the_center() {
+        // Needs an adapter, software ones like llvmpipe do
+        let mut state = match pollster::block_on(WgpuState::new_headless(65, 33, None)) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("Skipping, no headless GPU: {}", e);
+                return;
+            }
+        };
+        // Between the spirals of seahorse valley, neighbouring pixels differ in color
+        let params = RenderParams {
+            camera: Complex::with_val(PRECISION, (-0.7453, 0.1127)),
+            zoom: Float::with_val(PRECISION, 1e3),
+            max_iter: Some(1000),
+        };
+
+        // Odd sizes put a pixel center exactly on the camera
+        let square = state.render_params_to_image(&params, 33, 33).unwrap();
+        let wide = state.render_params_to_image(&params, 65, 33).unwrap();
+        let tall = state.render_params_to_image(&params, 33, 65).unwrap();
+
+        let center = square.get_pixel(16, 16);
+        assert_ne!(square.get_pixel(15, 16), center);
+        assert_eq!(wide.get_pixel(32, 16), center);
+        assert_eq!(tall.get_pixel(16, 32), center);
+    }
+
+    /// Resizes the window to `width`x`height` and renders it at the aspect `update` uses.
+    fn render_resized(state: &mut WgpuState, width: u32, height: u32) -> image::RgbaImage {
+        state.resize(PhysicalSize::new(width, height));
+        state.update();
+        assert_eq!(
+            state.uniform_data.uniforms.aspect,
+            width as f32 / height as f32
+        );
+        state.render_to_image(width, height, state.view_aspect())
+    }
+
+    /// Fraction of `small`'s pixels equal to the ones of `large` at `x0`, `y0` further on.
+    fn overlap(large: &image::RgbaImage, x0: u32, y0: u32, small: &image::RgbaImage) -> f32 {
+        let same = small
+            .enumerate_pixels()
+            .filter(|&(x, y, pixel)| large.get_pixel(x + x0, y + y0) == pixel)
+            .count();
+        same as f32 / (small.width() * small.height()) as f32
+    }
+
+    #[test]
+    fn resize_keeps_the_center_and_scales_the_width() {
+        let mut state = match pollster::block_on(WgpuState::new_headless(33, 33, None)) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("Skipping, no headless GPU: {}", e);
+                return;
+            }
+        };
+        state
+            .set_view(&RenderParams {
+                camera: Complex::with_val(PRECISION, (-0.7453, 0.1127)),
+                zoom: Float::with_val(PRECISION, 1e3),
+                max_iter: Some(1000),
+            })
+            .unwrap();
+
+        let square = render_resized(&mut state, 33, 33);
+        let wide = render_resized(&mut state, 65, 33);
+        let large = render_resized(&mut state, 65, 65);
+        let tall = render_resized(&mut state, 33, 65);
+
+        // The center pixel sits exactly on the camera whatever the shape
+        assert_eq!(wide.get_pixel(32, 16), square.get_pixel(16, 16));
+        assert_eq!(tall.get_pixel(16, 32), large.get_pixel(32, 32));
+
+        // The height always spans the same range, so a wider window shows the square one with
+        // 16 more columns on either side, and a narrower one crops the sides of the larger
+        // square. Off the center the pixel coordinates round differently in f32, which flips
+        // a few pixels on the boundary, so only most of them have to match.
+        assert!(overlap(&wide, 16, 0, &square) > 0.9);
+        assert!(overlap(&large, 16, 0, &tall) > 0.9);
+        // Off by a column they don't line up, so the extent did scale with the aspect
+        assert!(overlap(&wide, 15, 0, &square) < 0.5);
+        assert!(overlap(&large, 17, 0, &tall) < 0.5);
+    }
+}
@@ -1,15 +1,65 @@
-use std::{ops::AddAssign, sync::Arc};
+use std::{
+    collections::HashSet,
+    fs,
+    ops::AddAssign,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use rug::Float;
+use arboard::Clipboard;
+use rug::{Assign, Complex, Float};
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalPosition,
-    event::{self, WindowEvent},
+    event::{self, ElementState, MouseButton, WindowEvent},
     event_loop::ActiveEventLoop,
+    keyboard::{Key, KeyCode, ModifiersState, PhysicalKey},
     window::{Window, WindowId},
 };
 
-use crate::wgpu::WgpuState;
+use crate::{
+    animation::{Autopilot, CameraFlight, Demo, Easing, landmark_view},
+    cli::{Args, BitDepth, Palette, ZoomAnchor},
+    julia::{JULIA_UPDATE_INTERVAL, JULIA_ZOOM, in_rect},
+    location::format_location,
+    math::{
+        BITS_PER_DECADE, FractalMode, HighPrecisionState, MAX_ITER, NUCLEUS_SEARCH_RADIUS,
+        PRECISION, ball_period, find_nucleus, minibrot_size, target_iterations,
+    },
+    metadata::{ViewMetadata, read_png, save_png, save_png_16},
+    minimap::{MINIMAP_JUMP_ZOOM, minimap_point, minimap_rect},
+    panel::PanelAction,
+    primitives::{
+        COLOR_MODE_DISTANCE, COLOR_MODE_ESCAPE_TIME, COLOR_MODE_STRIPE, COLOR_MODE_TRAP,
+        COLOR_SCALE_LOG, COLOR_SCALE_POWER, COLOR_SCALE_SQRT, MAX_AA_SAMPLES, PALETTE_COUNT,
+    },
+    recipe::Recipe,
+    render::{downsample, parse_float},
+    scale::RENDER_SCALE_LEVELS,
+    timelapse::{DEFAULT_TIMELAPSE_INTERVAL, Timelapse},
+    view_params::ViewParams,
+    wgpu::{RenderParams, SurfaceError, WgpuState},
+};
+
+// How far one press of , or . turns the view
+const ROTATION_STEP_DEGREES: f64 = 15.0;
+
+// How often the diagnostics overlay refreshes, so the numbers stay readable
+const DIAGNOSTICS_INTERVAL: Duration = Duration::from_millis(250);
+
+// Candidate references tried by the first periodic refresh of a static view, doubled
+// on every further refresh up to the maximum
+const REFERENCE_REFRESH_MIN_SAMPLES: u32 = 256;
+const REFERENCE_REFRESH_MAX_SAMPLES: u32 = 8192;
+
+// Zoom factor per second while Page Up or Page Down is held
+const KEY_ZOOM_PER_SECOND: f64 = 4.0;
+// Zoom factor of one Ctrl + =/- press
+const KEY_ZOOM_STEP: f64 = 2.0;
+
+// Height of the grab area along the top of a frameless window, in logical pixels
+const DRAG_STRIP_HEIGHT: f64 = 24.0;
 
 #[derive(Default)]
 pub struct App<'a> {
@@ -17,24 +67,221 @@ pub struct App<'a> {
     window: Option<Arc<Window>>,
 
     cursor_position: Option<PhysicalPosition<f64>>,
+    last_mouse_move: Option<Instant>,
+    cursor_hidden: bool,                       // Hidden by `--hide-cursor`
+    drag_start: Option<PhysicalPosition<f64>>, // Corner of the box-zoom selection
+    pan_anchor: Option<PhysicalPosition<f64>>, // Cursor position the pan last moved to
+    modifiers: ModifiersState,
+    held_keys: HashSet<KeyCode>, // Physical keys currently down, for continuous motion
+    last_key_motion: Option<Instant>, // When held keys last moved the view
+    args: Args,
+
+    flight: Option<CameraFlight>, // Active animated jump, if any
+    instant_jumps: bool,
+    easing: Easing,
+    zoom_anchor: ZoomAnchor,
+
+    last_input: Option<Instant>,
+    autopilot: Option<Autopilot>, // Idle zoom, runs until the next input
+    demo: Option<Demo>,           // Tour shown on launch, also runs until the next input
+    timelapse: Option<Timelapse>,
+    last_reference_refresh: Option<Instant>,
+    reference_refresh_samples: u32, // Samples for the next refresh, 0 = start over
+
+    show_hud: bool,
+    show_diagnostics: bool,
+    diagnostics_text: String, // Last diagnostics readout, refreshed every DIAGNOSTICS_INTERVAL
+    last_diagnostics_update: Option<Instant>,
+    julia_pending: Option<Complex>, // Julia constant waiting for the next preview update
+    mandelbrot_view: Option<(Complex, Float)>, // Camera and zoom a Julia set was picked at
+    clipboard: Option<Clipboard>, // Opened on first use and kept, some platforms drop copies with it
+    last_julia_update: Option<Instant>,
+    last_frame: Option<Instant>,
+    fps: f32, // Smoothed frames per second, shown on the HUD
+    nudge: Option<Nudge>,
+}
+
+/// Pixels the camera was nudged with the arrow keys. Only meaningful at the zoom it was
+/// made at, any zoom change starts a new count.
+struct Nudge {
+    zoom: Float,
+    pixels: (i64, i64), // Right and up
 }
 
 impl ApplicationHandler for App<'_> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
             let window = event_loop
-                .create_window(Window::default_attributes().with_title("Mandelbrot"))
+                .create_window(
+                    Window::default_attributes()
+                        .with_title("Mandelbrot")
+                        .with_transparent(self.args.alpha_mode.is_transparent())
+                        .with_decorations(!self.args.frameless),
+                )
                 .expect("Unable to create window");
             let window = Arc::new(window);
 
             self.window = Some(window.clone());
 
-            let wgpu_state = pollster::block_on(WgpuState::new(window.clone()));
+            let mut wgpu_state = pollster::block_on(WgpuState::new(
+                window.clone(),
+                self.args.shader.as_deref(),
+                self.args.alpha_mode.composite_mode(),
+            ));
+            wgpu_state.uniform_data.uniforms.color_scale_mode =
+                self.args.color_scale.uniform_value();
+            wgpu_state.uniform_data.uniforms.color_exponent = self.args.color_exponent;
+            wgpu_state.uniform_data.uniforms.stripe_frequency = self.args.stripe_frequency;
+            wgpu_state.uniform_data.uniforms.smooth_coloring = !self.args.banded as u32;
+            wgpu_state.uniform_data.uniforms.palette_id = self.args.palette.uniform_value();
+            wgpu_state.uniform_data.uniforms.boundary_aa = self.args.boundary_aa as u32;
+            wgpu_state.uniform_data.uniforms.aa_samples = self.args.shader_aa;
+            wgpu_state.ocean_threshold = self.args.ocean_threshold;
+            wgpu_state.max_orbit_precision = self.args.max_precision;
+            wgpu_state.set_double_single(self.args.double_single);
+            wgpu_state.set_render_scale(self.args.render_scale);
+            wgpu_state.progressive = self.args.progressive;
+            if self.args.msaa && !wgpu_state.set_msaa(true) {
+                eprintln!("MSAA is not supported for this window's format, drawing without it.");
+            }
+            wgpu_state.rotation = self.args.rotation.to_radians();
+            wgpu_state.set_auto_exposure(self.args.auto_exposure);
+            wgpu_state.start_orbit_worker();
+            wgpu_state
+                .uniform_data
+                .uniforms
+                .set_exterior(self.args.exterior_color, self.args.exterior_ramp);
+            wgpu_state
+                .uniform_data
+                .uniforms
+                .set_glitches(self.args.glitches.uniform_value(), self.args.glitch_color);
+            if let Some(path) = &self.args.trap_image {
+                match wgpu_state.load_trap_image(path, self.args.trap_center, self.args.trap_size) {
+                    Ok(()) => wgpu_state.uniform_data.uniforms.color_mode = COLOR_MODE_TRAP,
+                    Err(e) => eprintln!("Failed to load the trap image: {}", e),
+                }
+            }
+            if let Err(e) = wgpu_state.set_iteration_cap(self.args.iteration_cap) {
+                eprintln!(
+                    "Failed to set --iteration-cap {}: {}",
+                    self.args.iteration_cap, e
+                );
+            }
             self.state = Some(wgpu_state);
 
+            if let Some(path) = &self.args.from_image
+                && let Some(state) = &mut self.state
+            {
+                match read_png(path).and_then(|metadata| metadata.apply(state)) {
+                    Ok(()) => println!("Restored view from {}", path.display()),
+                    Err(e) => eprintln!("Failed to restore view from {}: {}", path.display(), e),
+                }
+            }
+
+            if let Some(path) = &self.args.recipe
+                && let Some(state) = &mut self.state
+            {
+                match Recipe::load(path).and_then(|recipe| recipe.apply(state).map(|_| recipe)) {
+                    Ok(recipe) => {
+                        println!("Loaded recipe from {}", path.display());
+                        // Remembered so F2 saves the trap image back into new recipes
+                        if let Some(trap) = recipe.coloring.trap {
+                            self.args.trap_image = Some(trap.image);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to load recipe {}: {}", path.display(), e),
+                }
+            }
+
+            if self.has_start_view()
+                && let Some(state) = &mut self.state
+                && let Err(e) = Self::apply_start_view(state, &self.args)
+            {
+                eprintln!("Ignoring the start view: {}", e);
+            }
+
+            // Applied after the image and recipe so it can override the stored count
+            if let Some(max_iter) = self.args.max_iter
+                && let Some(state) = &mut self.state
+                && let Err(e) = state.pin_iterations(max_iter)
+            {
+                eprintln!("Failed to set --max-iter {}: {}", max_iter, e);
+            }
+
+            // A restored view is where the user wants to be, so only a fresh start gets the demo
+            if !self.args.no_demo
+                && self.args.from_image.is_none()
+                && self.args.recipe.is_none()
+                && !self.has_start_view()
+                && let Some(state) = &self.state
+            {
+                println!("Starting the demo, any input takes over.");
+                self.demo = Some(Demo::new(
+                    &state.uniform_data.fractal_state,
+                    self.args.autopilot_speed,
+                ));
+            }
+
+            if self.args.timelapse_interval.is_some() || self.args.timelapse_decades.is_some() {
+                self.toggle_timelapse();
+            }
+
             println!("Window created.");
             println!("Controls:");
             println!("  - Scroll: Zoom in/out");
+            println!("  - Left drag: Pan the view");
+            println!("  - Shift + left drag: Zoom to the selected box");
+            println!("  - Click the minimap: Jump to that point");
+            println!("  - N: Toggle the minimap");
+            println!("  - J: Toggle a Julia preview for the point under the cursor");
+            println!("  - Tab: Toggle split screen with the Julia set on the right");
+            println!(
+                "  - Ctrl + click: Explore the Julia set of the clicked point, again to go back"
+            );
+            println!("  - Home: Jump back to the full view");
+            println!("  - Arrow keys: Pan, hold to keep moving");
+            println!("  - Shift + arrow keys: Nudge the camera by one pixel");
+            println!("  - Page Up / Page Down: Zoom in/out at the center, hold to keep zooming");
+            println!("  - Ctrl + = / Ctrl + -: Zoom in/out at the center by one 2x step");
+            println!("  - , / .: Rotate the view counterclockwise/clockwise");
+            println!("  - U: Fly to the nearest minibrot");
+            println!("  - 1-9: Jump to zoom 10^(2 x key) at the current center");
+            println!("  - I: Toggle animated/instant jumps");
+            println!("  - G: Cycle the jump easing curve");
+            println!(
+                "  - D: Cycle coloring (escape time/distance estimate/stripe average/image trap)"
+            );
+            println!("  - Shift + D: Toggle smooth/banded coloring");
+            println!("  - B: Toggle reference orbit rebasing");
+            println!("  - K: Toggle boundary anti-aliasing");
+            println!("  - A: Toggle 4x MSAA for the overlays");
+            println!("  - W: Toggle double-single perturbation deltas");
+            println!("  - [ / ]: Fewer/more supersamples per pixel");
+            println!(
+                "  - {}: Cycle the render scale (0.25x/0.5x/1x/2x)",
+                self.args.render_scale_key
+            );
+            println!("  - R: Force the reference to the camera");
+            println!("  - L: Lock/unlock the reference point");
+            println!("  - M: Cycle the color scale (linear/log/sqrt/power)");
+            println!(
+                "  - Shift + P: Cycle the palette (rainbow/Ultra Fractal/fire/grayscale/blue)"
+            );
+            println!("  - Y: Toggle auto exposure");
+            println!("  - E: Export image");
+            println!("  - S: Save a screenshot of the window, overlays included");
+            println!("  - F: Save the location for another fractal program");
+            println!("  - O: Dump reference orbit to CSV");
+            println!("  - C / V: Copy the location to the clipboard / jump to a pasted one");
+            println!("  - Ctrl + S / Ctrl + O: Save/load the view as JSON (see --view-file)");
+            println!("  - Right click: Trace the orbit of that point, X clears it");
+            println!("  - T: Start/stop a timelapse");
+            println!("  - P: Cycle CPU precision (auto/f64/rug)");
+            println!("  - H: Toggle the HUD");
+            println!("  - F3: Toggle the diagnostics overlay");
+            println!("  - F4: Toggle the precision loss view");
+            println!("  - F5: Toggle incremental/full orbit uploads");
+            println!("  - Z: Toggle zooming toward the cursor or the center");
 
             window.request_redraw();
         }
@@ -46,17 +293,144 @@ impl ApplicationHandler for App<'_> {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
+        // The settings panel sees events first, so clicks and scrolls on it don't move the view
+        if let (Some(state), Some(window)) = (&mut self.state, &self.window)
+            && let Some(panel) = &mut state.panel
+            && panel.on_window_event(window, &event)
+        {
+            return;
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 println!("Close requested, exiting.");
                 event_loop.exit();
             }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.register_input();
+                let pressed = event.state == ElementState::Pressed && !event.repeat;
+
+                // Motion keys act for as long as they're held, see `apply_held_keys`
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed => {
+                            if self.held_keys.insert(key) && is_motion_key(key) {
+                                self.window.as_ref().unwrap().request_redraw();
+                            }
+                        }
+                        ElementState::Released => {
+                            self.held_keys.remove(&key);
+                        }
+                    }
+                }
+
+                // The configurable key goes by the character, so it follows the keyboard layout
+                let scale_key = self.args.render_scale_key.to_string();
+                if pressed
+                    && let Key::Character(text) = &event.logical_key
+                    && text.eq_ignore_ascii_case(&scale_key)
+                {
+                    self.cycle_render_scale();
+                } else if pressed && let PhysicalKey::Code(key) = event.physical_key {
+                    self.handle_key(key);
+                }
+            }
             WindowEvent::CursorMoved { position, .. } => {
+                self.register_input();
                 self.cursor_position = Some(position);
+                self.last_mouse_move = Some(Instant::now());
+                if std::mem::take(&mut self.cursor_hidden) {
+                    self.window.as_ref().unwrap().set_cursor_visible(true);
+                }
+
+                // Drag the view along with the cursor
+                if let Some(anchor) = self.pan_anchor.replace(position) {
+                    self.pan_view(position.x - anchor.x, position.y - anchor.y);
+                }
+
+                // Update the box-zoom selection while dragging
+                if let (Some(start), Some(state)) = (self.drag_start, &mut self.state) {
+                    state.selection = Some(Self::selection_ndc(state, start, position));
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+
+                // The Julia view follows the point under the cursor, which is only a c
+                // while the main view is the Mandelbrot set
+                if let Some(state) = &self.state
+                    && state.uniform_data.fractal_state.fractal_mode == FractalMode::Mandelbrot
+                    && let Some(julia_rect) = state.julia_viewport()
+                    && !in_rect(julia_rect, position.x, position.y)
+                {
+                    self.julia_pending = Some(Self::screen_to_complex(state, position));
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+
+                // Keep the HUD's cursor readout current
+                if self.show_hud {
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.register_input();
+                match button_state {
+                    ElementState::Pressed => {
+                        if self.in_drag_strip() {
+                            // No title bar, so the top edge moves the window instead
+                            let window = self.window.as_ref().unwrap();
+                            if let Err(e) = window.drag_window() {
+                                eprintln!("Failed to drag the window: {}", e);
+                            }
+                        } else if let Some(point) = self.minimap_point_under_cursor() {
+                            // Clicks on the minimap navigate instead of starting a box zoom
+                            let zoom = Float::with_val(PRECISION, MINIMAP_JUMP_ZOOM);
+                            self.jump_to(&point, &zoom);
+                        } else if self.modifiers.control_key() {
+                            self.toggle_julia_mode();
+                        } else if self.modifiers.shift_key() {
+                            self.drag_start = self.cursor_position;
+                        } else {
+                            self.pan_anchor = self.cursor_position;
+                        }
+                    }
+                    ElementState::Released => {
+                        self.pan_anchor = None;
+                        self.finish_box_zoom();
+                    }
+                }
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.register_input();
+                self.trace_orbit_under_cursor();
+            }
+            WindowEvent::MouseInput { .. } => {
+                self.register_input();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::Focused(false) => {
+                // Releases while unfocused never arrive, so don't keep moving
+                self.held_keys.clear();
             }
             // Handle Scrolling (Zoom)
             WindowEvent::MouseWheel { delta, .. } => {
-                let cursor_pos = self.cursor_position;
+                // Manual zoom takes over from any running jump
+                self.register_input();
+                self.flight = None;
+
+                // Center anchoring skips the cursor shift entirely
+                let cursor_pos = match self.zoom_anchor {
+                    ZoomAnchor::Cursor => self.cursor_position,
+                    ZoomAnchor::Center => None,
+                };
                 if let Some(state) = &mut self.state {
                     Self::update_camera(state, cursor_pos, delta);
 
@@ -73,13 +447,122 @@ impl ApplicationHandler for App<'_> {
                 }
             }
             WindowEvent::RedrawRequested => {
+                // Sleep out the rest of the frame budget, before the animations sample the clock
+                if let (Some(max_fps), Some(last)) = (self.args.max_fps, self.last_frame) {
+                    let budget = Duration::from_secs_f64(1.0 / max_fps as f64);
+                    if let Some(remaining) = budget.checked_sub(last.elapsed()) {
+                        std::thread::sleep(remaining);
+                    }
+                }
+
+                self.update_julia_preview();
+                self.apply_held_keys();
+
+                let mut panel_actions = Vec::new();
                 if let Some(state) = &mut self.state {
+                    // Lay out the panel first, so its changes show up in this frame
+                    if let Some(mut panel) = state.panel.take() {
+                        panel_actions = panel.build(self.window.as_ref().unwrap(), state, self.fps);
+                        state.panel = Some(panel);
+                    }
+
+                    if let Some(autopilot) = &mut self.autopilot {
+                        autopilot.step(&mut state.uniform_data.fractal_state);
+                        self.window.as_ref().unwrap().request_redraw();
+                    }
+                    if let Some(demo) = &mut self.demo {
+                        demo.step(&mut state.uniform_data.fractal_state);
+                        self.window.as_ref().unwrap().request_redraw();
+                    }
+
+                    // Advance an animated jump before computing the frame
+                    if let Some(flight) = &self.flight {
+                        let (camera, zoom, finished) = flight.sample();
+                        state.uniform_data.fractal_state.camera.assign(&camera);
+                        state.uniform_data.fractal_state.zoom = zoom;
+
+                        if finished {
+                            self.flight = None;
+                        } else {
+                            self.window.as_ref().unwrap().request_redraw();
+                        }
+                    }
+
+                    let update_start = Instant::now();
                     state.update();
+                    let update_time = update_start.elapsed();
+
+                    // Smooth the frame rate so the readout doesn't flicker
+                    let now = Instant::now();
+                    if let Some(last) = self.last_frame {
+                        let frame_fps = 1.0 / now.duration_since(last).as_secs_f32().max(1e-6);
+                        self.fps = if self.fps > 0.0 {
+                            self.fps * 0.9 + frame_fps * 0.1
+                        } else {
+                            frame_fps
+                        };
+                    }
+                    self.last_frame = Some(now);
+
+                    if self.show_diagnostics
+                        && self
+                            .last_diagnostics_update
+                            .is_none_or(|last| now.duration_since(last) >= DIAGNOSTICS_INTERVAL)
+                    {
+                        self.diagnostics_text =
+                            Self::diagnostics_text(state, self.fps, update_time);
+                        self.last_diagnostics_update = Some(now);
+                    }
+
+                    let mut overlay = Vec::new();
+                    if self.show_hud {
+                        let nudge = self
+                            .nudge
+                            .as_ref()
+                            .filter(|nudge| nudge.zoom == state.uniform_data.fractal_state.zoom)
+                            .map(|nudge| nudge.pixels);
+                        let cursor = self
+                            .cursor_position
+                            .map(|position| Self::screen_to_complex(state, position));
+                        overlay.push(Self::hud_text(state, self.fps, nudge, cursor.as_ref()));
+                    }
+                    if self.show_diagnostics {
+                        overlay.push(self.diagnostics_text.clone());
+                    }
+                    state.hud_text = (!overlay.is_empty()).then(|| overlay.join("\n\n"));
+
                     match state.render() {
+                        // Keep drawing until a cross-fade or refinement is done
+                        Ok(_) if state.is_fading() || state.is_refining() => {
+                            self.window.as_ref().unwrap().request_redraw()
+                        }
                         Ok(_) => {}
-                        Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-                        Err(wgpu::SurfaceError::OutOfMemory) => event_loop.exit(),
-                        Err(e) => eprintln!("{:?}", e),
+                        Err(SurfaceError::Lost) => state.resize(state.size),
+                        // Hidden, the next redraw after it's shown again draws
+                        Err(SurfaceError::Occluded) => {}
+                        // Retries ran out, try again with the next frame instead of dropping this one
+                        Err(e) => {
+                            eprintln!("Failed to get a frame: {:?}", e);
+                            self.window.as_ref().unwrap().request_redraw();
+                        }
+                    }
+
+                    if let Some(image) = state.take_screenshot() {
+                        let path = screenshot_path(state);
+                        match save_png(&image, &path, &ViewMetadata::from_state(state)) {
+                            Ok(()) => println!("Saved screenshot to {}", path.display()),
+                            Err(e) => eprintln!("Failed to save {}: {}", path.display(), e),
+                        }
+                    }
+                }
+
+                for action in panel_actions {
+                    match action {
+                        PanelAction::Landmark(index) => {
+                            let (camera, zoom) = landmark_view(index);
+                            self.jump_to(&camera, &zoom);
+                        }
+                        PanelAction::Home => self.handle_key(KeyCode::Home),
                     }
                 }
             }
@@ -88,12 +571,1347 @@ impl ApplicationHandler for App<'_> {
                     state.resize(physical_size);
                 }
             }
+            WindowEvent::Occluded(false) => {
+                // The compositor may have dropped the old contents
+                if let Some(state) = &mut self.state {
+                    state.invalidate_frame();
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                if let Some(state) = &mut self.state {
+                    state.scale_factor = scale_factor as f32;
+                }
+            }
             _ => {}
         }
     }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        self.redraw_when_orbit_ready();
+        self.capture_timelapse_frame();
+        self.refresh_reference_if_static();
+        self.start_autopilot_if_idle();
+        self.hide_cursor_if_idle();
+    }
 }
 
 impl<'a> App<'a> {
+    pub fn new(args: Args) -> Self {
+        Self {
+            instant_jumps: args.instant_jumps,
+            easing: args.easing,
+            zoom_anchor: args.zoom_anchor,
+            last_input: Some(Instant::now()),
+            show_hud: true,
+            args,
+            ..Default::default()
+        }
+    }
+
+    /// Draws a frame as soon as the orbit worker is done, the redraw's update picks it up.
+    fn redraw_when_orbit_ready(&mut self) {
+        if let (Some(state), Some(window)) = (&mut self.state, &self.window)
+            && state.poll_orbit_worker()
+        {
+            window.request_redraw();
+        }
+    }
+
+    /// Hides the cursor once the mouse has been still for `--hide-cursor` seconds.
+    /// Only the arrow goes away, the last position still anchors zooming.
+    fn hide_cursor_if_idle(&mut self) {
+        let (Some(timeout), Some(window)) = (self.args.hide_cursor, &self.window) else {
+            return;
+        };
+        if self.cursor_hidden {
+            return;
+        }
+
+        // Counted from launch until the mouse first moves
+        let still_since = self.last_mouse_move.get_or_insert_with(Instant::now);
+        if still_since.elapsed() >= Duration::from_secs(timeout) {
+            window.set_cursor_visible(false);
+            self.cursor_hidden = true;
+        }
+    }
+
+    /// Starts the autopilot once the user has been away long enough.
+    fn start_autopilot_if_idle(&mut self) {
+        let Some(timeout) = self.args.idle_timeout else {
+            return;
+        };
+        if self.autopilot.is_some() || self.demo.is_some() || self.flight.is_some() {
+            return;
+        }
+
+        let idle = self
+            .last_input
+            .is_some_and(|t| t.elapsed() >= Duration::from_secs(timeout));
+        if let Some(state) = &self.state
+            && idle
+        {
+            println!("Idle for {}s, starting autopilot.", timeout);
+            self.autopilot = Some(Autopilot::new(
+                &state.uniform_data.fractal_state,
+                self.args.autopilot_speed,
+            ));
+            self.window.as_ref().unwrap().request_redraw();
+        }
+    }
+
+    /// While a timelapse records a view that isn't moving, looks for a better reference
+    /// every `--reference-refresh` seconds with a growing number of samples.
+    fn refresh_reference_if_static(&mut self) {
+        let interval = Duration::from_secs(self.args.reference_refresh);
+        if interval.is_zero()
+            || self.timelapse.is_none()
+            || self.flight.is_some()
+            || self.autopilot.is_some()
+            || self.demo.is_some()
+        {
+            return;
+        }
+
+        let is_static = self.last_input.is_some_and(|t| t.elapsed() >= interval);
+        let due = self
+            .last_reference_refresh
+            .is_none_or(|t| t.elapsed() >= interval);
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if !is_static || !due {
+            return;
+        }
+        self.last_reference_refresh = Some(Instant::now());
+
+        let samples = self
+            .reference_refresh_samples
+            .max(REFERENCE_REFRESH_MIN_SAMPLES);
+        if state.refresh_reference(samples) {
+            println!("Found a better reference with {} samples.", samples);
+            self.window.as_ref().unwrap().request_redraw();
+        }
+        self.reference_refresh_samples = (samples * 2).min(REFERENCE_REFRESH_MAX_SAMPLES);
+    }
+
+    /// Resets the idle timer and hands control back from the autopilot or the demo.
+    fn register_input(&mut self) {
+        self.last_input = Some(Instant::now());
+        // The view may move, a later refresh starts small again
+        self.reference_refresh_samples = 0;
+        if self.autopilot.take().is_some() {
+            println!("Autopilot stopped.");
+        }
+        if self.demo.take().is_some() {
+            println!("Demo stopped.");
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Home => {
+                let Some(state) = &mut self.state else {
+                    return;
+                };
+                state.rotation = 0.0;
+                state.reset_iteration_shift();
+                let home = HighPrecisionState::with_default_view(state.view_aspect() as f64);
+                if state.uniform_data.fractal_state.fractal_mode == FractalMode::Julia {
+                    let zoom = Float::with_val(PRECISION, JULIA_ZOOM);
+                    self.jump_to(&Complex::new(PRECISION), &zoom);
+                } else {
+                    self.jump_to(&home.camera, &home.zoom);
+                }
+            }
+            // Plain arrows pan while held, with Shift they step by exact pixels
+            KeyCode::ArrowLeft if self.modifiers.shift_key() => self.nudge_camera(-1, 0),
+            KeyCode::ArrowRight if self.modifiers.shift_key() => self.nudge_camera(1, 0),
+            KeyCode::ArrowUp if self.modifiers.shift_key() => self.nudge_camera(0, 1),
+            KeyCode::ArrowDown if self.modifiers.shift_key() => self.nudge_camera(0, -1),
+            KeyCode::Comma => self.rotate_view(ROTATION_STEP_DEGREES),
+            KeyCode::Period => self.rotate_view(-ROTATION_STEP_DEGREES),
+            KeyCode::KeyI => {
+                self.instant_jumps = !self.instant_jumps;
+                println!(
+                    "Jumps: {}",
+                    if self.instant_jumps {
+                        "instant"
+                    } else {
+                        "animated"
+                    }
+                );
+            }
+            KeyCode::KeyD if self.modifiers.shift_key() => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.smooth_coloring = 1 - uniforms.smooth_coloring;
+                    println!(
+                        "Coloring: {}",
+                        if uniforms.smooth_coloring != 0 {
+                            "smooth"
+                        } else {
+                            "banded"
+                        }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyD => {
+                if let Some(state) = &mut self.state {
+                    let has_trap_image = state.has_trap_image();
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.color_mode = match uniforms.color_mode {
+                        COLOR_MODE_ESCAPE_TIME => {
+                            println!("Coloring: distance estimate");
+                            COLOR_MODE_DISTANCE
+                        }
+                        COLOR_MODE_DISTANCE => {
+                            println!("Coloring: stripe average");
+                            COLOR_MODE_STRIPE
+                        }
+                        COLOR_MODE_STRIPE if has_trap_image => {
+                            println!("Coloring: image trap");
+                            COLOR_MODE_TRAP
+                        }
+                        _ => {
+                            println!("Coloring: escape time");
+                            COLOR_MODE_ESCAPE_TIME
+                        }
+                    };
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::F5 => {
+                if let Some(state) = &mut self.state {
+                    state.incremental_upload = !state.incremental_upload;
+                    println!(
+                        "Orbit uploads: {}",
+                        if state.incremental_upload {
+                            "incremental"
+                        } else {
+                            "full"
+                        }
+                    );
+                }
+            }
+            KeyCode::KeyY => {
+                if let Some(state) = &mut self.state {
+                    let enabled = !state.auto_exposure();
+                    state.set_auto_exposure(enabled);
+                    println!("Auto exposure: {}", if enabled { "on" } else { "off" });
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyA => {
+                if let Some(state) = &mut self.state {
+                    let enabled = !state.msaa_enabled();
+                    if state.set_msaa(enabled) {
+                        println!("MSAA: {}", if enabled { "4x" } else { "off" });
+                        self.window.as_ref().unwrap().request_redraw();
+                    } else {
+                        println!("MSAA is not supported for this window's format");
+                    }
+                }
+            }
+            KeyCode::KeyW => {
+                if let Some(state) = &mut self.state {
+                    let enabled = !state.double_single();
+                    state.set_double_single(enabled);
+                    println!(
+                        "Double-single deltas: {}",
+                        if enabled { "on" } else { "off" }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyK => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.boundary_aa = 1 - uniforms.boundary_aa;
+                    println!(
+                        "Boundary anti-aliasing: {}",
+                        if uniforms.boundary_aa != 0 {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            // Ctrl turns - and + into zoom steps, like a browser's page zoom
+            KeyCode::Minus | KeyCode::NumpadSubtract if self.modifiers.control_key() => {
+                self.step_zoom(-1)
+            }
+            KeyCode::Equal | KeyCode::NumpadAdd if self.modifiers.control_key() => {
+                self.step_zoom(1)
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => self.shift_iterations(-1),
+            KeyCode::Equal | KeyCode::NumpadAdd => self.shift_iterations(1),
+            KeyCode::BracketLeft => self.adjust_aa_samples(-1),
+            KeyCode::BracketRight => self.adjust_aa_samples(1),
+            KeyCode::KeyB => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.rebase = 1 - uniforms.rebase;
+                    println!(
+                        "Orbit rebasing: {}",
+                        if uniforms.rebase != 0 { "on" } else { "off" }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyG => {
+                self.easing = self.easing.next();
+                println!("Jump easing: {:?}", self.easing);
+            }
+            KeyCode::KeyZ => {
+                self.zoom_anchor = match self.zoom_anchor {
+                    ZoomAnchor::Cursor => ZoomAnchor::Center,
+                    ZoomAnchor::Center => ZoomAnchor::Cursor,
+                };
+                println!("Zoom anchor: {:?}", self.zoom_anchor);
+            }
+            KeyCode::KeyM => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.color_scale_mode = (uniforms.color_scale_mode + 1) % 4;
+                    let name = match uniforms.color_scale_mode {
+                        COLOR_SCALE_LOG => "log",
+                        COLOR_SCALE_SQRT => "sqrt",
+                        COLOR_SCALE_POWER => "power",
+                        _ => "linear",
+                    };
+                    println!("Color scale: {}", name);
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyP if self.modifiers.shift_key() => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.palette_id = (uniforms.palette_id + 1) % PALETTE_COUNT;
+                    println!(
+                        "Palette: {:?}",
+                        Palette::from_uniform_value(uniforms.palette_id)
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyP => {
+                if let Some(state) = &mut self.state {
+                    let fractal_state = &mut state.uniform_data.fractal_state;
+                    fractal_state.precision_mode = fractal_state.precision_mode.next();
+                    println!(
+                        "CPU precision: {:?} (using {})",
+                        fractal_state.precision_mode,
+                        fractal_state.orbit_math().name()
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyR => {
+                if let Some(state) = &mut self.state {
+                    state.force_camera_reference = true;
+                    println!("Reference reset to the camera");
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyL => {
+                if let Some(state) = &mut self.state {
+                    state.reference_locked = !state.reference_locked;
+                    println!(
+                        "Reference {}",
+                        if state.reference_locked {
+                            "locked"
+                        } else {
+                            "unlocked"
+                        }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyJ => {
+                if let Some(state) = &mut self.state {
+                    state.show_julia = !state.show_julia;
+                    println!(
+                        "Julia preview: {}",
+                        if state.show_julia { "on" } else { "off" }
+                    );
+                }
+                self.reset_julia_c();
+            }
+            KeyCode::Tab => {
+                if let Some(state) = &mut self.state {
+                    state.split_screen = !state.split_screen;
+                    println!(
+                        "Split screen: {}",
+                        if state.split_screen { "on" } else { "off" }
+                    );
+                }
+                self.reset_julia_c();
+            }
+            KeyCode::KeyT => self.toggle_timelapse(),
+            KeyCode::KeyN => {
+                if let Some(state) = &mut self.state {
+                    state.show_minimap = !state.show_minimap;
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyH => {
+                self.show_hud = !self.show_hud;
+                self.window.as_ref().unwrap().request_redraw();
+            }
+            KeyCode::F1 => {
+                if let Some(panel) = self.state.as_mut().and_then(|state| state.panel.as_mut()) {
+                    panel.visible = !panel.visible;
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::F2 => self.export_recipe(),
+            KeyCode::F3 => {
+                self.show_diagnostics = !self.show_diagnostics;
+                self.last_diagnostics_update = None;
+                self.window.as_ref().unwrap().request_redraw();
+            }
+            KeyCode::F4 => {
+                if let Some(state) = &mut self.state {
+                    let uniforms = &mut state.uniform_data.uniforms;
+                    uniforms.precision_view = 1 - uniforms.precision_view;
+                    println!(
+                        "Precision loss view: {}",
+                        if uniforms.precision_view != 0 {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    );
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            KeyCode::KeyE => self.export_image(),
+            KeyCode::KeyC => self.copy_location(),
+            KeyCode::KeyV => self.paste_location(),
+            KeyCode::KeyS if self.modifiers.control_key() => self.save_view_params(),
+            KeyCode::KeyO if self.modifiers.control_key() => self.load_view_params(),
+            KeyCode::KeyS => {
+                if let Some(state) = &mut self.state {
+                    if state.request_screenshot() {
+                        self.window.as_ref().unwrap().request_redraw();
+                    } else {
+                        eprintln!("This surface doesn't allow copying frames, use E to export");
+                    }
+                }
+            }
+            KeyCode::Digit1 | KeyCode::Numpad1 => self.jump_to_zoom_level(1),
+            KeyCode::Digit2 | KeyCode::Numpad2 => self.jump_to_zoom_level(2),
+            KeyCode::Digit3 | KeyCode::Numpad3 => self.jump_to_zoom_level(3),
+            KeyCode::Digit4 | KeyCode::Numpad4 => self.jump_to_zoom_level(4),
+            KeyCode::Digit5 | KeyCode::Numpad5 => self.jump_to_zoom_level(5),
+            KeyCode::Digit6 | KeyCode::Numpad6 => self.jump_to_zoom_level(6),
+            KeyCode::Digit7 | KeyCode::Numpad7 => self.jump_to_zoom_level(7),
+            KeyCode::Digit8 | KeyCode::Numpad8 => self.jump_to_zoom_level(8),
+            KeyCode::Digit9 | KeyCode::Numpad9 => self.jump_to_zoom_level(9),
+            KeyCode::KeyF => self.export_location(),
+            KeyCode::KeyU => self.jump_to_minibrot(),
+            KeyCode::KeyO => self.dump_orbit(),
+            KeyCode::KeyX => {
+                if let Some(state) = &mut self.state {
+                    state.set_orbit_trace(None);
+                    println!("Orbit trace cleared");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Steps the in-shader supersampling grid, between 1x1 and `MAX_AA_SAMPLES` per axis.
+    fn adjust_aa_samples(&mut self, step: i32) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        let uniforms = &mut state.uniform_data.uniforms;
+        let samples = uniforms.aa_samples.saturating_add_signed(step);
+        uniforms.aa_samples = samples.clamp(1, MAX_AA_SAMPLES);
+
+        let n = uniforms.aa_samples;
+        if n == 1 {
+            println!("Supersampling: off");
+        } else {
+            // Each sample is a full iteration, so the cost grows with the square
+            println!(
+                "Supersampling: {}x{} ({} times the GPU work per frame)",
+                n,
+                n,
+                n * n
+            );
+        }
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Doubles or halves the iteration count on top of the zoom-based budget.
+    fn shift_iterations(&mut self, step: i32) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        state.shift_iterations(step);
+        if state.iteration_override.is_some() {
+            println!("Iterations are pinned by --max-iter, the multiplier has no effect.");
+        }
+        println!(
+            "Iteration multiplier: {}",
+            iteration_multiplier(state.iteration_shift())
+        );
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Moves the camera by whole pixels on screen, for exact framing. `dy` points up.
+    fn nudge_camera(&mut self, dx: i64, dy: i64) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        Self::move_camera(state, dx as f64, dy as f64);
+        self.flight = None;
+
+        let fractal_state = &state.uniform_data.fractal_state;
+        let (x, y) = match &self.nudge {
+            Some(nudge) if nudge.zoom == fractal_state.zoom => nudge.pixels,
+            _ => (0, 0),
+        };
+        let pixels = (x + dx, y + dy);
+        self.nudge = Some(Nudge {
+            zoom: fractal_state.zoom.clone(),
+            pixels,
+        });
+
+        println!("Nudge: {:+}, {:+} px", pixels.0, pixels.1);
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Pans and zooms for the held arrow and Page Up/Down keys, by how long it's been since
+    /// the last frame so the speed doesn't depend on the frame rate.
+    fn apply_held_keys(&mut self) {
+        let now = Instant::now();
+        let elapsed = self
+            .last_key_motion
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+
+        // Shift turns the arrows into single-pixel nudges
+        let held = |key| self.held_keys.contains(&key);
+        let pan_x = held(KeyCode::ArrowRight) as i32 - held(KeyCode::ArrowLeft) as i32;
+        let pan_y = held(KeyCode::ArrowUp) as i32 - held(KeyCode::ArrowDown) as i32;
+        let (pan_x, pan_y) = if self.modifiers.shift_key() {
+            (0, 0)
+        } else {
+            (pan_x, pan_y)
+        };
+        let zoom = held(KeyCode::PageUp) as i32 - held(KeyCode::PageDown) as i32;
+
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if pan_x == 0 && pan_y == 0 && zoom == 0 {
+            self.last_key_motion = None;
+            return;
+        }
+        self.last_key_motion = Some(now);
+
+        let (width, _) = state.view_size();
+        let step = self.args.pan_speed * width as f64 * elapsed;
+        Self::move_camera(state, pan_x as f64 * step, pan_y as f64 * step);
+
+        let factor = KEY_ZOOM_PER_SECOND.powf(zoom as f64 * elapsed);
+        state.uniform_data.fractal_state.zoom *= factor;
+
+        self.flight = None;
+        self.nudge = None;
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Zooms in (`steps` > 0) or out at the center by `KEY_ZOOM_STEP` per step.
+    fn step_zoom(&mut self, steps: i32) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        state.uniform_data.fractal_state.zoom *= KEY_ZOOM_STEP.powi(steps);
+        self.flight = None;
+        self.nudge = None;
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Moves the camera against a mouse drag of `dx`, `dy` screen pixels (y down),
+    /// so the point under the cursor stays under it.
+    fn pan_view(&mut self, dx: f64, dy: f64) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        Self::move_camera(state, -dx, dy);
+        self.flight = None;
+        self.nudge = None;
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Shifts the camera by `dx`, `dy` screen pixels (y up), following the rotation.
+    fn move_camera(state: &mut WgpuState<'_>, dx: f64, dy: f64) {
+        // One pixel in complex units, in full precision so it still works deep down
+        let (_, height) = state.view_size();
+        let (step_x, step_y) = state.view_to_plane(dx, dy);
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        let pixel = Float::with_val(PRECISION, 2.0) / &fractal_state.zoom / height;
+        fractal_state
+            .camera
+            .mut_real()
+            .add_assign(Float::with_val(PRECISION, &pixel * step_x));
+        fractal_state
+            .camera
+            .mut_imag()
+            .add_assign(Float::with_val(PRECISION, &pixel * step_y));
+    }
+
+    /// Turns the view counterclockwise by `degrees` around its center.
+    fn rotate_view(&mut self, degrees: f64) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        let full_turn = std::f64::consts::TAU;
+        state.rotation = (state.rotation + degrees.to_radians()).rem_euclid(full_turn);
+        println!("Rotation: {:.0}°", state.rotation.to_degrees());
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Steps to the next render scale level, wrapping from the sharpest back to the fastest.
+    fn cycle_render_scale(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        let current = state.render_scale();
+        let next = RENDER_SCALE_LEVELS
+            .into_iter()
+            .find(|&level| level > current)
+            .unwrap_or(RENDER_SCALE_LEVELS[0]);
+        state.set_render_scale(next);
+        println!("Render scale: {}x", next);
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Whether `--re`, `--im` or `--zoom` was given.
+    fn has_start_view(&self) -> bool {
+        self.args.re.is_some() || self.args.im.is_some() || self.args.zoom.is_some()
+    }
+
+    /// Moves to the center and zoom given on the command line. Parts that weren't given
+    /// keep what the default view, image or recipe set.
+    fn apply_start_view(state: &mut WgpuState<'_>, args: &Args) -> Result<(), String> {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let re = match &args.re {
+            Some(re) => parse_float("--re", re)?,
+            None => fractal_state.camera.real().clone(),
+        };
+        let im = match &args.im {
+            Some(im) => parse_float("--im", im)?,
+            None => fractal_state.camera.imag().clone(),
+        };
+        let zoom = match &args.zoom {
+            Some(zoom) => parse_float("--zoom", zoom)?,
+            None => fractal_state.zoom.clone(),
+        };
+        if !(re.is_finite() && im.is_finite() && zoom.is_finite()) {
+            return Err("--re, --im and --zoom must be finite".to_string());
+        }
+        if zoom <= 0 {
+            return Err("--zoom must be positive".to_string());
+        }
+
+        let precision = re.prec().max(im.prec());
+        state.set_view(&RenderParams {
+            camera: Complex::with_val(precision, (&re, &im)),
+            zoom,
+            max_iter: state.iteration_override,
+        })
+    }
+
+    /// Points the Julia view at the cursor right away, or the center if it's outside the window.
+    fn reset_julia_c(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        if state.julia_viewport().is_some() {
+            let c = match self.cursor_position {
+                Some(position) => Self::screen_to_complex(state, position),
+                None => state.uniform_data.fractal_state.camera.clone(),
+            };
+            state.set_julia_c(&c);
+            self.last_julia_update = Some(Instant::now());
+        }
+        self.julia_pending = None;
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Ctrl+click: shows the Julia set of the point under the cursor in the main view,
+    /// or goes back to the Mandelbrot view it was picked from.
+    fn toggle_julia_mode(&mut self) {
+        let (Some(state), Some(position)) = (&mut self.state, self.cursor_position) else {
+            return;
+        };
+
+        // The two views don't share coordinates, so there's nothing to fly between
+        self.flight = None;
+        if let Some((camera, zoom)) = self.mandelbrot_view.take() {
+            let fractal_state = &mut state.uniform_data.fractal_state;
+            fractal_state.fractal_mode = FractalMode::Mandelbrot;
+            fractal_state.zoom.assign(&zoom);
+            fractal_state.grow_precision();
+            fractal_state.camera.assign(&camera);
+            fractal_state.reference.assign(&camera);
+            println!("Mandelbrot set");
+        } else {
+            let c = Self::screen_to_complex(state, position);
+            let fractal_state = &mut state.uniform_data.fractal_state;
+            self.mandelbrot_view = Some((fractal_state.camera.clone(), fractal_state.zoom.clone()));
+            println!(
+                "Julia set for c = {} + {}i",
+                c.real().to_f64(),
+                c.imag().to_f64()
+            );
+            // Still in Mandelbrot mode here, so this asks whether c is in the Mandelbrot set
+            if !fractal_state.contains(&c, state.uniform_data.uniforms.max_iter) {
+                println!("c is outside the Mandelbrot set, its Julia set is disconnected dust");
+            }
+            fractal_state.fractal_mode = FractalMode::Julia;
+            fractal_state.julia_c = c;
+            fractal_state.camera.assign((0.0, 0.0));
+            fractal_state.reference.assign((0.0, 0.0));
+            fractal_state.zoom.assign(JULIA_ZOOM);
+        }
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Applies the latest Julia constant from the cursor, at most every `JULIA_UPDATE_INTERVAL`.
+    fn update_julia_preview(&mut self) {
+        let (Some(state), Some(c)) = (&mut self.state, &self.julia_pending) else {
+            return;
+        };
+
+        let due = self
+            .last_julia_update
+            .is_none_or(|last| last.elapsed() >= JULIA_UPDATE_INTERVAL);
+        if due {
+            state.set_julia_c(c);
+            self.julia_pending = None;
+            self.last_julia_update = Some(Instant::now());
+        } else {
+            // Too soon, come back on a later frame
+            self.window.as_ref().unwrap().request_redraw();
+        }
+    }
+
+    /// Whether the cursor is on the strip along the top edge that moves a frameless window.
+    fn in_drag_strip(&self) -> bool {
+        let (Some(window), Some(pos)) = (&self.window, self.cursor_position) else {
+            return false;
+        };
+        self.args.frameless && pos.y < DRAG_STRIP_HEIGHT * window.scale_factor()
+    }
+
+    /// Point in the complex plane under the cursor if it's over the minimap.
+    fn minimap_point_under_cursor(&self) -> Option<Complex> {
+        let state = self.state.as_ref()?;
+        let pos = self.cursor_position?;
+        if !state.show_minimap {
+            return None;
+        }
+
+        let (width, height) = state.view_size();
+        let rect = minimap_rect(width, height);
+        minimap_point(rect, pos.x, pos.y)
+    }
+
+    /// Jumps to zoom 10^(2 * level) without moving the center.
+    fn jump_to_zoom_level(&mut self, level: u32) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let camera = state.uniform_data.fractal_state.camera.clone();
+        let zoom = Float::with_val(PRECISION, 2 * level).exp10();
+        println!("Zoom level {}: 10^{}", level, 2 * level);
+        self.jump_to(&camera, &zoom);
+    }
+
+    /// Moves the camera to a new view, either immediately or as an animated flight.
+    fn jump_to(&mut self, camera: &Complex, zoom: &Float) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        if self.instant_jumps {
+            self.flight = None;
+            state.uniform_data.fractal_state.camera.assign(camera);
+            state.uniform_data.fractal_state.zoom.assign(zoom);
+        } else {
+            let fractal_state = &state.uniform_data.fractal_state;
+            self.flight = Some(CameraFlight::new(
+                &fractal_state.camera,
+                &fractal_state.zoom,
+                camera,
+                zoom,
+                self.easing,
+            ));
+        }
+
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Finds the nucleus of a minibrot near the cursor (or the center) and flies there,
+    /// framed like the full set. Uses `--nucleus-period`, or the lowest period nearby.
+    fn jump_to_minibrot(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let fractal_state = &state.uniform_data.fractal_state;
+        let guess = match self.cursor_position {
+            Some(position) => Self::screen_to_complex(state, position),
+            None => fractal_state.camera.clone(),
+        };
+        let radius = Float::with_val(PRECISION, NUCLEUS_SEARCH_RADIUS) / &fractal_state.zoom;
+
+        let period = match self.args.nucleus_period {
+            Some(period) => period,
+            None => match ball_period(&guess, &radius, MAX_ITER) {
+                Some(period) => period,
+                None => {
+                    println!("No minibrot found near this point, try closer to the set");
+                    return;
+                }
+            },
+        };
+
+        let Some(nucleus) = find_nucleus(&guess, period) else {
+            println!("Couldn't settle on a period {} nucleus here", period);
+            return;
+        };
+
+        // The size estimate is 1 for the whole set, so this frames it like zoom 1 does the set
+        let size = minibrot_size(&nucleus, period);
+        let zoom = Float::with_val(PRECISION, size.recip_ref());
+        println!(
+            "Period {} minibrot at {} {}, zoom 10^{:.2}",
+            period,
+            nucleus.real().to_string_radix(10, Some(20)),
+            nucleus.imag().to_string_radix(10, Some(20)),
+            zoom.clone().log10().to_f64()
+        );
+        self.jump_to(&nucleus, &zoom);
+    }
+
+    /// Builds the HUD readout for the current view, with the point under the cursor if any.
+    fn hud_text(
+        state: &WgpuState<'_>,
+        fps: f32,
+        nudge: Option<(i64, i64)>,
+        cursor: Option<&Complex>,
+    ) -> String {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let log_zoom = fractal_state.zoom.clone().log10().to_f64();
+
+        // Show enough digits to tell neighbouring pixels apart at this zoom, but no more
+        // than the camera actually holds
+        let held_digits = (fractal_state.precision as f64 / BITS_PER_DECADE) as usize;
+        let digits = (log_zoom.max(0.0) as usize + 6).min(held_digits);
+
+        let gpu_limit = if state.past_gpu_zoom_limit() {
+            format!("10^{:.1} (exceeded)", state.gpu_zoom_limit())
+        } else {
+            format!("10^{:.1}", state.gpu_zoom_limit())
+        };
+
+        let math = if state.precision_limited {
+            format!(
+                "{} (precision-limited at {} bits)",
+                fractal_state.orbit_math().name(),
+                state.max_orbit_precision
+            )
+        } else {
+            fractal_state.orbit_math().name().to_string()
+        };
+
+        let mut iterations = match state.iteration_shift() {
+            0 => state.uniform_data.uniforms.max_iter.to_string(),
+            shift => format!(
+                "{} ({})",
+                state.uniform_data.uniforms.max_iter,
+                iteration_multiplier(shift)
+            ),
+        };
+        if state.is_refining() {
+            iterations.push_str(", refining");
+        }
+        let reference = if state.reference_locked {
+            "locked"
+        } else if state.manual_reference {
+            "manual"
+        } else {
+            "auto"
+        };
+        let reference = if state.is_computing_orbit() {
+            format!("{}, computing", reference)
+        } else {
+            reference.to_string()
+        };
+
+        let nudge = match nudge {
+            Some((x, y)) => format!("\nNudge: {:+}, {:+} px", x, y),
+            None => String::new(),
+        };
+
+        // Same digits as the center, enough to tell the pixel under the cursor apart
+        let cursor = match cursor {
+            Some(point) => format!(
+                "\nCursor: {:.*}, {:.*}",
+                digits,
+                point.real(),
+                digits,
+                point.imag()
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            "Re: {:.*}\nIm: {:.*}{}{}\nZoom: 10^{:.2}\nGPU limit: {}\nIterations: {}\nMath: {}\nReference: {}\nRotation: {:.0}°\nScale: {}x\nExposure: {}\nFPS: {:.0}",
+            digits,
+            fractal_state.camera.real(),
+            digits,
+            fractal_state.camera.imag(),
+            nudge,
+            cursor,
+            log_zoom,
+            gpu_limit,
+            iterations,
+            math,
+            reference,
+            state.rotation.to_degrees(),
+            state.render_scale(),
+            if state.auto_exposure() {
+                "auto"
+            } else {
+                "fixed"
+            },
+            fps
+        )
+    }
+
+    /// Builds the performance and correctness readout for the diagnostics overlay.
+    fn diagnostics_text(state: &WgpuState<'_>, fps: f32, update_time: Duration) -> String {
+        let fractal_state = &state.uniform_data.fractal_state;
+        let uniforms = &state.uniform_data.uniforms;
+        let math = fractal_state.orbit_math();
+        // f64 has a fixed mantissa, the orbit precision setting only applies to rug
+        let precision_bits = if math.name() == "f64" {
+            53
+        } else {
+            fractal_state.orbit_precision
+        };
+
+        let mut text = format!(
+            "Frame: {:.1} ms ({:.0} FPS)\nCPU update: {:.1} ms\nGPU: {:.1} ms\nPrecision: {} bits ({})\nTarget iterations: {}\nReference escape: {}/{}\nGlitches: {:.1}%\nOrbit upload: {:.1} KB ({})",
+            1000.0 / fps.max(1e-3),
+            fps,
+            update_time.as_secs_f64() * 1000.0,
+            state.gpu_time().as_secs_f64() * 1000.0,
+            precision_bits,
+            math.name(),
+            uniforms.max_iter,
+            uniforms.iter_count,
+            uniforms.max_iter,
+            state.glitch_fraction * 100.0,
+            state.orbit_upload_bytes() as f64 / 1024.0,
+            if state.incremental_upload {
+                "incremental"
+            } else {
+                "full"
+            }
+        );
+        if let Some(capabilities) = &state.capabilities {
+            text.push_str(&format!("\n\n{}", capabilities));
+        }
+        text
+    }
+
+    /// Renders the current view offscreen at the export size and saves it as a PNG.
+    fn export_image(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        // The aspect comes from the output size, the supersampled render has the same shape
+        let factor = self.args.export_aa;
+        let (width, height) = self
+            .args
+            .export_size
+            .unwrap_or((state.config.width, state.config.height));
+        let aspect = width as f32 / height as f32;
+        let (render_width, render_height) =
+            (width.saturating_mul(factor), height.saturating_mul(factor));
+        let max_dim = state.max_texture_dimension();
+        if factor > 1 && (render_width > max_dim || render_height > max_dim) {
+            eprintln!(
+                "{}x{} at {}x export AA exceeds the GPU texture limit of {}, nothing exported",
+                width, height, factor, max_dim
+            );
+            return;
+        }
+
+        let (path, saved) = match self.args.bit_depth {
+            BitDepth::Eight => {
+                let image = downsample(
+                    &state.render_to_image(render_width, render_height, aspect),
+                    factor,
+                );
+                let path = export_path(image.width(), image.height());
+                let saved = save_png(&image, &path, &ViewMetadata::from_state(state));
+                (path, saved)
+            }
+            BitDepth::Sixteen => {
+                let image = downsample(
+                    &state.render_to_image_16(render_width, render_height, aspect),
+                    factor,
+                );
+                let path = export_path(image.width(), image.height());
+                let saved = save_png_16(&image, &path, &ViewMetadata::from_state(state));
+                (path, saved)
+            }
+        };
+
+        match saved {
+            Ok(_) => println!("Exported image to {}", path.display()),
+            Err(e) => eprintln!("Failed to export image to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Saves the current location for another fractal program, see `--location-format`.
+    fn export_location(&self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let format = self.args.location_format;
+        let (width, height) = self
+            .args
+            .export_size
+            .unwrap_or((state.config.width, state.config.height));
+        let location = format_location(state, format, width, height);
+
+        let path = format!("location_{}.{}", timestamp(), format.extension());
+        match fs::write(&path, &location) {
+            Ok(_) => println!("Saved location to {}:\n{}", path, location),
+            Err(e) => eprintln!("Failed to save location to {}: {}", path, e),
+        }
+    }
+
+    /// C: copies the view as a location string, see `HighPrecisionState::to_location_string`.
+    fn copy_location(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let location = state
+            .uniform_data
+            .fractal_state
+            .to_location_string(state.uniform_data.uniforms.max_iter);
+        let copied = self.clipboard().and_then(|clipboard| {
+            clipboard
+                .set_text(location.as_str())
+                .map_err(|e| e.to_string())
+        });
+        match copied {
+            Ok(()) => println!("Copied the location: {}", location),
+            Err(e) => eprintln!("Failed to copy the location: {}", e),
+        }
+    }
+
+    /// V: jumps to the location string on the clipboard. The iteration count is pinned
+    /// only if it differs from the zoom-based budget, so copied default views stay automatic.
+    fn paste_location(&mut self) {
+        let pasted = self
+            .clipboard()
+            .and_then(|clipboard| clipboard.get_text().map_err(|e| e.to_string()))
+            .and_then(|text| HighPrecisionState::from_location_string(&text));
+        let (view, iterations) = match pasted {
+            Ok(pasted) => pasted,
+            Err(e) => {
+                eprintln!("Failed to paste a location: {}", e);
+                return;
+            }
+        };
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        self.flight = None;
+        let pinned = iterations != target_iterations(&view.zoom);
+        let params = RenderParams {
+            camera: view.camera,
+            zoom: view.zoom,
+            max_iter: pinned.then_some(iterations),
+        };
+        match state.set_view(&params) {
+            Ok(()) => {
+                println!("Pasted location, {} iterations", iterations);
+                self.window.as_ref().unwrap().request_redraw();
+            }
+            Err(e) => eprintln!("Failed to paste a location: {}", e),
+        }
+    }
+
+    /// The system clipboard, opened on first use.
+    fn clipboard(&mut self) -> Result<&mut Clipboard, String> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+
+    /// Ctrl+S: writes the view to `--view-file` as JSON, see `ViewParams`.
+    fn save_view_params(&self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let path = &self.args.view_file;
+        match ViewParams::from_state(state).save(path) {
+            Ok(()) => println!("Saved the view to {}", path.display()),
+            Err(e) => eprintln!("Failed to save the view to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Ctrl+O: jumps to the view stored in `--view-file`.
+    fn load_view_params(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        let path = &self.args.view_file;
+        let loaded = ViewParams::load(path).and_then(|params| params.apply(state));
+        match loaded {
+            Ok(()) => {
+                self.flight = None;
+                println!("Loaded the view from {}", path.display());
+                self.window.as_ref().unwrap().request_redraw();
+            }
+            Err(e) => eprintln!("Failed to load the view from {}: {}", path.display(), e),
+        }
+    }
+
+    /// Saves the current view and settings as a recipe for `--recipe`, sized like an export.
+    fn export_recipe(&self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let (width, height) = self
+            .args
+            .export_size
+            .unwrap_or((state.config.width, state.config.height));
+        let recipe = Recipe::from_state(
+            state,
+            width,
+            height,
+            self.args.bit_depth,
+            self.args.trap_image.as_deref(),
+        );
+
+        let path = PathBuf::from(format!("recipe_{}.toml", timestamp()));
+        match recipe.save(&path) {
+            Ok(()) => println!("Saved recipe to {}", path.display()),
+            Err(e) => eprintln!("Failed to save recipe to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Renders the current view at the export size, or the window size if none is set.
+    fn render_export(
+        state: &mut WgpuState<'_>,
+        export_size: Option<(u32, u32)>,
+    ) -> image::RgbaImage {
+        // The export shape is independent of the window, so derive aspect from the output size
+        let (width, height) = export_size.unwrap_or((state.config.width, state.config.height));
+        let aspect = width as f32 / height as f32;
+
+        state.render_to_image(width, height, aspect)
+    }
+
+    fn toggle_timelapse(&mut self) {
+        if let Some(timelapse) = self.timelapse.take() {
+            println!(
+                "Timelapse stopped, frames are in {}",
+                timelapse.dir.display()
+            );
+            return;
+        }
+
+        // Without any trigger configured, fall back to a fixed interval
+        let mut interval = self.args.timelapse_interval.map(Duration::from_secs);
+        if interval.is_none() && self.args.timelapse_decades.is_none() {
+            interval = Some(DEFAULT_TIMELAPSE_INTERVAL);
+        }
+
+        let dir = PathBuf::from(format!("timelapse_{}", timestamp()));
+        match Timelapse::start(dir, interval, self.args.timelapse_decades) {
+            Ok(timelapse) => {
+                println!("Timelapse recording to {}", timelapse.dir.display());
+                self.timelapse = Some(timelapse);
+            }
+            Err(e) => eprintln!("Failed to start timelapse: {}", e),
+        }
+    }
+
+    /// Saves the next timelapse frame if one is due.
+    fn capture_timelapse_frame(&mut self) {
+        let (Some(timelapse), Some(state)) = (&mut self.timelapse, &mut self.state) else {
+            return;
+        };
+
+        let log_zoom = state
+            .uniform_data
+            .fractal_state
+            .zoom
+            .clone()
+            .log10()
+            .to_f64();
+        if !timelapse.is_due(log_zoom) {
+            return;
+        }
+
+        let image = Self::render_export(state, self.args.export_size);
+        let path = timelapse.next_frame(log_zoom);
+        // A gap in the numbered frames would go unnoticed until assembly, so stop instead
+        if let Err(e) = save_png(&image, &path, &ViewMetadata::from_state(state)) {
+            eprintln!(
+                "Failed to save timelapse frame {}: {}. Timelapse stopped.",
+                path.display(),
+                e
+            );
+            self.timelapse = None;
+        }
+    }
+
+    /// Converts a window pixel position to its point in the complex plane.
+    /// Mirrors the shader mapping: NDC with x scaled by aspect, rotated, divided by zoom.
+    fn screen_to_complex(state: &WgpuState<'_>, pos: PhysicalPosition<f64>) -> Complex {
+        let (width, height) = state.view_size();
+        let (width, height) = (width as f64, height as f64);
+        let aspect = width / height;
+
+        let ndc_x = (pos.x / width) * 2.0 - 1.0;
+        let ndc_y = 1.0 - (pos.y / height) * 2.0;
+        state
+            .uniform_data
+            .fractal_state
+            .point_at(ndc_x, ndc_y, aspect, state.rotation)
+    }
+
+    /// The rectangle between two pixel positions as NDC [x0, y0, x1, y1].
+    fn selection_ndc(
+        state: &WgpuState<'_>,
+        a: PhysicalPosition<f64>,
+        b: PhysicalPosition<f64>,
+    ) -> [f32; 4] {
+        let (width, height) = state.view_size();
+        let (width, height) = (width as f64, height as f64);
+        let to_ndc = |p: PhysicalPosition<f64>| {
+            (
+                ((p.x / width) * 2.0 - 1.0) as f32,
+                (1.0 - (p.y / height) * 2.0) as f32,
+            )
+        };
+
+        let (x0, y0) = to_ndc(a);
+        let (x1, y1) = to_ndc(b);
+        [x0, y0, x1, y1]
+    }
+
+    /// Ends a left drag. If the box is big enough, zoom so it fills the window.
+    fn finish_box_zoom(&mut self) {
+        let start = self.drag_start.take();
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        state.selection = None;
+
+        let (Some(start), Some(end)) = (start, self.cursor_position) else {
+            return;
+        };
+
+        // Ignore clicks and accidental tiny drags
+        if (end.x - start.x).abs() < 5.0 || (end.y - start.y).abs() < 5.0 {
+            self.window.as_ref().unwrap().request_redraw();
+            return;
+        }
+
+        // New center is the middle of the box, in full precision
+        let middle = PhysicalPosition::new((start.x + end.x) * 0.5, (start.y + end.y) * 0.5);
+        let center = Self::screen_to_complex(state, middle);
+
+        // The box is measured on screen, so the fit doesn't depend on the rotation.
+        // Take the smaller zoom so the whole box stays visible when its shape doesn't match.
+        let (width, height) = state.view_size();
+        let scale_x = width as f64 / (end.x - start.x).abs();
+        let scale_y = height as f64 / (end.y - start.y).abs();
+        let new_zoom = Float::with_val(
+            PRECISION,
+            &state.uniform_data.fractal_state.zoom * scale_x.min(scale_y),
+        );
+
+        let fractal_state = &mut state.uniform_data.fractal_state;
+        fractal_state.camera.assign(&center);
+        fractal_state.zoom = new_zoom;
+        self.flight = None;
+
+        println!(
+            "Box zoom: 10^{:.2}",
+            fractal_state.zoom.clone().log10().to_f32()
+        );
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Overlays the orbit of the point under the cursor.
+    fn trace_orbit_under_cursor(&mut self) {
+        let (Some(state), Some(position)) = (&mut self.state, self.cursor_position) else {
+            return;
+        };
+
+        let c = Self::screen_to_complex(state, position);
+        let points = state.set_orbit_trace(Some(&c));
+        println!(
+            "Tracing orbit of ({}, {}): {} points",
+            c.real().to_f64(),
+            c.imag().to_f64(),
+            points
+        );
+        self.window.as_ref().unwrap().request_redraw();
+    }
+
+    /// Saves the current reference orbit as CSV for debugging perturbation.
+    fn dump_orbit(&self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        let path = format!("orbit_{}.csv", timestamp());
+        match state.dump_orbit(Path::new(&path)) {
+            Ok(_) => println!("Dumped reference orbit to {}", path),
+            Err(e) => eprintln!("Failed to dump orbit to {}: {}", path, e),
+        }
+    }
+
     fn update_camera(
         state: &mut WgpuState<'_>,
         cursor_position: Option<PhysicalPosition<f64>>,
@@ -122,15 +1940,14 @@ impl<'a> App<'a> {
 
         // Handle "Zoom Towards Cursor"
         if let Some(pos) = cursor_position {
-            let width = state.config.width as f64;
-            let height = state.config.height as f64;
+            let (width, height) = state.view_size();
+            let (width, height) = (width as f64, height as f64);
             let aspect = width / height;
 
             let ndc_x = (pos.x / width) * 2.0 - 1.0;
             let ndc_y = 1.0 - (pos.y / height) * 2.0;
 
-            let mouse_vec_x = ndc_x * aspect;
-            let mouse_vec_y = ndc_y;
+            let (mouse_vec_x, mouse_vec_y) = state.view_to_plane(ndc_x * aspect, ndc_y);
 
             // Calculate "Zoom Difference"
             let one = Float::with_val(128, 1.0);
@@ -159,3 +1976,60 @@ impl<'a> App<'a> {
         state.uniform_data.fractal_state.zoom = new_zoom;
     }
 }
+
+/// File name for an image exported with the E key.
+fn export_path(width: u32, height: u32) -> PathBuf {
+    PathBuf::from(format!(
+        "mandelbrot_{}x{}_{}.png",
+        width,
+        height,
+        timestamp()
+    ))
+}
+
+/// File name for a screenshot (S key), with the center and zoom so it's findable again.
+/// Enough digits to place the view, the PNG metadata has the full precision.
+fn screenshot_path(state: &WgpuState<'_>) -> PathBuf {
+    let fractal_state = &state.uniform_data.fractal_state;
+    let log_zoom = fractal_state.zoom.clone().log10().to_f64();
+    let digits = (log_zoom.max(0.0) as usize + 3).min(40);
+    PathBuf::from(format!(
+        "screenshot_{}_re{:.*}_im{:.*}_zoom1e{:.2}.png",
+        timestamp(),
+        digits,
+        fractal_state.camera.real(),
+        digits,
+        fractal_state.camera.imag(),
+        log_zoom
+    ))
+}
+
+/// Seconds since the Unix epoch, used to make output filenames unique
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Shows an iteration shift as a factor, like "x4" or "x1/4".
+pub fn iteration_multiplier(shift: i32) -> String {
+    if shift >= 0 {
+        format!("x{}", 1u32 << shift)
+    } else {
+        format!("x1/{}", 1u32 << -shift)
+    }
+}
+
+/// Keys handled every frame while held instead of once per press.
+fn is_motion_key(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ArrowLeft
+            | KeyCode::ArrowRight
+            | KeyCode::ArrowUp
+            | KeyCode::ArrowDown
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+    )
+}